@@ -16,6 +16,8 @@ fn main() {
                 "page",
                 "page_size",
                 "desc",
+                "cursor",
+                "match_mode",
             ],
         )
         .with_builder_option("reservation.ReservationQuery", &["start", "end"])
@@ -24,7 +26,7 @@ fn main() {
 
     fs::remove_file("src/pb/google.protobuf.rs").unwrap_or_default();
 
-    Command::new("cargo").args(&["fmt"]).output().unwrap();
+    Command::new("cargo").args(["fmt"]).output().unwrap();
 
     println!("cargo:rerun-if-changed=protos/reservation.proto");
 }
@@ -52,7 +54,7 @@ impl BuilderExt for Builder {
     fn with_builder_into(self, path: &str, fields: &[&str]) -> Self {
         fields.iter().fold(self, |acc, field| {
             acc.field_attribute(
-                &format!("{}.{}", path, field),
+                format!("{}.{}", path, field),
                 "#[builder(setter(into), default)]",
             )
         })
@@ -61,7 +63,7 @@ impl BuilderExt for Builder {
     fn with_builder_option(self, path: &str, fields: &[&str]) -> Self {
         fields.iter().fold(self, |acc, field| {
             acc.field_attribute(
-                &format!("{}.{}", path, field),
+                format!("{}.{}", path, field),
                 "#[builder(setter(into, strip_option))]",
             )
         })
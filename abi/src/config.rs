@@ -0,0 +1,380 @@
+use std::{env, fs, path::Path, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgConnectOptions;
+
+use crate::{Error, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+
+/// prefix of the environment variables overriding the config, e.g. `RESERVATION_DB_HOST`
+pub const ENV_PREFIX: &str = "RESERVATION_";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    pub db: DbConfig,
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub pagination: PaginationConfig,
+    #[serde(default)]
+    pub jobs: JobsConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DbConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub dbname: String,
+    #[serde(default = "default_pool_size")]
+    pub max_connections: u32,
+    /// to apply the pending migrations when the server starts, otherwise they fail the start
+    #[serde(default)]
+    pub auto_migrate: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+/// limits of the page size of the queries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaginationConfig {
+    /// the page size if it is not given
+    #[serde(default = "default_page_size")]
+    pub default_page_size: i32,
+    /// a page could not be larger than this
+    #[serde(default = "default_max_page_size")]
+    pub max_page_size: i32,
+}
+
+/// background jobs run by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobsConfig {
+    /// changes in `rsvp.reservation_changes` older than this are pruned
+    #[serde(default = "default_change_retention_secs")]
+    pub change_retention_secs: u64,
+    /// how often to prune the changes
+    #[serde(default = "default_change_compaction_interval_secs")]
+    pub change_compaction_interval_secs: u64,
+    /// how often to cancel the pending reservations whose hold has expired
+    #[serde(default = "default_hold_reaper_interval_secs")]
+    pub hold_reaper_interval_secs: u64,
+    /// idempotency keys older than this are pruned
+    #[serde(default = "default_idempotency_key_retention_secs")]
+    pub idempotency_key_retention_secs: u64,
+    /// how often to prune the idempotency keys
+    #[serde(default = "default_idempotency_key_pruning_interval_secs")]
+    pub idempotency_key_pruning_interval_secs: u64,
+}
+
+fn default_pool_size() -> u32 {
+    5
+}
+
+fn default_page_size() -> i32 {
+    DEFAULT_PAGE_SIZE
+}
+
+fn default_max_page_size() -> i32 {
+    MAX_PAGE_SIZE
+}
+
+fn default_change_retention_secs() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_change_compaction_interval_secs() -> u64 {
+    60 * 60
+}
+
+fn default_hold_reaper_interval_secs() -> u64 {
+    60
+}
+
+fn default_idempotency_key_retention_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_idempotency_key_pruning_interval_secs() -> u64 {
+    60 * 60
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            default_page_size: default_page_size(),
+            max_page_size: default_max_page_size(),
+        }
+    }
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            change_retention_secs: default_change_retention_secs(),
+            change_compaction_interval_secs: default_change_compaction_interval_secs(),
+            hold_reaper_interval_secs: default_hold_reaper_interval_secs(),
+            idempotency_key_retention_secs: default_idempotency_key_retention_secs(),
+            idempotency_key_pruning_interval_secs: default_idempotency_key_pruning_interval_secs(),
+        }
+    }
+}
+
+impl Config {
+    /// to load the config from a yaml or toml file, then to override it by the environment
+    pub fn load(filename: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut config = Self::load_file(filename)?;
+        config.override_with(env::vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+    /// to load the config from the file only, a `.toml` file is parsed as toml, others as yaml
+    pub fn load_file(filename: impl AsRef<Path>) -> Result<Self, Error> {
+        let filename = filename.as_ref();
+        let config = fs::read_to_string(filename).map_err(Error::ConfigReadError)?;
+        match filename.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&config).map_err(|e| Error::ConfigParseError(e.into())),
+            _ => serde_yaml::from_str(&config).map_err(|e| Error::ConfigParseError(e.into())),
+        }
+    }
+    /// to override the fields by the variables named after them with `ENV_PREFIX`,
+    /// e.g. `RESERVATION_DB_HOST` or `RESERVATION_JOBS_HOLD_REAPER_INTERVAL_SECS`.
+    /// other variables are ignored.
+    pub fn override_with(
+        &mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<(), Error> {
+        for (name, value) in vars {
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            match key {
+                "DB_HOST" => self.db.host = value,
+                "DB_PORT" => self.db.port = parse_var(&name, &value)?,
+                "DB_USER" => self.db.user = value,
+                "DB_PASSWORD" => self.db.password = value,
+                "DB_DBNAME" => self.db.dbname = value,
+                "DB_MAX_CONNECTIONS" => self.db.max_connections = parse_var(&name, &value)?,
+                "DB_AUTO_MIGRATE" => self.db.auto_migrate = parse_var(&name, &value)?,
+                "SERVER_HOST" => self.server.host = value,
+                "SERVER_PORT" => self.server.port = parse_var(&name, &value)?,
+                "PAGINATION_DEFAULT_PAGE_SIZE" => {
+                    self.pagination.default_page_size = parse_var(&name, &value)?
+                }
+                "PAGINATION_MAX_PAGE_SIZE" => {
+                    self.pagination.max_page_size = parse_var(&name, &value)?
+                }
+                "JOBS_CHANGE_RETENTION_SECS" => {
+                    self.jobs.change_retention_secs = parse_var(&name, &value)?
+                }
+                "JOBS_CHANGE_COMPACTION_INTERVAL_SECS" => {
+                    self.jobs.change_compaction_interval_secs = parse_var(&name, &value)?
+                }
+                "JOBS_HOLD_REAPER_INTERVAL_SECS" => {
+                    self.jobs.hold_reaper_interval_secs = parse_var(&name, &value)?
+                }
+                "JOBS_IDEMPOTENCY_KEY_RETENTION_SECS" => {
+                    self.jobs.idempotency_key_retention_secs = parse_var(&name, &value)?
+                }
+                "JOBS_IDEMPOTENCY_KEY_PRUNING_INTERVAL_SECS" => {
+                    self.jobs.idempotency_key_pruning_interval_secs = parse_var(&name, &value)?
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    /// to check the values which could not be told by their types
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.db.max_connections == 0 {
+            return Err(Error::InvalidConfig("db.max_connections".into()));
+        }
+        self.pagination.validate()?;
+        self.jobs.validate()
+    }
+}
+
+fn parse_var<T: FromStr>(name: &str, value: &str) -> Result<T, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::InvalidConfig(format!("{}={}", name, value)))
+}
+
+impl PaginationConfig {
+    /// the default page size should be positive and within the max page size
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_page_size < 1 {
+            return Err(Error::InvalidConfig("pagination.max_page_size".into()));
+        }
+        if !(1..=self.max_page_size).contains(&self.default_page_size) {
+            return Err(Error::InvalidConfig("pagination.default_page_size".into()));
+        }
+        Ok(())
+    }
+}
+
+impl DbConfig {
+    /// options to connect to the database, the user and password are taken as they are
+    pub fn connect_options(&self) -> PgConnectOptions {
+        let options = PgConnectOptions::new()
+            .host(&self.host)
+            .port(self.port)
+            .username(&self.user)
+            .database(&self.dbname);
+        if self.password.is_empty() {
+            options
+        } else {
+            options.password(&self.password)
+        }
+    }
+}
+
+impl JobsConfig {
+    /// the intervals should be positive, a job could not be run all the time
+    pub fn validate(&self) -> Result<(), Error> {
+        let intervals = [
+            (
+                "jobs.change_compaction_interval_secs",
+                self.change_compaction_interval_secs,
+            ),
+            (
+                "jobs.hold_reaper_interval_secs",
+                self.hold_reaper_interval_secs,
+            ),
+        ];
+        match intervals.into_iter().find(|(_, secs)| *secs == 0) {
+            Some((name, _)) => Err(Error::InvalidConfig(name.into())),
+            None => Ok(()),
+        }
+    }
+    pub fn change_retention(&self) -> Duration {
+        Duration::from_secs(self.change_retention_secs)
+    }
+    pub fn change_compaction_interval(&self) -> Duration {
+        Duration::from_secs(self.change_compaction_interval_secs)
+    }
+    pub fn hold_reaper_interval(&self) -> Duration {
+        Duration::from_secs(self.hold_reaper_interval_secs)
+    }
+    pub fn idempotency_key_retention(&self) -> Duration {
+        Duration::from_secs(self.idempotency_key_retention_secs)
+    }
+    pub fn idempotency_key_pruning_interval(&self) -> Duration {
+        Duration::from_secs(self.idempotency_key_pruning_interval_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn config_should_be_loaded() {
+        let config = Config::load("../service/fixtures/config.yml").unwrap();
+        assert_eq!(
+            config,
+            Config {
+                db: DbConfig {
+                    host: "localhost".to_string(),
+                    port: 5432,
+                    user: "postgres".to_string(),
+                    password: "postgres".to_string(),
+                    dbname: "reservation".to_string(),
+                    max_connections: 5,
+                    auto_migrate: false,
+                },
+                server: ServerConfig {
+                    host: "0.0.0.0".to_string(),
+                    port: 50051,
+                },
+                pagination: PaginationConfig {
+                    default_page_size: 20,
+                    max_page_size: 200,
+                },
+                jobs: JobsConfig {
+                    change_retention_secs: 86400,
+                    change_compaction_interval_secs: 3600,
+                    hold_reaper_interval_secs: 30,
+                    idempotency_key_retention_secs: 43200,
+                    idempotency_key_pruning_interval_secs: 600,
+                },
+            }
+        );
+    }
+    #[test]
+    fn zero_job_interval_should_be_rejected() {
+        let mut config = Config::load_file("../service/fixtures/config.yml").unwrap();
+        config
+            .override_with([(
+                "RESERVATION_JOBS_HOLD_REAPER_INTERVAL_SECS".to_owned(),
+                "0".to_owned(),
+            )])
+            .unwrap();
+        assert_eq!(
+            config.validate(),
+            Err(Error::InvalidConfig(
+                "jobs.hold_reaper_interval_secs".into()
+            ))
+        );
+        config.jobs = JobsConfig {
+            change_compaction_interval_secs: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(Error::InvalidConfig(
+                "jobs.change_compaction_interval_secs".into()
+            ))
+        );
+    }
+    #[test]
+    fn unparsable_config_should_keep_the_parse_error() {
+        let filename = env::temp_dir().join("reservation-invalid-config.toml");
+        fs::write(&filename, "[db\nhost = ").unwrap();
+        let err = Config::load_file(&filename).unwrap_err();
+        assert!(matches!(err, Error::ConfigParseError(_)));
+        assert!(std::error::Error::source(&err).is_some());
+        let err = Config::load_file("../service/fixtures/missing.yml").unwrap_err();
+        assert!(matches!(err, Error::ConfigReadError(_)));
+    }
+    #[test]
+    fn toml_config_should_be_the_same_as_yaml() {
+        let yaml = Config::load_file("../service/fixtures/config.yml").unwrap();
+        let toml = Config::load_file("../service/fixtures/config.toml").unwrap();
+        assert_eq!(toml, yaml);
+    }
+    #[test]
+    fn env_should_override_config() {
+        let mut config = Config::load_file("../service/fixtures/config.yml").unwrap();
+        let vars = [
+            ("RESERVATION_DB_HOST", "db.internal"),
+            ("RESERVATION_SERVER_PORT", "8080"),
+            ("RESERVATION_DB_AUTO_MIGRATE", "true"),
+            ("RESERVATION_PAGINATION_MAX_PAGE_SIZE", "500"),
+            (
+                "RESERVATION_JOBS_IDEMPOTENCY_KEY_PRUNING_INTERVAL_SECS",
+                "120",
+            ),
+            ("RESERVATION_UNKNOWN", "ignored"),
+            ("DB_HOST", "ignored"),
+        ];
+        config
+            .override_with(vars.map(|(k, v)| (k.to_owned(), v.to_owned())))
+            .unwrap();
+        assert_eq!(config.db.host, "db.internal");
+        assert_eq!(config.server.port, 8080);
+        assert!(config.db.auto_migrate);
+        assert_eq!(config.pagination.max_page_size, 500);
+        assert_eq!(config.jobs.idempotency_key_pruning_interval_secs, 120);
+        let err = config
+            .override_with([("RESERVATION_DB_PORT".to_owned(), "db".to_owned())])
+            .unwrap_err();
+        assert_eq!(err, Error::InvalidConfig("RESERVATION_DB_PORT=db".into()));
+        config.pagination.default_page_size = 1000;
+        assert_eq!(
+            config.validate(),
+            Err(Error::InvalidConfig("pagination.default_page_size".into()))
+        );
+    }
+}
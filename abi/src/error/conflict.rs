@@ -1,17 +1,35 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use regex::Regex;
-use std::{collections::HashMap, convert::Infallible, str::FromStr};
+use std::{collections::HashMap, convert::Infallible, fmt, str::FromStr};
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+use crate::{
+    convert_to_timestamp, convert_to_utc_time, get_timespan, BatchItemConflict, ConflictDetails,
+    ConflictWindow, Reservation,
+};
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum ReservationConflictInfo {
     Parsed(ReservationConflict),
     Unparsed(String),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// conflict of one of the reservations in a batch
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchConflict {
+    /// position of the reservation in the batch
+    pub index: usize,
+    pub info: ReservationConflictInfo,
+    /// positions of the earlier reservations in the batch saturating the capacity with it
+    pub blocking_indexes: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct ReservationConflict {
     pub new: ReservationWindow,
     pub old: ReservationWindow,
+    /// the existing reservations saturating the capacity of the resource
+    /// during the new one, if they have been looked up
+    pub blocking: Vec<Reservation>,
 }
 
 pub struct ParsedInfo {
@@ -37,6 +55,139 @@ impl FromStr for ReservationConflictInfo {
     }
 }
 
+/// to format the conflict the same way as the detail of the exclusion constraint violation,
+/// so it could be parsed back.
+impl fmt::Display for ReservationConflictInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReservationConflictInfo::Parsed(conflict) => write!(f, "{}", conflict),
+            ReservationConflictInfo::Unparsed(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl fmt::Display for ReservationConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Key (resource_id, timespan)={} conflicts with existing key (resource_id, timespan)={}.",
+            self.new, self.old
+        )
+    }
+}
+
+impl fmt::Display for ReservationWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, [\"{}\",\"{}\"))",
+            self.rid,
+            format_datetime(&self.start),
+            format_datetime(&self.end)
+        )
+    }
+}
+
+impl ReservationConflict {
+    /// id of the existing reservation reported by the database,
+    /// or the first of the saturating ones if it is not found.
+    pub fn blocking_id(&self) -> Option<&str> {
+        let timespan = get_timespan(
+            Some(&convert_to_timestamp(self.old.start)),
+            Some(&convert_to_timestamp(self.old.end)),
+        );
+        self.blocking
+            .iter()
+            .find(|rsvp| rsvp.get_timespan() == timespan)
+            .or_else(|| self.blocking.first())
+            .map(|rsvp| rsvp.id.as_str())
+    }
+}
+
+impl From<&ReservationConflictInfo> for ConflictDetails {
+    fn from(info: &ReservationConflictInfo) -> Self {
+        match info {
+            ReservationConflictInfo::Parsed(conflict) => Self {
+                resource_id: conflict.new.rid.clone(),
+                new: Some((&conflict.new).into()),
+                old: Some((&conflict.old).into()),
+                blocking_reservation_id: conflict.blocking_id().unwrap_or_default().to_owned(),
+                raw: String::new(),
+                blocking_reservations: conflict.blocking.clone(),
+            },
+            ReservationConflictInfo::Unparsed(s) => Self {
+                raw: s.clone(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<ConflictDetails> for ReservationConflictInfo {
+    fn from(details: ConflictDetails) -> Self {
+        match (details.new, details.old) {
+            (Some(new), Some(old)) if details.raw.is_empty() => {
+                ReservationConflictInfo::Parsed(ReservationConflict {
+                    new: new.into(),
+                    old: old.into(),
+                    blocking: details.blocking_reservations,
+                })
+            }
+            _ => ReservationConflictInfo::Unparsed(details.raw),
+        }
+    }
+}
+
+impl From<&BatchConflict> for BatchItemConflict {
+    fn from(conflict: &BatchConflict) -> Self {
+        Self {
+            index: conflict.index as i32,
+            details: Some((&conflict.info).into()),
+            blocking_indexes: conflict
+                .blocking_indexes
+                .iter()
+                .map(|i| *i as i32)
+                .collect(),
+        }
+    }
+}
+
+impl From<BatchItemConflict> for BatchConflict {
+    fn from(conflict: BatchItemConflict) -> Self {
+        Self {
+            index: conflict.index as usize,
+            info: conflict.details.unwrap_or_default().into(),
+            blocking_indexes: conflict
+                .blocking_indexes
+                .into_iter()
+                .map(|i| i as usize)
+                .collect(),
+        }
+    }
+}
+
+impl From<&ReservationWindow> for ConflictWindow {
+    fn from(window: &ReservationWindow) -> Self {
+        Self {
+            resource_id: window.rid.clone(),
+            start: Some(convert_to_timestamp(window.start)),
+            end: Some(convert_to_timestamp(window.end)),
+        }
+    }
+}
+
+impl From<ConflictWindow> for ReservationWindow {
+    fn from(window: ConflictWindow) -> Self {
+        let start = window.start.unwrap_or_default();
+        let end = window.end.unwrap_or_default();
+        Self {
+            rid: window.resource_id,
+            start: convert_to_utc_time(&start),
+            end: convert_to_utc_time(&end),
+        }
+    }
+}
+
 impl TryFrom<ParsedInfo> for ReservationConflict {
     type Error = ();
 
@@ -44,6 +195,7 @@ impl TryFrom<ParsedInfo> for ReservationConflict {
         Ok(Self {
             new: value.new.try_into()?,
             old: value.old.try_into()?,
+            blocking: vec![],
         })
     }
 }
@@ -97,10 +249,17 @@ impl FromStr for ParsedInfo {
     }
 }
 
+/// the time in the message of postgres, e.g. `2022-12-26 22:00:00.5+00`, or in RFC 3339
 fn parse_datetime(s: &str) -> Result<DateTime<Utc>, ()> {
-    Ok(DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%#z")
-        .map_err(|_| ())?
-        .with_timezone(&Utc))
+    DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f%#z")
+        .or_else(|_| DateTime::parse_from_rfc3339(s))
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| ())
+}
+
+/// the sub-second part is kept, so the window could be parsed back as it is
+fn format_datetime(dt: &DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)
 }
 
 #[cfg(test)]
@@ -114,6 +273,25 @@ mod test {
         assert_eq!(dt.to_rfc3339(), "2022-12-26T22:00:00+00:00");
     }
     #[test]
+    fn window_should_keep_the_sub_seconds_when_formatted_and_parsed() {
+        let dt = parse_datetime("2022-12-26 22:00:00.25+00").unwrap();
+        assert_eq!(dt.timestamp_subsec_millis(), 250);
+        assert_eq!(format_datetime(&dt), "2022-12-26T22:00:00.250Z");
+        assert_eq!(parse_datetime(&format_datetime(&dt)), Ok(dt));
+        let window = ReservationWindow {
+            rid: "ocean-view-room-777".into(),
+            start: dt,
+            end: dt + chrono::Duration::hours(1),
+        };
+        let conflict = ReservationConflict {
+            new: window.clone(),
+            old: window,
+            blocking: vec![],
+        };
+        let info: ReservationConflictInfo = conflict.to_string().parse().unwrap();
+        assert_eq!(info, ReservationConflictInfo::Parsed(conflict));
+    }
+    #[test]
     fn parsed_info_should_word() {
         let info: ParsedInfo = ERR_MSG.parse().unwrap();
         assert_eq!(info.new["resource_id"], "ocean-view-room-777");
@@ -158,4 +336,57 @@ mod test {
             ReservationConflictInfo::Unparsed(_) => panic!("should be parsed"),
         }
     }
+    #[test]
+    fn conflict_info_should_convert_to_details_and_back() {
+        let mut info: ReservationConflictInfo = ERR_MSG.parse().unwrap();
+        if let ReservationConflictInfo::Parsed(ref mut conflict) = info {
+            let overlapping = Reservation {
+                id: "overlapping-id".into(),
+                ..Reservation::new_pending(
+                    "alice",
+                    "ocean-view-room-777",
+                    "2022-12-29T22:00:00+0000".parse().unwrap(),
+                    "2022-12-30T22:00:00+0000".parse().unwrap(),
+                    "",
+                )
+            };
+            // the one reported by the database
+            let blocking = Reservation {
+                id: "blocking-id".into(),
+                ..Reservation::new_pending(
+                    "leon",
+                    "ocean-view-room-777",
+                    "2022-12-25T22:00:00+0000".parse().unwrap(),
+                    "2022-12-28T19:00:00+0000".parse().unwrap(),
+                    "",
+                )
+            };
+            conflict.blocking = vec![overlapping, blocking];
+        }
+        let details = ConflictDetails::from(&info);
+        assert_eq!(details.resource_id, "ocean-view-room-777");
+        assert_eq!(details.blocking_reservations.len(), 2);
+        assert_eq!(details.blocking_reservation_id, "blocking-id");
+        assert!(details.raw.is_empty());
+        assert_eq!(ReservationConflictInfo::from(details), info);
+
+        let info = ReservationConflictInfo::Unparsed("oops".into());
+        let details = ConflictDetails::from(&info);
+        assert_eq!(details.raw, "oops");
+        assert_eq!(ReservationConflictInfo::from(details), info);
+    }
+    #[test]
+    fn conflict_info_should_format_as_error_message() {
+        let info: ReservationConflictInfo = ERR_MSG.parse().unwrap();
+        assert_eq!(
+            info.to_string(),
+            "Key (resource_id, timespan)=(ocean-view-room-777, [\"2022-12-26T22:00:00Z\",\"2022-12-30T19:00:00Z\")) conflicts with existing key (resource_id, timespan)=(ocean-view-room-777, [\"2022-12-25T22:00:00Z\",\"2022-12-28T19:00:00Z\"))."
+        );
+        assert_eq!(
+            info.to_string().parse::<ReservationConflictInfo>(),
+            Ok(info)
+        );
+        let info = ReservationConflictInfo::Unparsed("oops".into());
+        assert_eq!(info.to_string(), "oops");
+    }
 }
@@ -1,8 +1,12 @@
 pub mod conflict;
-pub use conflict::{ReservationConflict, ReservationConflictInfo, ReservationWindow};
+pub use conflict::{
+    BatchConflict, ReservationConflict, ReservationConflictInfo, ReservationWindow,
+};
 
+use prost::Message;
 use sqlx::postgres::PgDatabaseError;
-use thiserror;
+
+use crate::{BatchConflictDetails, ConflictDetails, ErrorDetails, ReservationStatus};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -22,10 +26,59 @@ pub enum Error {
     InvalidTime,
     #[error("Conflicting Reservation")]
     ConflictingReservation(ReservationConflictInfo),
+    #[error("Conflicting reservations in the batch")]
+    ConflictingBatch(Vec<BatchConflict>),
+    #[error("Invalid status transition from {from} to {to}")]
+    InvalidStatusTransition {
+        from: ReservationStatus,
+        to: ReservationStatus,
+    },
+    #[error("Reservation is no longer active: {0}")]
+    InactiveReservation(ReservationStatus),
+    #[error("Version mismatch: expected {expected}, found {found}")]
+    VersionMismatch { expected: i64, found: i64 },
     #[error("No reservation found by the given condition")]
     NotFound,
     #[error("Invalid user id: {0}")]
     InvalidUserId(String),
+    #[error("Invalid idempotency key: {0}")]
+    InvalidIdempotencyKey(String),
+    #[error("Idempotency key reused with another request: {0}")]
+    IdempotencyKeyReused(String),
+    #[error("Idempotency key in use by a request in progress: {0}")]
+    IdempotencyKeyInUse(String),
+    #[error("Invalid update mask: {0}")]
+    InvalidUpdateMask(String),
+    #[error("Invalid page size: {0}")]
+    InvalidPageSize(i32),
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+    #[error("Invalid recurrence: {0}")]
+    InvalidRecurrence(String),
+    #[error("Invalid capacity: {0}")]
+    InvalidCapacity(i32),
+    #[error(
+        "Capacity {capacity} is below the {overlapping} reservations overlapping on the resource"
+    )]
+    CapacityBelowReservations { capacity: i32, overlapping: usize },
+    #[error("Resource not found: {0}")]
+    ResourceNotFound(String),
+    #[error("Resource already exists: {0}")]
+    ResourceAlreadyExists(String),
+    #[error("Failed to read the configuration file: {0}")]
+    ConfigReadError(#[source] std::io::Error),
+    #[error("Failed to parse the configuration file: {0}")]
+    ConfigParseError(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("Failed to migrate the database: {0}")]
+    MigrationError(String),
+    #[error("Database schema {applied} is newer than the latest migration {latest} known")]
+    SchemaTooNew { applied: i64, latest: i64 },
+    #[error("Database schema is behind by {} migrations, run `migrate` or set `db.auto_migrate`", .0.len())]
+    PendingMigrations(Vec<i64>),
+    #[error("Remote error: {0}")]
+    RpcError(Box<tonic::Status>),
     #[error("unknown data store error")]
     Unknown,
 }
@@ -37,8 +90,66 @@ impl PartialEq for Error {
             (Self::InvalidReservationId(v1), Self::InvalidReservationId(v2)) => v1 == v2,
             (Self::InvalidResourceId(v1), Self::InvalidResourceId(v2)) => v1 == v2,
             (Self::ConflictingReservation(v1), Self::ConflictingReservation(v2)) => v1 == v2,
+            (Self::ConflictingBatch(v1), Self::ConflictingBatch(v2)) => v1 == v2,
             (Self::InvalidUserId(v1), Self::InvalidUserId(v2)) => v1 == v2,
+            (Self::InvalidCapacity(v1), Self::InvalidCapacity(v2)) => v1 == v2,
+            (Self::InvalidRecurrence(v1), Self::InvalidRecurrence(v2)) => v1 == v2,
+            (Self::InvalidIdempotencyKey(v1), Self::InvalidIdempotencyKey(v2)) => v1 == v2,
+            (Self::IdempotencyKeyReused(v1), Self::IdempotencyKeyReused(v2)) => v1 == v2,
+            (Self::IdempotencyKeyInUse(v1), Self::IdempotencyKeyInUse(v2)) => v1 == v2,
+            (Self::InvalidUpdateMask(v1), Self::InvalidUpdateMask(v2)) => v1 == v2,
+            (Self::InvalidPageSize(v1), Self::InvalidPageSize(v2)) => v1 == v2,
+            (Self::InvalidCursor(v1), Self::InvalidCursor(v2)) => v1 == v2,
+            (Self::ResourceNotFound(v1), Self::ResourceNotFound(v2)) => v1 == v2,
+            (Self::ResourceAlreadyExists(v1), Self::ResourceAlreadyExists(v2)) => v1 == v2,
+            (
+                Self::InvalidStatusTransition { from: f1, to: t1 },
+                Self::InvalidStatusTransition { from: f2, to: t2 },
+            ) => f1 == f2 && t1 == t2,
+            (
+                Self::VersionMismatch {
+                    expected: e1,
+                    found: f1,
+                },
+                Self::VersionMismatch {
+                    expected: e2,
+                    found: f2,
+                },
+            ) => e1 == e2 && f1 == f2,
+            (Self::InactiveReservation(v1), Self::InactiveReservation(v2)) => v1 == v2,
+            (
+                Self::CapacityBelowReservations {
+                    capacity: c1,
+                    overlapping: o1,
+                },
+                Self::CapacityBelowReservations {
+                    capacity: c2,
+                    overlapping: o2,
+                },
+            ) => c1 == c2 && o1 == o2,
             (Self::NotFound, Self::NotFound) => true,
+            (Self::InvalidTime, Self::InvalidTime) => true,
+            (Self::ConfigReadError(v1), Self::ConfigReadError(v2)) => v1.kind() == v2.kind(),
+            (Self::ConfigParseError(v1), Self::ConfigParseError(v2)) => {
+                v1.to_string() == v2.to_string()
+            }
+            (Self::InvalidConfig(v1), Self::InvalidConfig(v2)) => v1 == v2,
+            (Self::MigrationError(v1), Self::MigrationError(v2)) => v1 == v2,
+            (
+                Self::SchemaTooNew {
+                    applied: a1,
+                    latest: l1,
+                },
+                Self::SchemaTooNew {
+                    applied: a2,
+                    latest: l2,
+                },
+            ) => a1 == a2 && l1 == l2,
+            (Self::PendingMigrations(v1), Self::PendingMigrations(v2)) => v1 == v2,
+            (Self::RpcError(v1), Self::RpcError(v2)) => {
+                v1.code() == v2.code() && v1.message() == v2.message()
+            }
+            (Self::Unknown, Self::Unknown) => true,
             _ => false,
         }
     }
@@ -61,3 +172,438 @@ impl From<sqlx::Error> for Error {
         }
     }
 }
+
+impl From<sqlx::migrate::MigrateError> for Error {
+    fn from(e: sqlx::migrate::MigrateError) -> Self {
+        Error::MigrationError(e.to_string())
+    }
+}
+
+/// the metadata of a status telling which error it is, see `ErrorDetails`
+pub const ERROR_KIND_KEY: &str = "rsvp-error";
+
+impl Error {
+    /// the name of the error, sent along with the status
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::DbError(_) => "DbError",
+            Error::InvalidReservationId(_) => "InvalidReservationId",
+            Error::InvalidResourceId(_) => "InvalidResourceId",
+            Error::InvalidTime => "InvalidTime",
+            Error::ConflictingReservation(_) => "ConflictingReservation",
+            Error::ConflictingBatch(_) => "ConflictingBatch",
+            Error::InvalidStatusTransition { .. } => "InvalidStatusTransition",
+            Error::InactiveReservation(_) => "InactiveReservation",
+            Error::VersionMismatch { .. } => "VersionMismatch",
+            Error::NotFound => "NotFound",
+            Error::InvalidUserId(_) => "InvalidUserId",
+            Error::InvalidIdempotencyKey(_) => "InvalidIdempotencyKey",
+            Error::IdempotencyKeyReused(_) => "IdempotencyKeyReused",
+            Error::IdempotencyKeyInUse(_) => "IdempotencyKeyInUse",
+            Error::InvalidUpdateMask(_) => "InvalidUpdateMask",
+            Error::InvalidPageSize(_) => "InvalidPageSize",
+            Error::InvalidCursor(_) => "InvalidCursor",
+            Error::InvalidRecurrence(_) => "InvalidRecurrence",
+            Error::InvalidCapacity(_) => "InvalidCapacity",
+            Error::CapacityBelowReservations { .. } => "CapacityBelowReservations",
+            Error::ResourceNotFound(_) => "ResourceNotFound",
+            Error::ResourceAlreadyExists(_) => "ResourceAlreadyExists",
+            Error::ConfigReadError(_) => "ConfigReadError",
+            Error::ConfigParseError(_) => "ConfigParseError",
+            Error::InvalidConfig(_) => "InvalidConfig",
+            Error::MigrationError(_) => "MigrationError",
+            Error::SchemaTooNew { .. } => "SchemaTooNew",
+            Error::PendingMigrations(_) => "PendingMigrations",
+            Error::RpcError(_) => "RpcError",
+            Error::Unknown => "Unknown",
+        }
+    }
+    /// the values carried by the error for `ErrorDetails`, the conflicts have their own details
+    fn values(&self) -> Vec<String> {
+        match self {
+            Error::InvalidReservationId(v)
+            | Error::InvalidResourceId(v)
+            | Error::InvalidUserId(v)
+            | Error::InvalidIdempotencyKey(v)
+            | Error::IdempotencyKeyReused(v)
+            | Error::IdempotencyKeyInUse(v)
+            | Error::InvalidUpdateMask(v)
+            | Error::InvalidCursor(v)
+            | Error::InvalidRecurrence(v)
+            | Error::ResourceNotFound(v)
+            | Error::ResourceAlreadyExists(v)
+            | Error::InvalidConfig(v)
+            | Error::MigrationError(v) => vec![v.clone()],
+            Error::InvalidStatusTransition { from, to } => vec![from.to_string(), to.to_string()],
+            Error::InactiveReservation(status) => vec![status.to_string()],
+            Error::VersionMismatch { expected, found } => {
+                vec![expected.to_string(), found.to_string()]
+            }
+            Error::InvalidPageSize(v) | Error::InvalidCapacity(v) => vec![v.to_string()],
+            Error::CapacityBelowReservations {
+                capacity,
+                overlapping,
+            } => vec![capacity.to_string(), overlapping.to_string()],
+            Error::SchemaTooNew { applied, latest } => {
+                vec![applied.to_string(), latest.to_string()]
+            }
+            Error::PendingMigrations(versions) => versions.iter().map(|v| v.to_string()).collect(),
+            Error::DbError(_)
+            | Error::InvalidTime
+            | Error::ConflictingReservation(_)
+            | Error::ConflictingBatch(_)
+            | Error::NotFound
+            | Error::ConfigReadError(_)
+            | Error::ConfigParseError(_)
+            | Error::RpcError(_)
+            | Error::Unknown => vec![],
+        }
+    }
+    /// to rebuild the error by its kind and the details of the status,
+    /// `None` if it could not be rebuilt, e.g. a database error.
+    fn from_details(kind: &str, details: &[u8]) -> Option<Self> {
+        match kind {
+            "ConflictingReservation" => {
+                let details = ConflictDetails::decode(details).ok()?;
+                return Some(Error::ConflictingReservation(details.into()));
+            }
+            "ConflictingBatch" => {
+                let details = BatchConflictDetails::decode(details).ok()?;
+                return Some(Error::ConflictingBatch(
+                    details.conflicts.into_iter().map(Into::into).collect(),
+                ));
+            }
+            _ => {}
+        }
+        let mut values = ErrorDetails::decode(details).ok()?.values.into_iter();
+        let mut next = || values.next();
+        let err = match kind {
+            "InvalidReservationId" => Error::InvalidReservationId(next()?),
+            "InvalidResourceId" => Error::InvalidResourceId(next()?),
+            "InvalidTime" => Error::InvalidTime,
+            "InvalidStatusTransition" => Error::InvalidStatusTransition {
+                from: next()?.parse().ok()?,
+                to: next()?.parse().ok()?,
+            },
+            "InactiveReservation" => Error::InactiveReservation(next()?.parse().ok()?),
+            "VersionMismatch" => Error::VersionMismatch {
+                expected: next()?.parse().ok()?,
+                found: next()?.parse().ok()?,
+            },
+            "NotFound" => Error::NotFound,
+            "InvalidUserId" => Error::InvalidUserId(next()?),
+            "InvalidIdempotencyKey" => Error::InvalidIdempotencyKey(next()?),
+            "IdempotencyKeyReused" => Error::IdempotencyKeyReused(next()?),
+            "IdempotencyKeyInUse" => Error::IdempotencyKeyInUse(next()?),
+            "InvalidUpdateMask" => Error::InvalidUpdateMask(next()?),
+            "InvalidPageSize" => Error::InvalidPageSize(next()?.parse().ok()?),
+            "InvalidCursor" => Error::InvalidCursor(next()?),
+            "InvalidRecurrence" => Error::InvalidRecurrence(next()?),
+            "InvalidCapacity" => Error::InvalidCapacity(next()?.parse().ok()?),
+            "CapacityBelowReservations" => Error::CapacityBelowReservations {
+                capacity: next()?.parse().ok()?,
+                overlapping: next()?.parse().ok()?,
+            },
+            "ResourceNotFound" => Error::ResourceNotFound(next()?),
+            "ResourceAlreadyExists" => Error::ResourceAlreadyExists(next()?),
+            "InvalidConfig" => Error::InvalidConfig(next()?),
+            "MigrationError" => Error::MigrationError(next()?),
+            "SchemaTooNew" => Error::SchemaTooNew {
+                applied: next()?.parse().ok()?,
+                latest: next()?.parse().ok()?,
+            },
+            "PendingMigrations" => Error::PendingMigrations(
+                std::iter::from_fn(next)
+                    .map(|v| v.parse().ok())
+                    .collect::<Option<_>>()?,
+            ),
+            "Unknown" => Error::Unknown,
+            _ => return None,
+        };
+        Some(err)
+    }
+}
+
+impl From<Error> for tonic::Status {
+    fn from(e: Error) -> Self {
+        let code = match e {
+            Error::DbError(_)
+            | Error::ConfigReadError(_)
+            | Error::ConfigParseError(_)
+            | Error::InvalidConfig(_)
+            | Error::MigrationError(_)
+            | Error::SchemaTooNew { .. }
+            | Error::PendingMigrations(_) => tonic::Code::Internal,
+            Error::InvalidReservationId(_)
+            | Error::InvalidResourceId(_)
+            | Error::InvalidUserId(_)
+            | Error::InvalidCapacity(_)
+            | Error::InvalidRecurrence(_)
+            | Error::InvalidIdempotencyKey(_)
+            | Error::IdempotencyKeyReused(_)
+            | Error::InvalidUpdateMask(_)
+            | Error::InvalidPageSize(_)
+            | Error::InvalidCursor(_)
+            | Error::InvalidTime => tonic::Code::InvalidArgument,
+            Error::ConflictingReservation(_)
+            | Error::ConflictingBatch(_)
+            | Error::InvalidStatusTransition { .. }
+            | Error::InactiveReservation(_)
+            | Error::CapacityBelowReservations { .. } => tonic::Code::FailedPrecondition,
+            Error::NotFound | Error::ResourceNotFound(_) => tonic::Code::NotFound,
+            Error::ResourceAlreadyExists(_) => tonic::Code::AlreadyExists,
+            // the client should read the reservation again and retry
+            Error::VersionMismatch { .. } | Error::IdempotencyKeyInUse(_) => tonic::Code::Aborted,
+            Error::RpcError(status) => return *status,
+            Error::Unknown => tonic::Code::Unknown,
+        };
+        // the conflicts are carried in their own details, so the client could rebuild them
+        let (message, details) = match e {
+            Error::ConflictingReservation(ref info) => (
+                format!("{}: {}", e, info),
+                ConflictDetails::from(info).encode_to_vec(),
+            ),
+            Error::ConflictingBatch(ref conflicts) => (
+                format!("{}: {} conflicting", e, conflicts.len()),
+                BatchConflictDetails {
+                    conflicts: conflicts.iter().map(Into::into).collect(),
+                }
+                .encode_to_vec(),
+            ),
+            ref e => (
+                e.to_string(),
+                ErrorDetails { values: e.values() }.encode_to_vec(),
+            ),
+        };
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        metadata.insert(
+            ERROR_KIND_KEY,
+            tonic::metadata::MetadataValue::from_static(e.kind()),
+        );
+        tonic::Status::with_details_and_metadata(code, message, details.into(), metadata)
+    }
+}
+
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        let kind = status
+            .metadata()
+            .get(ERROR_KIND_KEY)
+            .and_then(|kind| kind.to_str().ok());
+        match kind.and_then(|kind| Error::from_details(kind, status.details())) {
+            Some(err) => err,
+            None => Error::from_message(status),
+        }
+    }
+}
+
+impl Error {
+    /// to tell the error by the code and the message of the status,
+    /// for the statuses without the kind of the error, e.g. from an older server.
+    fn from_message(status: tonic::Status) -> Self {
+        let msg = status.message();
+        match status.code() {
+            tonic::Code::NotFound => match msg.strip_prefix("Resource not found: ") {
+                Some(id) => Error::ResourceNotFound(id.to_owned()),
+                None => Error::NotFound,
+            },
+            tonic::Code::AlreadyExists => match msg.strip_prefix("Resource already exists: ") {
+                Some(id) => Error::ResourceAlreadyExists(id.to_owned()),
+                None => Error::RpcError(Box::new(status)),
+            },
+            // the details of a conflict are told apart by the message
+            tonic::Code::FailedPrecondition
+                if msg.starts_with("Conflicting reservations in the batch") =>
+            {
+                match BatchConflictDetails::decode(status.details()) {
+                    Ok(details) => Error::ConflictingBatch(
+                        details.conflicts.into_iter().map(Into::into).collect(),
+                    ),
+                    Err(_) => Error::RpcError(Box::new(status)),
+                }
+            }
+            tonic::Code::FailedPrecondition if msg.starts_with("Conflicting Reservation") => {
+                match ConflictDetails::decode(status.details()) {
+                    Ok(details) => Error::ConflictingReservation(details.into()),
+                    Err(_) => Error::RpcError(Box::new(status)),
+                }
+            }
+            tonic::Code::FailedPrecondition => match parse_status_transition(msg) {
+                Some((from, to)) => Error::InvalidStatusTransition { from, to },
+                None => match msg
+                    .strip_prefix("Reservation is no longer active: ")
+                    .and_then(|v| v.parse().ok())
+                {
+                    Some(status) => Error::InactiveReservation(status),
+                    None => match parse_capacity_below_reservations(msg) {
+                        Some((capacity, overlapping)) => Error::CapacityBelowReservations {
+                            capacity,
+                            overlapping,
+                        },
+                        None => Error::RpcError(Box::new(status)),
+                    },
+                },
+            },
+            tonic::Code::Aborted => match parse_version_mismatch(msg) {
+                Some((expected, found)) => Error::VersionMismatch { expected, found },
+                None => match msg.strip_prefix("Idempotency key in use by a request in progress: ")
+                {
+                    Some(key) => Error::IdempotencyKeyInUse(key.to_owned()),
+                    None => Error::RpcError(Box::new(status)),
+                },
+            },
+            tonic::Code::InvalidArgument => {
+                if let Some(id) = msg.strip_prefix("Invalid reservation id: ") {
+                    Error::InvalidReservationId(id.to_owned())
+                } else if let Some(id) = msg.strip_prefix("Invalid resource id: ") {
+                    Error::InvalidResourceId(id.to_owned())
+                } else if let Some(id) = msg.strip_prefix("Invalid user id: ") {
+                    Error::InvalidUserId(id.to_owned())
+                } else if let Some(rule) = msg.strip_prefix("Invalid recurrence: ") {
+                    Error::InvalidRecurrence(rule.to_owned())
+                } else if let Some(cursor) = msg.strip_prefix("Invalid cursor: ") {
+                    Error::InvalidCursor(cursor.to_owned())
+                } else if let Some(size) = msg
+                    .strip_prefix("Invalid page size: ")
+                    .and_then(|v| v.parse().ok())
+                {
+                    Error::InvalidPageSize(size)
+                } else if let Some(path) = msg.strip_prefix("Invalid update mask: ") {
+                    Error::InvalidUpdateMask(path.to_owned())
+                } else if let Some(key) = msg.strip_prefix("Invalid idempotency key: ") {
+                    Error::InvalidIdempotencyKey(key.to_owned())
+                } else if let Some(key) =
+                    msg.strip_prefix("Idempotency key reused with another request: ")
+                {
+                    Error::IdempotencyKeyReused(key.to_owned())
+                } else if let Some(capacity) = msg
+                    .strip_prefix("Invalid capacity: ")
+                    .and_then(|v| v.parse().ok())
+                {
+                    Error::InvalidCapacity(capacity)
+                } else if msg == Error::InvalidTime.to_string() {
+                    Error::InvalidTime
+                } else {
+                    Error::RpcError(Box::new(status))
+                }
+            }
+            _ => Error::RpcError(Box::new(status)),
+        }
+    }
+}
+
+/// to parse the statuses back from the message of `Error::InvalidStatusTransition`
+fn parse_status_transition(msg: &str) -> Option<(ReservationStatus, ReservationStatus)> {
+    let (from, to) = msg
+        .strip_prefix("Invalid status transition from ")?
+        .split_once(" to ")?;
+    Some((from.parse().ok()?, to.parse().ok()?))
+}
+
+fn parse_version_mismatch(msg: &str) -> Option<(i64, i64)> {
+    let (expected, found) = msg
+        .strip_prefix("Version mismatch: expected ")?
+        .split_once(", found ")?;
+    Some((expected.parse().ok()?, found.parse().ok()?))
+}
+
+fn parse_capacity_below_reservations(msg: &str) -> Option<(i32, usize)> {
+    let (capacity, overlapping) = msg
+        .strip_prefix("Capacity ")?
+        .strip_suffix(" reservations overlapping on the resource")?
+        .split_once(" is below the ")?;
+    Some((capacity.parse().ok()?, overlapping.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+    const CONFLICT_MSG: &str = "Key (resource_id, timespan)=(ocean-view-room-777, [\"2022-12-26 22:00:00+00\",\"2022-12-30 19:00:00+00\")) conflicts with existing key (resource_id, timespan)=(ocean-view-room-777, [\"2022-12-25 22:00:00+00\",\"2022-12-28 19:00:00+00\")).";
+    #[test]
+    fn error_should_convert_to_status() {
+        let status: tonic::Status = Error::NotFound.into();
+        assert_eq!(status.code(), Code::NotFound);
+        let status: tonic::Status = Error::InvalidTime.into();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let status: tonic::Status =
+            Error::ConflictingReservation(ReservationConflictInfo::Unparsed("oops".into())).into();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        let status: tonic::Status = Error::DbError(sqlx::Error::PoolTimedOut).into();
+        assert_eq!(status.code(), Code::Internal);
+    }
+    #[test]
+    fn status_should_convert_back_to_error() {
+        let errors = || {
+            vec![
+                Error::NotFound,
+                Error::InvalidTime,
+                Error::InvalidReservationId("abc".into()),
+                Error::InvalidResourceId("".into()),
+                Error::InvalidUserId("".into()),
+                Error::InvalidCapacity(-1),
+                Error::InvalidRecurrence("FREQ=YEARLY".into()),
+                Error::InvalidIdempotencyKey("".into()),
+                Error::IdempotencyKeyReused("leon-2024-01-01".into()),
+                Error::IdempotencyKeyInUse("leon-2024-01-01".into()),
+                Error::InvalidUpdateMask("status".into()),
+                Error::InvalidPageSize(101),
+                Error::InvalidCursor("zz".into()),
+                Error::VersionMismatch {
+                    expected: 1,
+                    found: 2,
+                },
+                Error::ResourceNotFound("ocean-view-room-777".into()),
+                Error::ResourceAlreadyExists("ocean-view-room-777".into()),
+                Error::ConflictingReservation(CONFLICT_MSG.parse().unwrap()),
+                Error::ConflictingReservation(ReservationConflictInfo::Unparsed("oops".into())),
+                Error::ConflictingBatch(vec![
+                    BatchConflict {
+                        index: 0,
+                        info: CONFLICT_MSG.parse().unwrap(),
+                        blocking_indexes: vec![],
+                    },
+                    BatchConflict {
+                        index: 2,
+                        info: ReservationConflictInfo::Unparsed("oops".into()),
+                        blocking_indexes: vec![0, 1],
+                    },
+                ]),
+                Error::InvalidStatusTransition {
+                    from: ReservationStatus::CheckedIn,
+                    to: ReservationStatus::NoShow,
+                },
+                Error::InactiveReservation(ReservationStatus::Cancelled),
+                Error::CapacityBelowReservations {
+                    capacity: 1,
+                    overlapping: 2,
+                },
+            ]
+        };
+        for (err, expected) in errors().into_iter().zip(errors()) {
+            let status: tonic::Status = err.into();
+            // by the kind and the details
+            let reworded = tonic::Status::with_details_and_metadata(
+                status.code(),
+                "reworded",
+                status.details().to_vec().into(),
+                status.metadata().clone(),
+            );
+            assert_eq!(Error::from(reworded), expected);
+            // by the message if the kind is not told
+            let status = tonic::Status::with_details(
+                status.code(),
+                status.message(),
+                status.details().to_vec().into(),
+            );
+            assert_eq!(Error::from(status), expected);
+        }
+        let status: tonic::Status = Error::PendingMigrations(vec![1, 2]).into();
+        assert_eq!(Error::from(status), Error::PendingMigrations(vec![1, 2]));
+        let err = Error::from(tonic::Status::unavailable("server is down"));
+        assert_eq!(
+            err,
+            Error::RpcError(Box::new(tonic::Status::unavailable("server is down")))
+        );
+    }
+}
@@ -1,8 +1,10 @@
+mod config;
 mod error;
 mod pb;
 mod types;
 mod utils;
 
+pub use config::*;
 pub use error::*;
 pub use pb::*;
 pub use types::*;
@@ -11,6 +13,7 @@ pub use utils::*;
 pub type ReservationId = String;
 pub type UserId = String;
 pub type ResourceId = String;
+pub type ChangeId = i64;
 
 /// to validate the data structure,
 /// to raise errors if it is invalid.
@@ -20,12 +23,26 @@ pub trait Validator {
 
 /// database equivalent of the enum `reservation_status`
 #[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
-#[sqlx(type_name = "reservation_status", rename_all = "lowercase")]
+#[sqlx(type_name = "reservation_status", rename_all = "snake_case")]
 pub enum RsvpStatus {
     Unknown,
     Pending,
     Confirmed,
     Blocked,
+    Cancelled,
+    CheckedIn,
+    Completed,
+    NoShow,
+}
+
+/// database equivalent of the enum `reservation_update_type`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "reservation_update_type", rename_all = "lowercase")]
+pub enum RsvpUpdateType {
+    Unknown,
+    Create,
+    Update,
+    Delete,
 }
 
 impl Validator for ReservationId {
@@ -26,6 +26,34 @@ pub struct Reservation {
     /// extra note
     #[prost(string, tag = "7")]
     pub note: ::prost::alloc::string::String,
+    /// when the reservation was cancelled, only set if it is cancelled
+    #[prost(message, optional, tag = "8")]
+    pub cancelled_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// who cancelled the reservation
+    #[prost(string, tag = "9")]
+    pub cancelled_by: ::prost::alloc::string::String,
+    /// why the reservation was cancelled
+    #[prost(string, tag = "10")]
+    pub cancel_reason: ::prost::alloc::string::String,
+    /// a pending reservation is cancelled once its hold expires,
+    /// not set if the hold never expires.
+    #[prost(message, optional, tag = "11")]
+    pub hold_expires_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// recurrence rule, a subset of RFC 5545 RRULE, e.g. "FREQ=WEEKLY;BYDAY=MO;COUNT=10".
+    /// if put into ReservationRequest, the reservation is expanded into a series,
+    /// start and end are the time of the first occurrence.
+    /// the occurrences are at the same UTC time, they do not follow the daylight saving time.
+    #[prost(string, tag = "12")]
+    pub recurrence: ::prost::alloc::string::String,
+    /// id of the series the reservation belongs to, empty if it does not recur
+    #[prost(string, tag = "13")]
+    pub series_id: ::prost::alloc::string::String,
+    /// bumped on every update, to detect the concurrent updates
+    #[prost(int64, tag = "14")]
+    pub version: i64,
+    /// when the reservation was updated last
+    #[prost(message, optional, tag = "15")]
+    pub updated_at: ::core::option::Option<::prost_types::Timestamp>,
 }
 /// to make a reservation,
 /// to send a ReservationRequest with Reservation object (id should be empty).
@@ -33,6 +61,9 @@ pub struct Reservation {
 pub struct ReservationRequest {
     #[prost(message, optional, tag = "1")]
     pub reservation: ::core::option::Option<Reservation>,
+    /// retried requests with the same key get the response of the first one
+    #[prost(string, tag = "2")]
+    pub idempotency_key: ::prost::alloc::string::String,
 }
 /// created reservation will be returned in ReservationResponse.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -42,11 +73,21 @@ pub struct ReservationResponse {
 }
 /// to update a reservation,
 /// to send an UpdateRequest.
-/// Only note can be updated.
+/// the fields in update_mask are updated, only note if it is empty.
+/// the paths allowed are "note", "start", "end" and "resource_id",
+/// a changed time slot is checked for conflicts the same as a new reservation.
+/// the version should be the one read last,
+/// otherwise the update is rejected as the reservation has been changed by someone else.
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UpdateRequest {
     #[prost(message, optional, tag = "1")]
     pub reservation: ::core::option::Option<Reservation>,
+    /// retried requests with the same key get the response of the first one
+    #[prost(string, tag = "2")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    /// the fields to update
+    #[prost(message, optional, tag = "3")]
+    pub update_mask: ::core::option::Option<::prost_types::FieldMask>,
 }
 /// updated reservation will be returned via the UpdateResponse.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -60,6 +101,9 @@ pub struct UpdateResponse {
 pub struct ConfirmRequest {
     #[prost(message, optional, tag = "1")]
     pub reservation: ::core::option::Option<Reservation>,
+    /// retried requests with the same key get the response of the first one
+    #[prost(string, tag = "2")]
+    pub idempotency_key: ::prost::alloc::string::String,
 }
 /// Confirmed reservation will be returned in ConfirmResponse.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -72,12 +116,83 @@ pub struct ConfirmResponse {
 pub struct CancelRequest {
     #[prost(string, tag = "1")]
     pub id: ::prost::alloc::string::String,
+    /// who cancels the reservation
+    #[prost(string, tag = "2")]
+    pub cancelled_by: ::prost::alloc::string::String,
+    /// why the reservation is cancelled
+    #[prost(string, tag = "3")]
+    pub reason: ::prost::alloc::string::String,
+    /// retried requests with the same key get the response of the first one
+    #[prost(string, tag = "4")]
+    pub idempotency_key: ::prost::alloc::string::String,
 }
 /// canceled reservation will be returned in CancelResponse.
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CancelResponse {
+    /// the id of the cancelled reservation, kept for the clients built before `reservation`
+    #[deprecated]
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub reservation: ::core::option::Option<Reservation>,
+}
+/// to move a reservation to another status, send a TransitionRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TransitionRequest {
     #[prost(string, tag = "1")]
     pub id: ::prost::alloc::string::String,
+    /// the target status, must be allowed from the current one
+    #[prost(enumeration = "ReservationStatus", tag = "2")]
+    pub status: i32,
+}
+/// the reservation in the new status will be returned in TransitionResponse.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TransitionResponse {
+    #[prost(message, optional, tag = "1")]
+    pub reservation: ::core::option::Option<Reservation>,
+}
+/// to make several reservations at once, send a ReserveBatchRequest.
+/// all the reservations are made or none.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReserveBatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub reservations: ::prost::alloc::vec::Vec<Reservation>,
+}
+/// the created reservations will be returned in the same order in ReserveBatchResponse.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReserveBatchResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub reservations: ::prost::alloc::vec::Vec<Reservation>,
+}
+/// all the occurrences of a recurring reservation ordered by start time
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SeriesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub reservations: ::prost::alloc::vec::Vec<Reservation>,
+}
+/// to get the occurrences of a series, send a GetSeriesRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSeriesRequest {
+    #[prost(string, tag = "1")]
+    pub series_id: ::prost::alloc::string::String,
+}
+/// to cancel the occurrences of a series which are not over yet, send a CancelSeriesRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelSeriesRequest {
+    #[prost(string, tag = "1")]
+    pub series_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub cancelled_by: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub reason: ::prost::alloc::string::String,
+}
+/// to update the note of all the occurrences of a series, send an UpdateSeriesRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateSeriesRequest {
+    #[prost(string, tag = "1")]
+    pub series_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub note: ::prost::alloc::string::String,
 }
 /// to get a reservation, send a GetRequest.
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -119,11 +234,12 @@ pub struct ReservationQuery {
     #[prost(message, optional, tag = "5")]
     #[builder(setter(into, strip_option))]
     pub end: ::core::option::Option<::prost_types::Timestamp>,
-    /// the current page for the query
+    /// the current page for the query, ignored if cursor is set
     #[prost(int32, tag = "6")]
     #[builder(setter(into), default)]
     pub page: i32,
-    /// the page size for the query
+    /// the page size for the query, `pagination.default_page_size` of the server config if 0.
+    /// a negative size or one above `pagination.max_page_size` fails with InvalidPageSize
     #[prost(int32, tag = "7")]
     #[builder(setter(into), default)]
     pub page_size: i32,
@@ -131,45 +247,255 @@ pub struct ReservationQuery {
     #[prost(bool, tag = "8")]
     #[builder(setter(into), default)]
     pub desc: bool,
+    /// next_cursor of the previous page, to get the page after it.
+    /// the reservations are ordered by start time and id, so the pages are stable
+    /// while the reservations are changed.
+    #[prost(string, tag = "9")]
+    #[builder(setter(into), default)]
+    pub cursor: ::prost::alloc::string::String,
+    /// how the reservations are matched against the window, contained by default
+    #[prost(enumeration = "QueryMatchMode", tag = "10")]
+    #[builder(setter(into), default)]
+    pub match_mode: i32,
 }
 /// to query reservations, send a QueryRequest
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct QueryRequest {
-    /// resource id for the reservation query.
-    /// if empty, query all resources.
+    /// the filters sent by the clients built before `query`, used only if `query` is not set
+    #[deprecated]
     #[prost(string, tag = "1")]
     pub resource_id: ::prost::alloc::string::String,
-    /// user id for the reservation query.
-    /// if empty, query all users.
+    #[deprecated]
     #[prost(string, tag = "2")]
     pub user_id: ::prost::alloc::string::String,
-    /// use status to filter result.
-    /// if unknown, return all reservations.
+    #[deprecated]
     #[prost(enumeration = "ReservationStatus", tag = "3")]
     pub status: i32,
-    /// start time for the reservation query,
-    /// if 0, use Infinity as the start time.
+    #[deprecated]
     #[prost(message, optional, tag = "4")]
     pub start: ::core::option::Option<::prost_types::Timestamp>,
-    /// end time for the reservation query.
-    /// if 0, use Infinity as the end time
+    #[deprecated]
     #[prost(message, optional, tag = "5")]
     pub end: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "6")]
+    pub query: ::core::option::Option<ReservationQuery>,
+}
+/// a page of the reservations queried
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReservationPage {
+    #[prost(message, repeated, tag = "1")]
+    pub reservations: ::prost::alloc::vec::Vec<Reservation>,
+    /// to get the next page, empty if this is the last one
+    #[prost(string, tag = "2")]
+    pub next_cursor: ::prost::alloc::string::String,
+    /// the number of all the reservations matching the query
+    #[prost(int64, tag = "3")]
+    pub total: i64,
+    /// whether there are more reservations after this page
+    #[prost(bool, tag = "4")]
+    pub has_more: bool,
 }
 /// Client can listen to reservation updates by sending a ListenRequest
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct ListenRequest {}
+pub struct ListenRequest {
+    /// if set, the changes recorded after this change id are replayed
+    /// before the live updates, so a restarted client could resume from
+    /// the last change it has seen.
+    /// changes older than the retention period are pruned and can not be replayed.
+    #[prost(int64, optional, tag = "1")]
+    pub after_change_id: ::core::option::Option<i64>,
+}
 /// Server will send ListenResponse to the client in streaming response.
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListenResponse {
     /// update type
     #[prost(enumeration = "ReservationUpdateType", tag = "1")]
     pub op: i32,
-    /// id for the updated reservation
+    /// the updated reservation,
+    /// if op is DELETE, only id will be populated.
     #[prost(message, optional, tag = "2")]
     pub reservation: ::core::option::Option<Reservation>,
+    /// position of the change in the order it was committed, used as the cursor to resume listening
+    #[prost(int64, tag = "3")]
+    pub change_id: i64,
+}
+/// time window of a reservation on a resource
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConflictWindow {
+    #[prost(string, tag = "1")]
+    pub resource_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "3")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
+}
+/// attached to the status details when the reservation conflicts with an existing one.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConflictDetails {
+    /// resource id of the conflicting reservations
+    #[prost(string, tag = "1")]
+    pub resource_id: ::prost::alloc::string::String,
+    /// window of the reservation being made
+    #[prost(message, optional, tag = "2")]
+    pub new: ::core::option::Option<ConflictWindow>,
+    /// window of the existing reservation
+    #[prost(message, optional, tag = "3")]
+    pub old: ::core::option::Option<ConflictWindow>,
+    /// id of the existing reservation which blocks the new one,
+    /// empty if it could not be found.
+    #[prost(string, tag = "4")]
+    pub blocking_reservation_id: ::prost::alloc::string::String,
+    /// raw description of the conflict,
+    /// only populated if the conflict could not be parsed.
+    #[prost(string, tag = "5")]
+    pub raw: ::prost::alloc::string::String,
+    /// the existing reservations saturating the capacity of the resource during the new one
+    #[prost(message, repeated, tag = "6")]
+    pub blocking_reservations: ::prost::alloc::vec::Vec<Reservation>,
 }
-/// reverseation status for a given time period
+/// conflict of one of the reservations in a batch
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchItemConflict {
+    /// position of the reservation in ReserveBatchRequest
+    #[prost(int32, tag = "1")]
+    pub index: i32,
+    #[prost(message, optional, tag = "2")]
+    pub details: ::core::option::Option<ConflictDetails>,
+    /// positions of the earlier reservations in the batch saturating the capacity with it,
+    /// they are not in the blocking reservations of the details as they have never been made.
+    #[prost(int32, repeated, tag = "3")]
+    pub blocking_indexes: ::prost::alloc::vec::Vec<i32>,
+}
+/// attached to the status details when any reservation in a batch conflicts.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchConflictDetails {
+    #[prost(message, repeated, tag = "1")]
+    pub conflicts: ::prost::alloc::vec::Vec<BatchItemConflict>,
+}
+/// attached to the status details of the errors other than the conflicts.
+/// the name of the error, e.g. "ResourceNotFound", is in the `rsvp-error` metadata of every error,
+/// so the client could rebuild it without parsing the message.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ErrorDetails {
+    /// the values carried by the error, in the order they are declared
+    #[prost(string, repeated, tag = "1")]
+    pub values: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// to find the free time slots of the resources in a window
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AvailabilityQuery {
+    /// the resources to search, at least one should be given
+    #[prost(string, repeated, tag = "1")]
+    pub resource_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// start time of the search window
+    #[prost(message, optional, tag = "2")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    /// end time of the search window
+    #[prost(message, optional, tag = "3")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
+    /// the shortest free slot wanted,
+    /// if not set, all the free slots are returned.
+    #[prost(message, optional, tag = "4")]
+    pub duration: ::core::option::Option<::prost_types::Duration>,
+}
+/// a time period within the search window when the resource could still be reserved
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FreeSlot {
+    #[prost(string, tag = "1")]
+    pub resource_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "3")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
+}
+/// to find the free time slots, send an AvailabilityRequest
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AvailabilityRequest {
+    #[prost(message, optional, tag = "1")]
+    pub query: ::core::option::Option<AvailabilityQuery>,
+}
+/// the free slots ordered by resource, in the order given, then by start time
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AvailabilityResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub slots: ::prost::alloc::vec::Vec<FreeSlot>,
+}
+/// a resource which could be reserved, e.g. a room.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Resource {
+    /// unique id of the resource, referred by Reservation.resource_id
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    /// human readable name
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// extra description
+    #[prost(string, tag = "3")]
+    pub description: ::prost::alloc::string::String,
+    /// how many reservations could overlap on the resource at the same time,
+    /// 1 if not set.
+    #[prost(int32, tag = "4")]
+    pub capacity: i32,
+    /// when the resource was archived, archived resources could no longer be reserved
+    #[prost(message, optional, tag = "5")]
+    pub archived_at: ::core::option::Option<::prost_types::Timestamp>,
+}
+/// to register a resource, send a CreateResourceRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateResourceRequest {
+    #[prost(message, optional, tag = "1")]
+    pub resource: ::core::option::Option<Resource>,
+}
+/// the created resource will be returned in CreateResourceResponse.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateResourceResponse {
+    #[prost(message, optional, tag = "1")]
+    pub resource: ::core::option::Option<Resource>,
+}
+/// to update the name, description and capacity of a resource, send an UpdateResourceRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateResourceRequest {
+    #[prost(message, optional, tag = "1")]
+    pub resource: ::core::option::Option<Resource>,
+}
+/// the updated resource will be returned in UpdateResourceResponse.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateResourceResponse {
+    #[prost(message, optional, tag = "1")]
+    pub resource: ::core::option::Option<Resource>,
+}
+/// to archive a resource, send an ArchiveResourceRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ArchiveResourceRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+}
+/// the archived resource will be returned in ArchiveResourceResponse.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ArchiveResourceResponse {
+    #[prost(message, optional, tag = "1")]
+    pub resource: ::core::option::Option<Resource>,
+}
+/// to list the resources, send a ListResourcesRequest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListResourcesRequest {
+    /// if true, the archived resources are listed too
+    #[prost(bool, tag = "1")]
+    pub include_archived: bool,
+}
+/// the resources ordered by id will be returned in ListResourcesResponse.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListResourcesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub resources: ::prost::alloc::vec::Vec<Resource>,
+}
+/// reverseation status for a given time period.
+/// the allowed transitions are:
+///    pending -> confirmed | cancelled
+///    confirmed -> checked_in | cancelled | no_show
+///    blocked -> cancelled
+///    checked_in -> completed
+/// cancelled, completed and no_show are final.
 #[derive(
     sqlx::Type, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration,
 )]
@@ -179,6 +505,14 @@ pub enum ReservationStatus {
     Pending = 1,
     Confirmed = 2,
     Blocked = 3,
+    /// cancelled reservations are kept, but no longer block the time period
+    Cancelled = 4,
+    /// the user has arrived
+    CheckedIn = 5,
+    /// the user has left after using the resource
+    Completed = 6,
+    /// the user never arrived, the time period is released
+    NoShow = 7,
 }
 impl ReservationStatus {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -191,6 +525,10 @@ impl ReservationStatus {
             ReservationStatus::Pending => "RESERVATION_STATUS_PENDING",
             ReservationStatus::Confirmed => "RESERVATION_STATUS_CONFIRMED",
             ReservationStatus::Blocked => "RESERVATION_STATUS_BLOCKED",
+            ReservationStatus::Cancelled => "RESERVATION_STATUS_CANCELLED",
+            ReservationStatus::CheckedIn => "RESERVATION_STATUS_CHECKED_IN",
+            ReservationStatus::Completed => "RESERVATION_STATUS_COMPLETED",
+            ReservationStatus::NoShow => "RESERVATION_STATUS_NO_SHOW",
         }
     }
 }
@@ -217,6 +555,30 @@ impl ReservationUpdateType {
         }
     }
 }
+/// how the reservations are matched against the time window of a query
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum QueryMatchMode {
+    /// the reservations entirely within the window
+    Contained = 0,
+    /// the reservations sharing any time with the window, e.g. for a day view
+    Overlapping = 1,
+    /// the reservations starting within the window
+    StartingWithin = 2,
+}
+impl QueryMatchMode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            QueryMatchMode::Contained => "QUERY_MATCH_MODE_CONTAINED",
+            QueryMatchMode::Overlapping => "QUERY_MATCH_MODE_OVERLAPPING",
+            QueryMatchMode::StartingWithin => "QUERY_MATCH_MODE_STARTING_WITHIN",
+        }
+    }
+}
 /// Generated client implementations.
 pub mod reservation_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -286,7 +648,8 @@ pub mod reservation_service_client {
             self.inner = self.inner.accept_compressed(encoding);
             self
         }
-        /// make a reservation
+        /// make a reservation,
+        /// if it recurs, the whole series is created and the first occurrence is returned
         pub async fn reserve(
             &mut self,
             request: impl tonic::IntoRequest<super::ReservationRequest>,
@@ -302,8 +665,92 @@ pub mod reservation_service_client {
                 http::uri::PathAndQuery::from_static("/reservation.ReservationService/reserve");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// make several reservations, e.g. a room and a projector, all of them are made or none
+        pub async fn reserve_batch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReserveBatchRequest>,
+        ) -> Result<tonic::Response<super::ReserveBatchResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/reserve_batch",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// make a recurring reservation, all the occurrences are created or none
+        pub async fn reserve_series(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReservationRequest>,
+        ) -> Result<tonic::Response<super::SeriesResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/reserve_series",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// get the occurrences of a series
+        pub async fn get_series(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetSeriesRequest>,
+        ) -> Result<tonic::Response<super::SeriesResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/get_series");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// cancel the occurrences of a series, the ones already over are kept
+        pub async fn cancel_series(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CancelSeriesRequest>,
+        ) -> Result<tonic::Response<super::SeriesResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/cancel_series",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// update the note of the occurrences of a series
+        pub async fn update_series(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateSeriesRequest>,
+        ) -> Result<tonic::Response<super::SeriesResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/update_series",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// confirm a pending reservation,
-        /// if reservation is not pending, to do nothing
+        /// if reservation is not pending, it fails with InvalidStatusTransition (FAILED_PRECONDITION)
         pub async fn confirm(
             &mut self,
             request: impl tonic::IntoRequest<super::ConfirmRequest>,
@@ -319,7 +766,7 @@ pub mod reservation_service_client {
                 http::uri::PathAndQuery::from_static("/reservation.ReservationService/confirm");
             self.inner.unary(request.into_request(), path, codec).await
         }
-        /// update the reservation note
+        /// update the note, the time or the resource of a reservation
         pub async fn update(
             &mut self,
             request: impl tonic::IntoRequest<super::UpdateRequest>,
@@ -335,7 +782,7 @@ pub mod reservation_service_client {
                 http::uri::PathAndQuery::from_static("/reservation.ReservationService/update");
             self.inner.unary(request.into_request(), path, codec).await
         }
-        /// cancel a reservation
+        /// cancel a reservation, the cancelled reservation is kept
         pub async fn cancel(
             &mut self,
             request: impl tonic::IntoRequest<super::CancelRequest>,
@@ -351,6 +798,22 @@ pub mod reservation_service_client {
                 http::uri::PathAndQuery::from_static("/reservation.ReservationService/cancel");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// move a reservation to another status, e.g. check in or complete it
+        pub async fn transition(
+            &mut self,
+            request: impl tonic::IntoRequest<super::TransitionRequest>,
+        ) -> Result<tonic::Response<super::TransitionResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/transition");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// get a reservation
         pub async fn get(
             &mut self,
@@ -385,11 +848,28 @@ pub mod reservation_service_client {
                 .server_streaming(request.into_request(), path, codec)
                 .await
         }
+        /// query a page of reservations, with the cursor of the next page
+        pub async fn query_page(
+            &mut self,
+            request: impl tonic::IntoRequest<super::QueryRequest>,
+        ) -> Result<tonic::Response<super::ReservationPage>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/reservation.ReservationService/query_page");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// another system monitor newly added/confirmed/cancelled reservations
         pub async fn listen(
             &mut self,
             request: impl tonic::IntoRequest<super::ListenRequest>,
-        ) -> Result<tonic::Response<super::ListenResponse>, tonic::Status> {
+        ) -> Result<tonic::Response<tonic::codec::Streaming<super::ListenResponse>>, tonic::Status>
+        {
             self.inner.ready().await.map_err(|e| {
                 tonic::Status::new(
                     tonic::Code::Unknown,
@@ -399,57 +879,209 @@ pub mod reservation_service_client {
             let codec = tonic::codec::ProstCodec::default();
             let path =
                 http::uri::PathAndQuery::from_static("/reservation.ReservationService/listen");
+            self.inner
+                .server_streaming(request.into_request(), path, codec)
+                .await
+        }
+        /// find the free time slots of the resources, respecting their capacity
+        pub async fn availability(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AvailabilityRequest>,
+        ) -> Result<tonic::Response<super::AvailabilityResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/availability",
+            );
             self.inner.unary(request.into_request(), path, codec).await
         }
-    }
-}
-/// Generated server implementations.
-pub mod reservation_service_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with ReservationServiceServer.
-    #[async_trait]
-    pub trait ReservationService: Send + Sync + 'static {
-        /// make a reservation
-        async fn reserve(
-            &self,
-            request: tonic::Request<super::ReservationRequest>,
-        ) -> Result<tonic::Response<super::ReservationResponse>, tonic::Status>;
-        /// confirm a pending reservation,
-        /// if reservation is not pending, to do nothing
-        async fn confirm(
-            &self,
-            request: tonic::Request<super::ConfirmRequest>,
-        ) -> Result<tonic::Response<super::ConfirmResponse>, tonic::Status>;
-        /// update the reservation note
-        async fn update(
-            &self,
-            request: tonic::Request<super::UpdateRequest>,
-        ) -> Result<tonic::Response<super::UpdateResponse>, tonic::Status>;
-        /// cancel a reservation
-        async fn cancel(
-            &self,
-            request: tonic::Request<super::CancelRequest>,
-        ) -> Result<tonic::Response<super::CancelResponse>, tonic::Status>;
-        /// get a reservation
-        async fn get(
-            &self,
-            request: tonic::Request<super::GetRequest>,
-        ) -> Result<tonic::Response<super::GetResponse>, tonic::Status>;
-        /// Server streaming response type for the query method.
-        type queryStream: futures_core::Stream<Item = Result<super::Reservation, tonic::Status>>
-            + Send
-            + 'static;
-        /// query reservations by resource id, user id, status, start time, end time
-        async fn query(
-            &self,
+        /// register a resource which could be reserved
+        pub async fn create_resource(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateResourceRequest>,
+        ) -> Result<tonic::Response<super::CreateResourceResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/create_resource",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// update a resource
+        pub async fn update_resource(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateResourceRequest>,
+        ) -> Result<tonic::Response<super::UpdateResourceResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/update_resource",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// archive a resource, the existing reservations are kept
+        pub async fn archive_resource(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ArchiveResourceRequest>,
+        ) -> Result<tonic::Response<super::ArchiveResourceResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/archive_resource",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// list the resources
+        pub async fn list_resources(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListResourcesRequest>,
+        ) -> Result<tonic::Response<super::ListResourcesResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/reservation.ReservationService/list_resources",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod reservation_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with ReservationServiceServer.
+    #[async_trait]
+    pub trait ReservationService: Send + Sync + 'static {
+        /// make a reservation,
+        /// if it recurs, the whole series is created and the first occurrence is returned
+        async fn reserve(
+            &self,
+            request: tonic::Request<super::ReservationRequest>,
+        ) -> Result<tonic::Response<super::ReservationResponse>, tonic::Status>;
+        /// make several reservations, e.g. a room and a projector, all of them are made or none
+        async fn reserve_batch(
+            &self,
+            request: tonic::Request<super::ReserveBatchRequest>,
+        ) -> Result<tonic::Response<super::ReserveBatchResponse>, tonic::Status>;
+        /// make a recurring reservation, all the occurrences are created or none
+        async fn reserve_series(
+            &self,
+            request: tonic::Request<super::ReservationRequest>,
+        ) -> Result<tonic::Response<super::SeriesResponse>, tonic::Status>;
+        /// get the occurrences of a series
+        async fn get_series(
+            &self,
+            request: tonic::Request<super::GetSeriesRequest>,
+        ) -> Result<tonic::Response<super::SeriesResponse>, tonic::Status>;
+        /// cancel the occurrences of a series, the ones already over are kept
+        async fn cancel_series(
+            &self,
+            request: tonic::Request<super::CancelSeriesRequest>,
+        ) -> Result<tonic::Response<super::SeriesResponse>, tonic::Status>;
+        /// update the note of the occurrences of a series
+        async fn update_series(
+            &self,
+            request: tonic::Request<super::UpdateSeriesRequest>,
+        ) -> Result<tonic::Response<super::SeriesResponse>, tonic::Status>;
+        /// confirm a pending reservation,
+        /// if reservation is not pending, it fails with InvalidStatusTransition (FAILED_PRECONDITION)
+        async fn confirm(
+            &self,
+            request: tonic::Request<super::ConfirmRequest>,
+        ) -> Result<tonic::Response<super::ConfirmResponse>, tonic::Status>;
+        /// update the note, the time or the resource of a reservation
+        async fn update(
+            &self,
+            request: tonic::Request<super::UpdateRequest>,
+        ) -> Result<tonic::Response<super::UpdateResponse>, tonic::Status>;
+        /// cancel a reservation, the cancelled reservation is kept
+        async fn cancel(
+            &self,
+            request: tonic::Request<super::CancelRequest>,
+        ) -> Result<tonic::Response<super::CancelResponse>, tonic::Status>;
+        /// move a reservation to another status, e.g. check in or complete it
+        async fn transition(
+            &self,
+            request: tonic::Request<super::TransitionRequest>,
+        ) -> Result<tonic::Response<super::TransitionResponse>, tonic::Status>;
+        /// get a reservation
+        async fn get(
+            &self,
+            request: tonic::Request<super::GetRequest>,
+        ) -> Result<tonic::Response<super::GetResponse>, tonic::Status>;
+        /// Server streaming response type for the query method.
+        type queryStream: futures_core::Stream<Item = Result<super::Reservation, tonic::Status>>
+            + Send
+            + 'static;
+        /// query reservations by resource id, user id, status, start time, end time
+        async fn query(
+            &self,
             request: tonic::Request<super::QueryRequest>,
         ) -> Result<tonic::Response<Self::queryStream>, tonic::Status>;
+        /// query a page of reservations, with the cursor of the next page
+        async fn query_page(
+            &self,
+            request: tonic::Request<super::QueryRequest>,
+        ) -> Result<tonic::Response<super::ReservationPage>, tonic::Status>;
+        /// Server streaming response type for the listen method.
+        type listenStream: futures_core::Stream<Item = Result<super::ListenResponse, tonic::Status>>
+            + Send
+            + 'static;
         /// another system monitor newly added/confirmed/cancelled reservations
         async fn listen(
             &self,
             request: tonic::Request<super::ListenRequest>,
-        ) -> Result<tonic::Response<super::ListenResponse>, tonic::Status>;
+        ) -> Result<tonic::Response<Self::listenStream>, tonic::Status>;
+        /// find the free time slots of the resources, respecting their capacity
+        async fn availability(
+            &self,
+            request: tonic::Request<super::AvailabilityRequest>,
+        ) -> Result<tonic::Response<super::AvailabilityResponse>, tonic::Status>;
+        /// register a resource which could be reserved
+        async fn create_resource(
+            &self,
+            request: tonic::Request<super::CreateResourceRequest>,
+        ) -> Result<tonic::Response<super::CreateResourceResponse>, tonic::Status>;
+        /// update a resource
+        async fn update_resource(
+            &self,
+            request: tonic::Request<super::UpdateResourceRequest>,
+        ) -> Result<tonic::Response<super::UpdateResourceResponse>, tonic::Status>;
+        /// archive a resource, the existing reservations are kept
+        async fn archive_resource(
+            &self,
+            request: tonic::Request<super::ArchiveResourceRequest>,
+        ) -> Result<tonic::Response<super::ArchiveResourceResponse>, tonic::Status>;
+        /// list the resources
+        async fn list_resources(
+            &self,
+            request: tonic::Request<super::ListResourcesRequest>,
+        ) -> Result<tonic::Response<super::ListResourcesResponse>, tonic::Status>;
     }
     /// Reservation Service
     #[derive(Debug)]
@@ -538,6 +1170,175 @@ pub mod reservation_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/reservation.ReservationService/reserve_batch" => {
+                    #[allow(non_camel_case_types)]
+                    struct reserve_batchSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::ReserveBatchRequest>
+                        for reserve_batchSvc<T>
+                    {
+                        type Response = super::ReserveBatchResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReserveBatchRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).reserve_batch(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = reserve_batchSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/reserve_series" => {
+                    #[allow(non_camel_case_types)]
+                    struct reserve_seriesSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::ReservationRequest>
+                        for reserve_seriesSvc<T>
+                    {
+                        type Response = super::SeriesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReservationRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).reserve_series(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = reserve_seriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/get_series" => {
+                    #[allow(non_camel_case_types)]
+                    struct get_seriesSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::GetSeriesRequest>
+                        for get_seriesSvc<T>
+                    {
+                        type Response = super::SeriesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetSeriesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).get_series(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = get_seriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/cancel_series" => {
+                    #[allow(non_camel_case_types)]
+                    struct cancel_seriesSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::CancelSeriesRequest>
+                        for cancel_seriesSvc<T>
+                    {
+                        type Response = super::SeriesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CancelSeriesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).cancel_series(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = cancel_seriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/update_series" => {
+                    #[allow(non_camel_case_types)]
+                    struct update_seriesSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::UpdateSeriesRequest>
+                        for update_seriesSvc<T>
+                    {
+                        type Response = super::SeriesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateSeriesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).update_series(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = update_seriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/reservation.ReservationService/confirm" => {
                     #[allow(non_camel_case_types)]
                     struct confirmSvc<T: ReservationService>(pub Arc<T>);
@@ -631,6 +1432,39 @@ pub mod reservation_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/reservation.ReservationService/transition" => {
+                    #[allow(non_camel_case_types)]
+                    struct transitionSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::TransitionRequest> for transitionSvc<T>
+                    {
+                        type Response = super::TransitionResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TransitionRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).transition(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = transitionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/reservation.ReservationService/get" => {
                     #[allow(non_camel_case_types)]
                     struct getSvc<T: ReservationService>(pub Arc<T>);
@@ -697,12 +1531,48 @@ pub mod reservation_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/reservation.ReservationService/query_page" => {
+                    #[allow(non_camel_case_types)]
+                    struct query_pageSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService> tonic::server::UnaryService<super::QueryRequest> for query_pageSvc<T> {
+                        type Response = super::ReservationPage;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::QueryRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).query_page(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = query_pageSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/reservation.ReservationService/listen" => {
                     #[allow(non_camel_case_types)]
                     struct listenSvc<T: ReservationService>(pub Arc<T>);
-                    impl<T: ReservationService> tonic::server::UnaryService<super::ListenRequest> for listenSvc<T> {
+                    impl<T: ReservationService>
+                        tonic::server::ServerStreamingService<super::ListenRequest>
+                        for listenSvc<T>
+                    {
                         type Response = super::ListenResponse;
-                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        type ResponseStream = T::listenStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
                         fn call(
                             &mut self,
                             request: tonic::Request<super::ListenRequest>,
@@ -723,6 +1593,176 @@ pub mod reservation_service_server {
                             accept_compression_encodings,
                             send_compression_encodings,
                         );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/availability" => {
+                    #[allow(non_camel_case_types)]
+                    struct availabilitySvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::AvailabilityRequest>
+                        for availabilitySvc<T>
+                    {
+                        type Response = super::AvailabilityResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AvailabilityRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).availability(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = availabilitySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/create_resource" => {
+                    #[allow(non_camel_case_types)]
+                    struct create_resourceSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::CreateResourceRequest>
+                        for create_resourceSvc<T>
+                    {
+                        type Response = super::CreateResourceResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateResourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).create_resource(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = create_resourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/update_resource" => {
+                    #[allow(non_camel_case_types)]
+                    struct update_resourceSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::UpdateResourceRequest>
+                        for update_resourceSvc<T>
+                    {
+                        type Response = super::UpdateResourceResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateResourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).update_resource(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = update_resourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/archive_resource" => {
+                    #[allow(non_camel_case_types)]
+                    struct archive_resourceSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::ArchiveResourceRequest>
+                        for archive_resourceSvc<T>
+                    {
+                        type Response = super::ArchiveResourceResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ArchiveResourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).archive_resource(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = archive_resourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/reservation.ReservationService/list_resources" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_resourcesSvc<T: ReservationService>(pub Arc<T>);
+                    impl<T: ReservationService>
+                        tonic::server::UnaryService<super::ListResourcesRequest>
+                        for list_resourcesSvc<T>
+                    {
+                        type Response = super::ListResourcesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListResourcesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_resources(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_resourcesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
                         let res = grpc.unary(method, req).await;
                         Ok(res)
                     };
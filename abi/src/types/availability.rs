@@ -0,0 +1,247 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::postgres::types::PgRange;
+
+use crate::{
+    convert_to_timestamp, convert_to_utc_time, get_timespan, validate_range, AvailabilityQuery,
+    Error, FreeSlot, ResourceId, Validator,
+};
+
+impl AvailabilityQuery {
+    pub fn new(
+        resource_ids: Vec<ResourceId>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            resource_ids,
+            start: Some(convert_to_timestamp(start)),
+            end: Some(convert_to_timestamp(end)),
+            duration: Some(prost_types::Duration {
+                seconds: duration.num_seconds(),
+                nanos: 0,
+            }),
+        }
+    }
+    pub fn get_timespan(&self) -> PgRange<DateTime<Utc>> {
+        get_timespan(self.start.as_ref(), self.end.as_ref())
+    }
+    /// the shortest free slot wanted, zero if it is not set
+    pub fn min_duration(&self) -> Duration {
+        self.duration
+            .as_ref()
+            .map(|d| Duration::seconds(d.seconds) + Duration::nanoseconds(d.nanos as i64))
+            .unwrap_or_else(Duration::zero)
+    }
+}
+
+impl Validator for AvailabilityQuery {
+    fn validate(&self) -> Result<(), Error> {
+        if self.resource_ids.is_empty() {
+            return Err(Error::InvalidResourceId(String::new()));
+        }
+        if let Some(rid) = self.resource_ids.iter().find(|rid| rid.is_empty()) {
+            return Err(Error::InvalidResourceId(rid.to_owned()));
+        }
+        if self.min_duration() < Duration::zero() {
+            return Err(Error::InvalidTime);
+        }
+        validate_range(self.start.as_ref(), self.end.as_ref())
+    }
+}
+
+impl FreeSlot {
+    pub fn new(rid: impl Into<String>, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            resource_id: rid.into(),
+            start: Some(convert_to_timestamp(start)),
+            end: Some(convert_to_timestamp(end)),
+        }
+    }
+    pub fn duration(&self) -> Duration {
+        match (self.start.as_ref(), self.end.as_ref()) {
+            (Some(start), Some(end)) => convert_to_utc_time(end) - convert_to_utc_time(start),
+            _ => Duration::zero(),
+        }
+    }
+}
+
+/// to find the periods in `[start, end)` when fewer than `capacity` of the `[start, end)` spans
+/// overlap, and which last at least `min_duration`.
+pub fn find_free_slots(
+    rid: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    spans: &[(DateTime<Utc>, DateTime<Utc>)],
+    capacity: usize,
+    min_duration: Duration,
+) -> Vec<FreeSlot> {
+    // +1 when a span starts, -1 when it ends, clipped to the window.
+    // the spans are half open, so at the same time an end is counted before a start.
+    let mut events: Vec<(DateTime<Utc>, i32)> = spans
+        .iter()
+        .filter(|(s, e)| s < e && *s < end && *e > start)
+        .flat_map(|(s, e)| [((*s).max(start), 1), ((*e).min(end), -1)])
+        .collect();
+    events.sort();
+
+    let mut slots = Vec::new();
+    let mut used = 0;
+    let mut free_since = Some(start);
+    for (at, delta) in events {
+        used += delta;
+        let free = used < capacity as i32;
+        match (free_since, free) {
+            (Some(since), false) => {
+                if at > since {
+                    slots.push((since, at));
+                }
+                free_since = None;
+            }
+            (None, true) => free_since = Some(at),
+            _ => {}
+        }
+    }
+    if let Some(since) = free_since {
+        if end > since {
+            slots.push((since, end));
+        }
+    }
+    slots
+        .into_iter()
+        .filter(|(s, e)| *e - *s >= min_duration)
+        .map(|(s, e)| FreeSlot::new(rid, s, e))
+        .collect()
+}
+
+/// the most of the `[start, end)` spans overlapping at the same time
+pub fn max_overlap(spans: &[(DateTime<Utc>, DateTime<Utc>)]) -> usize {
+    // an end is counted before a start at the same time, the same as `find_free_slots`
+    let mut events: Vec<(DateTime<Utc>, i32)> = spans
+        .iter()
+        .filter(|(s, e)| s < e)
+        .flat_map(|(s, e)| [(*s, 1), (*e, -1)])
+        .collect();
+    events.sort();
+    let mut used = 0;
+    let mut max = 0;
+    for (_, delta) in events {
+        used += delta;
+        max = max.max(used);
+    }
+    max as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn t(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+    #[test]
+    fn max_overlap_should_count_the_spans_at_the_busiest_time() {
+        assert_eq!(max_overlap(&[]), 0);
+        let spans = [
+            (t("2023-12-25T10:00:00Z"), t("2023-12-25T12:00:00Z")),
+            (t("2023-12-25T11:00:00Z"), t("2023-12-25T13:00:00Z")),
+            // touching the first one, so only two at once
+            (t("2023-12-25T12:00:00Z"), t("2023-12-25T14:00:00Z")),
+        ];
+        assert_eq!(max_overlap(&spans), 2);
+        assert_eq!(max_overlap(&spans[..1]), 1);
+    }
+    #[test]
+    fn find_free_slots_should_return_gaps_between_reservations() {
+        let spans = [
+            (t("2023-12-25T10:00:00Z"), t("2023-12-25T11:00:00Z")),
+            // touching the previous one, there is no gap in between
+            (t("2023-12-25T11:00:00Z"), t("2023-12-25T12:00:00Z")),
+            (t("2023-12-25T15:00:00Z"), t("2023-12-25T20:00:00Z")),
+        ];
+        let slots = find_free_slots(
+            "room",
+            t("2023-12-25T08:00:00Z"),
+            t("2023-12-25T18:00:00Z"),
+            &spans,
+            1,
+            Duration::zero(),
+        );
+        assert_eq!(
+            slots,
+            vec![
+                FreeSlot::new("room", t("2023-12-25T08:00:00Z"), t("2023-12-25T10:00:00Z")),
+                FreeSlot::new("room", t("2023-12-25T12:00:00Z"), t("2023-12-25T15:00:00Z")),
+            ]
+        );
+        // the gaps shorter than the duration are skipped
+        let slots = find_free_slots(
+            "room",
+            t("2023-12-25T08:00:00Z"),
+            t("2023-12-25T18:00:00Z"),
+            &spans,
+            1,
+            Duration::hours(3),
+        );
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].duration(), Duration::hours(3));
+    }
+    #[test]
+    fn find_free_slots_should_respect_capacity() {
+        let spans = [
+            (t("2023-12-25T10:00:00Z"), t("2023-12-25T13:00:00Z")),
+            (t("2023-12-25T12:00:00Z"), t("2023-12-25T14:00:00Z")),
+        ];
+        let slots = find_free_slots(
+            "desks",
+            t("2023-12-25T09:00:00Z"),
+            t("2023-12-25T15:00:00Z"),
+            &spans,
+            2,
+            Duration::zero(),
+        );
+        assert_eq!(
+            slots,
+            vec![
+                FreeSlot::new(
+                    "desks",
+                    t("2023-12-25T09:00:00Z"),
+                    t("2023-12-25T12:00:00Z")
+                ),
+                FreeSlot::new(
+                    "desks",
+                    t("2023-12-25T13:00:00Z"),
+                    t("2023-12-25T15:00:00Z")
+                ),
+            ]
+        );
+        let slots = find_free_slots(
+            "desks",
+            t("2023-12-25T09:00:00Z"),
+            t("2023-12-25T15:00:00Z"),
+            &[],
+            1,
+            Duration::zero(),
+        );
+        assert_eq!(slots.len(), 1);
+    }
+    #[test]
+    fn availability_query_should_be_validated() {
+        let query = AvailabilityQuery::new(
+            vec!["room".into()],
+            t("2023-12-25T09:00:00Z"),
+            t("2023-12-25T15:00:00Z"),
+            Duration::hours(1),
+        );
+        assert!(query.validate().is_ok());
+        assert_eq!(query.min_duration(), Duration::hours(1));
+        let mut invalid = query.clone();
+        invalid.resource_ids.clear();
+        assert_eq!(
+            invalid.validate(),
+            Err(Error::InvalidResourceId(String::new()))
+        );
+        let mut invalid = query;
+        invalid.end = invalid.start.clone();
+        assert_eq!(invalid.validate(), Err(Error::InvalidTime));
+    }
+}
@@ -6,9 +6,19 @@ use sqlx::postgres::types::PgRange;
 
 use crate::{convert_to_utc_time, Error};
 
+pub mod availability;
+pub mod recurrence;
 pub mod reservation;
+pub mod reservation_field;
 pub mod reservation_query;
 pub mod reservation_status;
+pub mod reservation_update_type;
+pub mod resource;
+
+pub use availability::{find_free_slots, max_overlap};
+pub use recurrence::{Frequency, Recurrence, WeekdayNum, MAX_OCCURRENCES};
+pub use reservation_field::ReservationField;
+pub use reservation_query::{QueryCursor, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
 
 pub fn validate_range(start: Option<&Timestamp>, end: Option<&Timestamp>) -> Result<(), Error> {
     if start.is_none() || end.is_none() {
@@ -25,10 +35,10 @@ pub fn validate_range(start: Option<&Timestamp>, end: Option<&Timestamp>) -> Res
 pub fn get_timespan(start: Option<&Timestamp>, end: Option<&Timestamp>) -> PgRange<DateTime<Utc>> {
     let start: DateTime<Utc> = convert_to_utc_time(&start.unwrap().clone());
     let end = convert_to_utc_time(&end.unwrap().clone());
-    return PgRange {
+    PgRange {
         start: Bound::Included(start),
         end: Bound::Excluded(end),
-    };
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,367 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+
+use crate::Error;
+
+/// a series could not have more occurrences than this
+pub const MAX_OCCURRENCES: usize = 500;
+/// to stop looking for the occurrences of a rule which hardly matches
+const MAX_PERIODS: i64 = 10_000;
+
+/// the subset of the RFC 5545 RRULE supported for the recurring reservations,
+/// e.g. `FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10`.
+/// the occurrences are computed in UTC, the first one is the first matching time on or after the start.
+/// there is no time zone, so all of them are at the same UTC time of the day:
+/// a series at 09:00 local time is at 08:00 or 10:00 local time once the daylight saving time changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recurrence {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<usize>,
+    pub until: Option<DateTime<Utc>>,
+    pub by_day: Vec<WeekdayNum>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// a BYDAY item, the ordinal (e.g. `1MO`, `-1FR`) is only allowed in a monthly rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WeekdayNum {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+impl FromStr for Recurrence {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidRecurrence(s.to_owned());
+        let rule = s.trim().strip_prefix("RRULE:").unwrap_or(s.trim());
+        let mut freq = None;
+        let mut interval = 1;
+        let mut count = None;
+        let mut until = None;
+        let mut by_day = Vec::new();
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(invalid)?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => return Err(invalid()),
+                    })
+                }
+                "INTERVAL" => interval = value.parse().map_err(|_| invalid())?,
+                "COUNT" => count = Some(value.parse().map_err(|_| invalid())?),
+                "UNTIL" => until = Some(parse_until(value).ok_or_else(invalid)?),
+                "BYDAY" => {
+                    by_day = value
+                        .split(',')
+                        .map(parse_weekday_num)
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        let freq = freq.ok_or_else(invalid)?;
+        // the series should end, by either COUNT or UNTIL but not both
+        if interval == 0
+            || count == Some(0)
+            || count > Some(MAX_OCCURRENCES)
+            || count.is_some() == until.is_some()
+        {
+            return Err(invalid());
+        }
+        if freq != Frequency::Monthly && by_day.iter().any(|d| d.ordinal.is_some()) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            freq,
+            interval,
+            count,
+            until,
+            by_day,
+        })
+    }
+}
+
+impl Recurrence {
+    /// the start times of the occurrences of the series starting at `start`
+    pub fn occurrences(&self, start: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>, Error> {
+        let time = start.time();
+        let first = start.date_naive();
+        let mut occurrences = Vec::new();
+        // every period is a day, a week or a month, depending on the frequency
+        for period in 0..MAX_PERIODS {
+            let days = self.period_days(first, period * self.interval as i64);
+            let days = match days {
+                Some(days) => days,
+                // no more representable dates
+                None => break,
+            };
+            for day in days {
+                let at = Utc.from_utc_datetime(&NaiveDateTime::new(day, time));
+                if at < start {
+                    continue;
+                }
+                if self.until.is_some_and(|until| at > until) {
+                    return Ok(occurrences);
+                }
+                // the COUNT is within the limit, only UNTIL could go over it
+                if occurrences.len() == MAX_OCCURRENCES {
+                    return Err(Error::InvalidRecurrence(format!(
+                        "more than {} occurrences",
+                        MAX_OCCURRENCES
+                    )));
+                }
+                occurrences.push(at);
+                if self.count == Some(occurrences.len()) {
+                    return Ok(occurrences);
+                }
+            }
+        }
+        Ok(occurrences)
+    }
+
+    /// the matching days of the `n`th day, week or month after `first`, in order
+    fn period_days(&self, first: NaiveDate, n: i64) -> Option<Vec<NaiveDate>> {
+        match self.freq {
+            Frequency::Daily => {
+                let day = first.checked_add_signed(Duration::days(n))?;
+                Some(if self.matches_weekday(day) {
+                    vec![day]
+                } else {
+                    vec![]
+                })
+            }
+            Frequency::Weekly => {
+                let monday = first - Duration::days(first.weekday().num_days_from_monday() as i64)
+                    + Duration::weeks(n);
+                let weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![first.weekday()]
+                } else {
+                    self.by_day.iter().map(|d| d.weekday).collect()
+                };
+                let mut days: Vec<NaiveDate> = weekdays
+                    .into_iter()
+                    .map(|w| monday + Duration::days(w.num_days_from_monday() as i64))
+                    .collect();
+                days.sort();
+                days.dedup();
+                Some(days)
+            }
+            Frequency::Monthly => {
+                let months = first.year() as i64 * 12 + first.month0() as i64 + n;
+                let year = i32::try_from(months.div_euclid(12)).ok()?;
+                let month = months.rem_euclid(12) as u32 + 1;
+                let first_of_month = NaiveDate::from_ymd_opt(year, month, 1)?;
+                if self.by_day.is_empty() {
+                    // the months without the day are skipped
+                    return Some(
+                        NaiveDate::from_ymd_opt(year, month, first.day())
+                            .into_iter()
+                            .collect(),
+                    );
+                }
+                let month_days: Vec<NaiveDate> = first_of_month
+                    .iter_days()
+                    .take_while(|d| d.month() == month)
+                    .collect();
+                let mut days: Vec<NaiveDate> = self
+                    .by_day
+                    .iter()
+                    .flat_map(|d| {
+                        let matching: Vec<NaiveDate> = month_days
+                            .iter()
+                            .copied()
+                            .filter(|day| day.weekday() == d.weekday)
+                            .collect();
+                        match d.ordinal {
+                            None => matching,
+                            Some(n) if n > 0 => {
+                                matching.get(n as usize - 1).copied().into_iter().collect()
+                            }
+                            Some(n) => matching
+                                .len()
+                                .checked_sub(n.unsigned_abs() as usize)
+                                .and_then(|i| matching.get(i).copied())
+                                .into_iter()
+                                .collect(),
+                        }
+                    })
+                    .collect();
+                days.sort();
+                days.dedup();
+                Some(days)
+            }
+        }
+    }
+
+    fn matches_weekday(&self, day: NaiveDate) -> bool {
+        self.by_day.is_empty() || self.by_day.iter().any(|d| d.weekday == day.weekday())
+    }
+}
+
+fn parse_until(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim_end_matches('Z');
+    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%S") {
+        return Some(Utc.from_utc_datetime(&dt));
+    }
+    // a date only UNTIL includes the whole day
+    let date = NaiveDate::parse_from_str(s, "%Y%m%d").ok()?;
+    Some(Utc.from_utc_datetime(&date.and_hms_opt(23, 59, 59)?))
+}
+
+fn parse_weekday_num(s: &str) -> Option<WeekdayNum> {
+    let s = s.trim();
+    if s.len() < 2 {
+        return None;
+    }
+    let (ordinal, day) = s.split_at(s.len() - 2);
+    let weekday = match day.to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let ordinal = match ordinal {
+        "" => None,
+        n => match n.parse::<i32>().ok()? {
+            n @ (1..=5 | -5..=-1) => Some(n),
+            _ => return None,
+        },
+    };
+    Some(WeekdayNum { ordinal, weekday })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn t(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+    #[test]
+    fn recurrence_should_parse() {
+        let rule: Recurrence = "RRULE:FREQ=MONTHLY;INTERVAL=2;BYDAY=1MO,-1FR;UNTIL=20231231"
+            .parse()
+            .unwrap();
+        assert_eq!(rule.freq, Frequency::Monthly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.until, Some(t("2023-12-31T23:59:59Z")));
+        assert_eq!(
+            rule.by_day,
+            vec![
+                WeekdayNum {
+                    ordinal: Some(1),
+                    weekday: Weekday::Mon
+                },
+                WeekdayNum {
+                    ordinal: Some(-1),
+                    weekday: Weekday::Fri
+                },
+            ]
+        );
+        for invalid in [
+            "FREQ=YEARLY;COUNT=2",
+            "FREQ=DAILY",
+            "FREQ=DAILY;COUNT=2;UNTIL=20231231",
+            "FREQ=WEEKLY;BYDAY=1MO;COUNT=2",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;BYHOUR=9;COUNT=2",
+        ] {
+            assert_eq!(
+                invalid.parse::<Recurrence>(),
+                Err(Error::InvalidRecurrence(invalid.into()))
+            );
+        }
+    }
+    #[test]
+    fn weekly_recurrence_should_expand() {
+        // 2023-12-25 is a Monday
+        let rule: Recurrence = "FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4".parse().unwrap();
+        assert_eq!(
+            rule.occurrences(t("2023-12-25T09:00:00Z")).unwrap(),
+            vec![
+                t("2023-12-25T09:00:00Z"),
+                t("2023-12-27T09:00:00Z"),
+                t("2024-01-01T09:00:00Z"),
+                t("2024-01-03T09:00:00Z"),
+            ]
+        );
+        // starting on a Tuesday, the Monday of the first week is skipped
+        let rule: Recurrence = "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO;UNTIL=20240122T090000Z"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule.occurrences(t("2023-12-26T09:00:00Z")).unwrap(),
+            vec![t("2024-01-08T09:00:00Z"), t("2024-01-22T09:00:00Z")]
+        );
+    }
+    #[test]
+    fn daily_and_monthly_recurrence_should_expand() {
+        let rule: Recurrence = "FREQ=DAILY;BYDAY=SA,SU;COUNT=3".parse().unwrap();
+        assert_eq!(
+            rule.occurrences(t("2023-12-25T09:00:00Z")).unwrap(),
+            vec![
+                t("2023-12-30T09:00:00Z"),
+                t("2023-12-31T09:00:00Z"),
+                t("2024-01-06T09:00:00Z"),
+            ]
+        );
+        // the months without the 31st are skipped
+        let rule: Recurrence = "FREQ=MONTHLY;COUNT=3".parse().unwrap();
+        assert_eq!(
+            rule.occurrences(t("2024-01-31T09:00:00Z")).unwrap(),
+            vec![
+                t("2024-01-31T09:00:00Z"),
+                t("2024-03-31T09:00:00Z"),
+                t("2024-05-31T09:00:00Z"),
+            ]
+        );
+        let rule: Recurrence = "FREQ=MONTHLY;BYDAY=-1FR;COUNT=2".parse().unwrap();
+        assert_eq!(
+            rule.occurrences(t("2024-01-01T09:00:00Z")).unwrap(),
+            vec![t("2024-01-26T09:00:00Z"), t("2024-02-23T09:00:00Z")]
+        );
+    }
+    #[test]
+    fn recurrence_should_limit_occurrences() {
+        let rule: Recurrence = "FREQ=DAILY;UNTIL=20991231".parse().unwrap();
+        assert!(rule.occurrences(t("2023-12-25T09:00:00Z")).is_err());
+        let rule: Recurrence = "FREQ=DAILY;COUNT=500".parse().unwrap();
+        let occurrences = rule.occurrences(t("2023-12-25T09:00:00Z")).unwrap();
+        assert_eq!(occurrences.len(), MAX_OCCURRENCES);
+        assert_eq!(
+            "FREQ=DAILY;COUNT=501".parse::<Recurrence>(),
+            Err(Error::InvalidRecurrence("FREQ=DAILY;COUNT=501".into()))
+        );
+        // exactly 500 days up to the UNTIL, 2023-12-25 + 499 days is 2025-05-07
+        let rule: Recurrence = "FREQ=DAILY;UNTIL=20250507".parse().unwrap();
+        let occurrences = rule.occurrences(t("2023-12-25T09:00:00Z")).unwrap();
+        assert_eq!(occurrences.len(), MAX_OCCURRENCES);
+        let rule: Recurrence = "FREQ=DAILY;UNTIL=20250508".parse().unwrap();
+        assert!(rule.occurrences(t("2023-12-25T09:00:00Z")).is_err());
+    }
+    #[test]
+    fn recurrence_should_keep_the_utc_time_across_dst() {
+        // 09:00 in New York is 14:00 UTC in winter, it is 10:00 there after 2024-03-10
+        let rule: Recurrence = "FREQ=WEEKLY;COUNT=2".parse().unwrap();
+        assert_eq!(
+            rule.occurrences(t("2024-03-04T14:00:00Z")).unwrap(),
+            vec![t("2024-03-04T14:00:00Z"), t("2024-03-11T14:00:00Z")]
+        );
+    }
+}
@@ -7,7 +7,12 @@ use sqlx::{
     FromRow, Row,
 };
 
-use crate::{convert_to_timestamp, get_timespan, Reservation, ReservationStatus, RsvpStatus};
+use prost_types::Timestamp;
+
+use crate::{
+    convert_to_timestamp, convert_to_utc_time, get_timespan, Error, Recurrence, Reservation,
+    ReservationStatus, RsvpStatus,
+};
 
 impl Reservation {
     pub fn new_pending(
@@ -25,10 +30,52 @@ impl Reservation {
             end: Some(convert_to_timestamp(end.with_timezone(&Utc))),
             note: note.into(),
             status: ReservationStatus::Pending as i32,
+            cancelled_at: None,
+            cancelled_by: String::new(),
+            cancel_reason: String::new(),
+            hold_expires_at: None,
+            recurrence: String::new(),
+            series_id: String::new(),
+            version: 0,
+            updated_at: None,
         }
     }
+    /// to repeat the reservation by the `rule`, e.g. `FREQ=WEEKLY;COUNT=4`
+    pub fn with_recurrence(mut self, rule: impl Into<String>) -> Self {
+        self.recurrence = rule.into();
+        self
+    }
+    pub fn is_recurring(&self) -> bool {
+        !self.recurrence.is_empty()
+    }
+    /// to expand the recurring reservation into its occurrences,
+    /// which are all kept the same duration as the first one.
+    pub fn occurrences(&self) -> Result<Vec<Reservation>, Error> {
+        let rule: Recurrence = self.recurrence.parse()?;
+        let (start, end) = self.get_span();
+        let duration = end - start;
+        Ok(rule
+            .occurrences(start)?
+            .into_iter()
+            .map(|start| Reservation {
+                start: Some(convert_to_timestamp(start)),
+                end: Some(convert_to_timestamp(start + duration)),
+                ..self.clone()
+            })
+            .collect())
+    }
+    /// to cancel the pending reservation automatically if it is not confirmed by `expires_at`
+    pub fn with_hold_expires_at(mut self, expires_at: DateTime<FixedOffset>) -> Self {
+        self.hold_expires_at = Some(convert_to_timestamp(expires_at.with_timezone(&Utc)));
+        self
+    }
     pub fn get_timespan(&self) -> PgRange<DateTime<Utc>> {
-        return get_timespan(self.start.as_ref(), self.end.as_ref());
+        get_timespan(self.start.as_ref(), self.end.as_ref())
+    }
+    /// start and end time of the reservation, the epoch if not set
+    pub fn get_span(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let f = |ts: Option<&Timestamp>| ts.map(convert_to_utc_time).unwrap_or_default();
+        (f(self.start.as_ref()), f(self.end.as_ref()))
     }
 }
 
@@ -43,6 +90,13 @@ impl FromRow<'_, PgRow> for Reservation {
         let start = range.start.unwrap();
         let end = range.end.unwrap();
         let status: RsvpStatus = row.get("status");
+        let cancelled_at: Option<DateTime<Utc>> = row.get("cancelled_at");
+        let cancelled_by: Option<String> = row.get("cancelled_by");
+        let cancel_reason: Option<String> = row.get("cancel_reason");
+        let hold_expires_at: Option<DateTime<Utc>> = row.get("hold_expires_at");
+        let recurrence: Option<String> = row.get("recurrence");
+        let series_id: Option<Uuid> = row.get("series_id");
+        let updated_at: DateTime<Utc> = row.get("updated_at");
         Ok(Self {
             id: id.to_string(),
             user_id: row.get("user_id"),
@@ -51,6 +105,14 @@ impl FromRow<'_, PgRow> for Reservation {
             end: Some(convert_to_timestamp(end)),
             note: row.get("note"),
             status: ReservationStatus::from(status) as i32,
+            cancelled_at: cancelled_at.map(convert_to_timestamp),
+            cancelled_by: cancelled_by.unwrap_or_default(),
+            cancel_reason: cancel_reason.unwrap_or_default(),
+            hold_expires_at: hold_expires_at.map(convert_to_timestamp),
+            recurrence: recurrence.unwrap_or_default(),
+            series_id: series_id.map(|id| id.to_string()).unwrap_or_default(),
+            version: row.get("version"),
+            updated_at: Some(convert_to_timestamp(updated_at)),
         })
     }
 }
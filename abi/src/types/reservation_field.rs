@@ -0,0 +1,105 @@
+use std::{fmt, str::FromStr};
+
+use crate::{Error, Reservation, ReservationStatus};
+
+/// the fields of a reservation which could be changed by an update,
+/// named by the paths of the update mask.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReservationField {
+    Note,
+    Start,
+    End,
+    ResourceId,
+}
+
+impl ReservationField {
+    /// to copy the field from `from` to `to`
+    pub fn apply(self, from: &Reservation, to: &mut Reservation) {
+        match self {
+            ReservationField::Note => to.note = from.note.clone(),
+            ReservationField::Start => to.start = from.start.clone(),
+            ReservationField::End => to.end = from.end.clone(),
+            ReservationField::ResourceId => to.resource_id = from.resource_id.clone(),
+        }
+    }
+    /// whether a change of the field needs the time slot checked again
+    pub fn is_schedule(self) -> bool {
+        self != ReservationField::Note
+    }
+    /// to check the fields could be changed on a reservation of the status,
+    /// the time slot of a cancelled, completed or no-show one is kept as it was.
+    pub fn check_update(
+        fields: &[ReservationField],
+        status: ReservationStatus,
+    ) -> Result<(), Error> {
+        if status.is_final() && fields.iter().any(|field| field.is_schedule()) {
+            return Err(Error::InactiveReservation(status));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ReservationField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReservationField::Note => write!(f, "note"),
+            ReservationField::Start => write!(f, "start"),
+            ReservationField::End => write!(f, "end"),
+            ReservationField::ResourceId => write!(f, "resource_id"),
+        }
+    }
+}
+
+impl FromStr for ReservationField {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "note" => Ok(ReservationField::Note),
+            "start" => Ok(ReservationField::Start),
+            "end" => Ok(ReservationField::End),
+            "resource_id" => Ok(ReservationField::ResourceId),
+            _ => Err(Error::InvalidUpdateMask(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn reservation_field_should_parse_the_mask_paths() {
+        for field in [
+            ReservationField::Note,
+            ReservationField::Start,
+            ReservationField::End,
+            ReservationField::ResourceId,
+        ] {
+            assert_eq!(field.to_string().parse::<ReservationField>(), Ok(field));
+        }
+        assert_eq!(
+            "status".parse::<ReservationField>(),
+            Err(Error::InvalidUpdateMask("status".into()))
+        );
+    }
+    #[test]
+    fn check_update_should_keep_the_schedule_of_final_reservations() {
+        let schedule = [ReservationField::Start, ReservationField::End];
+        assert!(ReservationField::check_update(&schedule, ReservationStatus::Confirmed).is_ok());
+        assert!(ReservationField::check_update(
+            &[ReservationField::Note],
+            ReservationStatus::Cancelled
+        )
+        .is_ok());
+        for status in [
+            ReservationStatus::Cancelled,
+            ReservationStatus::Completed,
+            ReservationStatus::NoShow,
+        ] {
+            assert_eq!(
+                ReservationField::check_update(&[ReservationField::ResourceId], status),
+                Err(Error::InactiveReservation(status))
+            );
+        }
+    }
+}
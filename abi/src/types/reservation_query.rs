@@ -1,12 +1,30 @@
-use chrono::{DateTime, Utc};
-use sqlx::postgres::types::PgRange;
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::{postgres::types::PgRange, types::Uuid};
 
 use crate::{
-    convert_to_timestamp, get_timespan, validate_range, Error, ReservationQuery, ReservationStatus,
-    Validator,
+    convert_to_timestamp, convert_to_utc_time, get_timespan, validate_range, Error,
+    PaginationConfig, QueryMatchMode, QueryRequest, Reservation, ReservationQuery,
+    ReservationStatus, Validator,
 };
 
+/// the page size if it is not given, unless it is configured
+pub const DEFAULT_PAGE_SIZE: i32 = 10;
+/// a page could not be larger than this, unless it is configured
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+/// position of a reservation in the order of the query, i.e. by start time then id,
+/// the next page starts right after it.
+/// it is encoded into an opaque string for the client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryCursor {
+    pub start: DateTime<Utc>,
+    pub id: Uuid,
+}
+
 impl ReservationQuery {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         uid: impl Into<String>,
         rid: impl Into<String>,
@@ -26,13 +44,109 @@ impl ReservationQuery {
             page,
             page_size,
             desc,
+            cursor: String::new(),
+            match_mode: QueryMatchMode::Contained as i32,
         }
     }
     pub fn get_status(&self) -> ReservationStatus {
-        return ReservationStatus::from_i32(self.status).unwrap();
+        ReservationStatus::from_i32(self.status).unwrap()
     }
     pub fn get_timespan(&self) -> PgRange<DateTime<Utc>> {
-        return get_timespan(self.start.as_ref(), self.end.as_ref());
+        get_timespan(self.start.as_ref(), self.end.as_ref())
+    }
+    /// the match mode, contained if it is unknown
+    pub fn get_match_mode(&self) -> QueryMatchMode {
+        QueryMatchMode::from_i32(self.match_mode).unwrap_or(QueryMatchMode::Contained)
+    }
+    /// the page size within the `limits`, the default one if it is not given
+    pub fn get_page_size(&self, limits: &PaginationConfig) -> Result<i32, Error> {
+        match self.page_size {
+            0 => Ok(limits.default_page_size),
+            size if (1..=limits.max_page_size).contains(&size) => Ok(size),
+            size => Err(Error::InvalidPageSize(size)),
+        }
+    }
+    /// the cursor to start after, `None` to start from the first page
+    pub fn get_cursor(&self) -> Result<Option<QueryCursor>, Error> {
+        match self.cursor.as_str() {
+            "" => Ok(None),
+            cursor => cursor.parse().map(Some),
+        }
+    }
+}
+
+impl QueryRequest {
+    /// the query of the request, or the one made of the filters sent by the clients built before it
+    #[allow(deprecated)]
+    pub fn into_query(self) -> ReservationQuery {
+        match self.query {
+            Some(query) => query,
+            None => ReservationQuery {
+                resource_id: self.resource_id,
+                user_id: self.user_id,
+                status: self.status,
+                start: self.start,
+                end: self.end,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<ReservationQuery> for QueryRequest {
+    fn from(query: ReservationQuery) -> Self {
+        Self {
+            query: Some(query),
+            ..Default::default()
+        }
+    }
+}
+
+impl QueryCursor {
+    /// the cursor right after the reservation
+    pub fn after(rsvp: &Reservation) -> Result<Self, Error> {
+        let start = rsvp.start.as_ref().ok_or(Error::InvalidTime)?;
+        let id =
+            Uuid::parse_str(&rsvp.id).map_err(|_| Error::InvalidReservationId(rsvp.id.clone()))?;
+        Ok(Self {
+            start: convert_to_utc_time(start),
+            id,
+        })
+    }
+}
+
+impl fmt::Display for QueryCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let raw = format!("{}/{}", self.start.timestamp_micros(), self.id.simple());
+        raw.bytes().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+impl FromStr for QueryCursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidCursor(s.to_owned());
+        if s.len() % 2 != 0 || !s.is_ascii() {
+            return Err(invalid());
+        }
+        let raw = (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (micros, id) = raw.split_once('/').ok_or_else(invalid)?;
+        let micros: i64 = micros.parse().map_err(|_| invalid())?;
+        let start = NaiveDateTime::from_timestamp_opt(
+            micros.div_euclid(1_000_000),
+            (micros.rem_euclid(1_000_000) * 1_000) as u32,
+        )
+        .ok_or_else(invalid)?;
+        Ok(Self {
+            start: DateTime::from_utc(start, Utc),
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
     }
 }
 
@@ -47,3 +161,73 @@ impl Validator for ReservationQuery {
         validate_range(self.start.as_ref(), self.end.as_ref())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn query_cursor_should_round_trip() {
+        let cursor = QueryCursor {
+            start: "2023-12-25T22:00:00.123456Z".parse().unwrap(),
+            id: Uuid::new_v4(),
+        };
+        let encoded = cursor.to_string();
+        assert!(encoded.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(encoded.parse::<QueryCursor>().unwrap(), cursor);
+        for invalid in ["abc", "zz", "2f"] {
+            assert_eq!(
+                invalid.parse::<QueryCursor>(),
+                Err(Error::InvalidCursor(invalid.into()))
+            );
+        }
+    }
+    #[test]
+    #[allow(deprecated)]
+    fn query_request_should_fall_back_to_the_deprecated_filters() {
+        let query = ReservationQuery {
+            resource_id: "room".into(),
+            page_size: 20,
+            ..Default::default()
+        };
+        let request = QueryRequest {
+            user_id: "ignored".into(),
+            ..QueryRequest::from(query.clone())
+        };
+        assert_eq!(request.into_query(), query);
+        let request = QueryRequest {
+            resource_id: "room".into(),
+            user_id: "leon".into(),
+            status: ReservationStatus::Confirmed as i32,
+            ..Default::default()
+        };
+        let query = request.into_query();
+        assert_eq!(query.resource_id, "room");
+        assert_eq!(query.user_id, "leon");
+        assert_eq!(query.get_status(), ReservationStatus::Confirmed);
+        assert_eq!(query.page_size, 0);
+    }
+    #[test]
+    fn page_size_should_be_limited() {
+        let query = |page_size| ReservationQuery {
+            page_size,
+            ..Default::default()
+        };
+        let limits = PaginationConfig::default();
+        assert_eq!(query(0).get_page_size(&limits), Ok(DEFAULT_PAGE_SIZE));
+        assert_eq!(query(100).get_page_size(&limits), Ok(100));
+        assert_eq!(
+            query(101).get_page_size(&limits),
+            Err(Error::InvalidPageSize(101))
+        );
+        assert_eq!(
+            query(-1).get_page_size(&limits),
+            Err(Error::InvalidPageSize(-1))
+        );
+        let limits = PaginationConfig {
+            default_page_size: 20,
+            max_page_size: 500,
+        };
+        assert_eq!(query(0).get_page_size(&limits), Ok(20));
+        assert_eq!(query(500).get_page_size(&limits), Ok(500));
+    }
+}
@@ -1,6 +1,101 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 
-use crate::{ReservationStatus, RsvpStatus};
+use crate::{Error, ReservationStatus, RsvpStatus};
+
+impl ReservationStatus {
+    /// statuses which keep the time slot, the same as the ones counted
+    /// by the `reservations_capacity_trigger`.
+    pub const BLOCKING: [ReservationStatus; 5] = [
+        ReservationStatus::Pending,
+        ReservationStatus::Confirmed,
+        ReservationStatus::Blocked,
+        ReservationStatus::CheckedIn,
+        ReservationStatus::Completed,
+    ];
+
+    /// statuses a reservation could be made with, the others are only reached by a transition
+    pub const INITIAL: [ReservationStatus; 3] = [
+        ReservationStatus::Pending,
+        ReservationStatus::Confirmed,
+        ReservationStatus::Blocked,
+    ];
+
+    /// to check a new reservation could be made with the status,
+    /// a reservation made without one is pending.
+    pub fn initial(status: i32) -> Result<ReservationStatus, Error> {
+        match ReservationStatus::from_i32(status) {
+            None | Some(ReservationStatus::Unknown) => Ok(ReservationStatus::Pending),
+            Some(status) if Self::INITIAL.contains(&status) => Ok(status),
+            Some(status) => Err(Error::InvalidStatusTransition {
+                from: ReservationStatus::Unknown,
+                to: status,
+            }),
+        }
+    }
+
+    /// statuses the reservation could move to from the current one
+    pub fn next_statuses(&self) -> &'static [ReservationStatus] {
+        match self {
+            ReservationStatus::Pending => {
+                &[ReservationStatus::Confirmed, ReservationStatus::Cancelled]
+            }
+            ReservationStatus::Confirmed => &[
+                ReservationStatus::CheckedIn,
+                ReservationStatus::Cancelled,
+                ReservationStatus::NoShow,
+            ],
+            ReservationStatus::Blocked => &[ReservationStatus::Cancelled],
+            ReservationStatus::CheckedIn => &[ReservationStatus::Completed],
+            ReservationStatus::Unknown
+            | ReservationStatus::Cancelled
+            | ReservationStatus::Completed
+            | ReservationStatus::NoShow => &[],
+        }
+    }
+
+    /// statuses the reservation could move to `to` from
+    pub fn previous_statuses(to: ReservationStatus) -> Vec<ReservationStatus> {
+        ReservationStatus::all()
+            .into_iter()
+            .filter(|from| from.can_transition_to(to))
+            .collect()
+    }
+
+    pub fn can_transition_to(&self, to: ReservationStatus) -> bool {
+        self.next_statuses().contains(&to)
+    }
+
+    /// to check the transition to `to`
+    pub fn transition_to(&self, to: ReservationStatus) -> Result<ReservationStatus, Error> {
+        if self.can_transition_to(to) {
+            Ok(to)
+        } else {
+            Err(Error::InvalidStatusTransition { from: *self, to })
+        }
+    }
+
+    /// no transition is allowed from a final status
+    pub fn is_final(&self) -> bool {
+        self.next_statuses().is_empty()
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        Self::BLOCKING.contains(self)
+    }
+
+    fn all() -> [ReservationStatus; 8] {
+        [
+            ReservationStatus::Unknown,
+            ReservationStatus::Pending,
+            ReservationStatus::Confirmed,
+            ReservationStatus::Blocked,
+            ReservationStatus::Cancelled,
+            ReservationStatus::CheckedIn,
+            ReservationStatus::Completed,
+            ReservationStatus::NoShow,
+        ]
+    }
+}
 
 impl From<RsvpStatus> for ReservationStatus {
     fn from(status: RsvpStatus) -> Self {
@@ -9,17 +104,93 @@ impl From<RsvpStatus> for ReservationStatus {
             RsvpStatus::Pending => ReservationStatus::Pending,
             RsvpStatus::Confirmed => ReservationStatus::Confirmed,
             RsvpStatus::Blocked => ReservationStatus::Blocked,
+            RsvpStatus::Cancelled => ReservationStatus::Cancelled,
+            RsvpStatus::CheckedIn => ReservationStatus::CheckedIn,
+            RsvpStatus::Completed => ReservationStatus::Completed,
+            RsvpStatus::NoShow => ReservationStatus::NoShow,
         }
     }
 }
 
+/// the same names as the values of `rsvp.reservation_status`
 impl fmt::Display for ReservationStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ReservationStatus::Pending => write!(f, "pending"),
             &ReservationStatus::Blocked => write!(f, "blocked"),
             ReservationStatus::Confirmed => write!(f, "confirmed"),
+            ReservationStatus::Cancelled => write!(f, "cancelled"),
+            ReservationStatus::CheckedIn => write!(f, "checked_in"),
+            ReservationStatus::Completed => write!(f, "completed"),
+            ReservationStatus::NoShow => write!(f, "no_show"),
             &ReservationStatus::Unknown => write!(f, "unknown"),
         }
     }
 }
+
+impl FromStr for ReservationStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReservationStatus::all()
+            .into_iter()
+            .find(|status| status.to_string() == s)
+            .ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn status_transitions_should_follow_the_lifecycle() {
+        use ReservationStatus::*;
+        assert!(Pending.can_transition_to(Confirmed));
+        assert!(Confirmed.can_transition_to(CheckedIn));
+        assert!(CheckedIn.can_transition_to(Completed));
+        assert!(Confirmed.can_transition_to(NoShow));
+        assert!(Blocked.can_transition_to(Cancelled));
+        assert!(!Pending.can_transition_to(CheckedIn));
+        assert!(!Confirmed.can_transition_to(Confirmed));
+        assert!(!CheckedIn.can_transition_to(Cancelled));
+        for status in [Cancelled, Completed, NoShow] {
+            assert!(status.is_final());
+        }
+        assert_eq!(
+            ReservationStatus::previous_statuses(Cancelled),
+            vec![Pending, Confirmed, Blocked]
+        );
+        assert_eq!(
+            Completed.transition_to(Pending),
+            Err(Error::InvalidStatusTransition {
+                from: Completed,
+                to: Pending
+            })
+        );
+    }
+    #[test]
+    fn initial_status_should_be_pending_confirmed_or_blocked() {
+        use ReservationStatus::*;
+        assert_eq!(ReservationStatus::initial(Unknown as i32), Ok(Pending));
+        assert_eq!(ReservationStatus::initial(42), Ok(Pending));
+        for status in ReservationStatus::INITIAL {
+            assert_eq!(ReservationStatus::initial(status as i32), Ok(status));
+        }
+        for status in [Cancelled, CheckedIn, Completed, NoShow] {
+            assert_eq!(
+                ReservationStatus::initial(status as i32),
+                Err(Error::InvalidStatusTransition {
+                    from: Unknown,
+                    to: status
+                })
+            );
+        }
+    }
+    #[test]
+    fn status_should_parse_from_its_name() {
+        for status in ReservationStatus::all() {
+            assert_eq!(status.to_string().parse(), Ok(status));
+        }
+        assert_eq!("checked-in".parse::<ReservationStatus>(), Err(()));
+    }
+}
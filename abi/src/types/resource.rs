@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+use crate::{convert_to_timestamp, Error, Resource, Validator};
+
+impl Resource {
+    pub fn new(id: impl Into<String>, name: impl Into<String>, capacity: i32) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: String::new(),
+            capacity,
+            archived_at: None,
+        }
+    }
+    /// capacity stored for a new resource, 1 if it is not set
+    pub fn effective_capacity(&self) -> i32 {
+        if self.capacity == 0 {
+            1
+        } else {
+            self.capacity
+        }
+    }
+    /// capacity stored on update, the `current` one is kept if it is not set
+    pub fn updated_capacity(&self, current: i32) -> i32 {
+        if self.capacity == 0 {
+            current
+        } else {
+            self.capacity
+        }
+    }
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+}
+
+impl Validator for Resource {
+    fn validate(&self) -> Result<(), Error> {
+        if self.id.is_empty() || self.id.len() > 64 {
+            return Err(Error::InvalidResourceId(self.id.to_owned()));
+        }
+        if self.capacity < 0 {
+            return Err(Error::InvalidCapacity(self.capacity));
+        }
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for Resource {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let description: Option<String> = row.get("description");
+        let archived_at: Option<DateTime<Utc>> = row.get("archived_at");
+        Ok(Self {
+            id: row.get("id"),
+            name: row.get("name"),
+            description: description.unwrap_or_default(),
+            capacity: row.get("capacity"),
+            archived_at: archived_at.map(convert_to_timestamp),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn resource_should_be_validated() {
+        assert!(Resource::new("ocean-view-room-777", "", 0)
+            .validate()
+            .is_ok());
+        assert_eq!(
+            Resource::new("", "", 1).validate(),
+            Err(Error::InvalidResourceId("".into()))
+        );
+        assert_eq!(
+            Resource::new("ocean-view-room-777", "", -1).validate(),
+            Err(Error::InvalidCapacity(-1))
+        );
+        assert_eq!(Resource::new("a", "", 0).effective_capacity(), 1);
+        assert_eq!(Resource::new("a", "", 3).effective_capacity(), 3);
+        assert_eq!(Resource::new("a", "", 0).updated_capacity(5), 5);
+        assert_eq!(Resource::new("a", "", 2).updated_capacity(5), 2);
+    }
+}
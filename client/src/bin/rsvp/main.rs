@@ -0,0 +1,406 @@
+//! command line client of the reservation service, e.g. for the front desk and ops.
+//!
+//! `rsvp --server http://localhost:50051 query --resource ocean-view-room-777 --start ... --end ...`
+
+use abi::{
+    AvailabilityQuery, QueryMatchMode, Reservation, ReservationField, ReservationQuery,
+    ReservationStatus, Resource,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use client::ReservationClient;
+use futures::StreamExt;
+use prost_types::Timestamp;
+
+mod output;
+
+use output::Format;
+
+#[derive(Debug, Parser)]
+#[command(name = "rsvp", about = "manage the reservations from the terminal")]
+struct Cli {
+    /// url of the reservation service
+    #[arg(long, env = "RSVP_SERVER", default_value = "http://127.0.0.1:50051")]
+    server: String,
+    /// how the results are printed
+    #[arg(long, value_enum, default_value = "table")]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// to make a reservation, or a series of them with `--recurrence`
+    Reserve(ReserveArgs),
+    /// to confirm a pending reservation
+    Confirm {
+        id: String,
+        /// the retries with the same key get the original result
+        #[arg(long, default_value = "")]
+        key: String,
+    },
+    /// to move a reservation to another status, e.g. `checked_in`
+    Transition { id: String, status: Status },
+    /// to update the note, the time or the resource of a reservation
+    Update(UpdateArgs),
+    /// to cancel a reservation
+    Cancel {
+        id: String,
+        /// who cancelled it
+        #[arg(long, default_value = "")]
+        by: String,
+        #[arg(long, default_value = "")]
+        reason: String,
+        /// the retries with the same key get the original result
+        #[arg(long, default_value = "")]
+        key: String,
+    },
+    /// to get a reservation by id
+    Get { id: String },
+    /// to query the reservations, all of them unless `--page-size` is given
+    Query(QueryArgs),
+    /// to find the free time slots of the resources
+    Availability {
+        /// the resources to look at, could be repeated
+        #[arg(long = "resource", required = true)]
+        resources: Vec<String>,
+        #[arg(long)]
+        start: Timestamp,
+        #[arg(long)]
+        end: Timestamp,
+        /// the shortest free slot wanted
+        #[arg(long, default_value_t = 0)]
+        min_duration_secs: i64,
+    },
+    /// to manage the occurrences of a recurring reservation
+    #[command(subcommand)]
+    Series(SeriesCommand),
+    /// to manage the resources which could be reserved
+    #[command(subcommand)]
+    Resource(ResourceCommand),
+    /// to print the changes made to the reservations as they happen
+    Watch {
+        /// to replay the changes recorded after this one first
+        #[arg(long)]
+        after: Option<i64>,
+    },
+}
+
+#[derive(Debug, Args)]
+struct ReserveArgs {
+    #[arg(long)]
+    user: String,
+    #[arg(long)]
+    resource: String,
+    #[arg(long)]
+    start: Timestamp,
+    #[arg(long)]
+    end: Timestamp,
+    #[arg(long, default_value = "")]
+    note: String,
+    /// the rule to repeat it, e.g. `FREQ=WEEKLY;COUNT=4`
+    #[arg(long)]
+    recurrence: Option<String>,
+    /// to hold a pending reservation until this time only
+    #[arg(long)]
+    hold_until: Option<Timestamp>,
+    /// the retries with the same key get the original result
+    #[arg(long, default_value = "")]
+    key: String,
+}
+
+#[derive(Debug, Args)]
+struct UpdateArgs {
+    id: String,
+    /// the version updated, the current one if it is not given
+    #[arg(long)]
+    version: Option<i64>,
+    #[arg(long)]
+    note: Option<String>,
+    #[arg(long)]
+    start: Option<Timestamp>,
+    #[arg(long)]
+    end: Option<Timestamp>,
+    #[arg(long)]
+    resource: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct QueryArgs {
+    #[arg(long)]
+    user: Option<String>,
+    #[arg(long)]
+    resource: Option<String>,
+    #[arg(long)]
+    start: Timestamp,
+    #[arg(long)]
+    end: Timestamp,
+    #[arg(long, value_enum, default_value = "pending")]
+    status: Status,
+    #[arg(long, value_enum, default_value = "contained")]
+    mode: MatchMode,
+    /// to order by start time descending
+    #[arg(long)]
+    desc: bool,
+    /// to query one page only
+    #[arg(long)]
+    page_size: Option<i32>,
+    /// to query the page after the cursor printed with the previous one
+    #[arg(long, default_value = "")]
+    cursor: String,
+}
+
+#[derive(Debug, Subcommand)]
+enum SeriesCommand {
+    /// to get the occurrences of a series
+    Get { series_id: String },
+    /// to cancel the occurrences of a series which are not over yet
+    Cancel {
+        series_id: String,
+        #[arg(long, default_value = "")]
+        by: String,
+        #[arg(long, default_value = "")]
+        reason: String,
+    },
+    /// to update the note of the occurrences of a series
+    Note { series_id: String, note: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum ResourceCommand {
+    /// to register a resource
+    Create(ResourceArgs),
+    /// to update the name, description and capacity of a resource
+    Update(ResourceArgs),
+    /// to archive a resource, so it could no longer be reserved
+    Archive { id: String },
+    /// to list the resources
+    List {
+        #[arg(long)]
+        include_archived: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+struct ResourceArgs {
+    id: String,
+    #[arg(long, default_value = "")]
+    name: String,
+    #[arg(long, default_value = "")]
+    description: String,
+    /// 1 for a new resource if it is not given, the current one is kept on update
+    #[arg(long, default_value_t = 0)]
+    capacity: i32,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+#[value(rename_all = "snake_case")]
+enum Status {
+    Pending,
+    Blocked,
+    Confirmed,
+    Cancelled,
+    CheckedIn,
+    Completed,
+    NoShow,
+}
+
+impl From<Status> for ReservationStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Pending => ReservationStatus::Pending,
+            Status::Blocked => ReservationStatus::Blocked,
+            Status::Confirmed => ReservationStatus::Confirmed,
+            Status::Cancelled => ReservationStatus::Cancelled,
+            Status::CheckedIn => ReservationStatus::CheckedIn,
+            Status::Completed => ReservationStatus::Completed,
+            Status::NoShow => ReservationStatus::NoShow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum MatchMode {
+    Contained,
+    Overlapping,
+    StartingWithin,
+}
+
+impl From<MatchMode> for QueryMatchMode {
+    fn from(mode: MatchMode) -> Self {
+        match mode {
+            MatchMode::Contained => QueryMatchMode::Contained,
+            MatchMode::Overlapping => QueryMatchMode::Overlapping,
+            MatchMode::StartingWithin => QueryMatchMode::StartingWithin,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let format = cli.format;
+    let mut client = ReservationClient::connect(cli.server).await?;
+    match cli.command {
+        Command::Reserve(args) => {
+            let mut rsvp = Reservation {
+                user_id: args.user,
+                resource_id: args.resource,
+                start: Some(args.start),
+                end: Some(args.end),
+                note: args.note,
+                status: ReservationStatus::Pending as i32,
+                hold_expires_at: args.hold_until,
+                ..Default::default()
+            };
+            match args.recurrence {
+                Some(rule) => {
+                    rsvp.recurrence = rule;
+                    let occurrences = client.reserve_series_with_key(rsvp, args.key).await?;
+                    output::print_reservations(format, &occurrences);
+                }
+                None => {
+                    let rsvp = client.reserve_with_key(rsvp, args.key).await?;
+                    output::print_reservation(format, &rsvp);
+                }
+            }
+        }
+        Command::Confirm { id, key } => {
+            let rsvp = client.confirm_with_key(id, key).await?;
+            output::print_reservation(format, &rsvp);
+        }
+        Command::Transition { id, status } => {
+            let rsvp = client.transition(id, status.into()).await?;
+            output::print_reservation(format, &rsvp);
+        }
+        Command::Update(args) => {
+            let version = match args.version {
+                Some(version) => version,
+                None => client.get(args.id.clone()).await?.version,
+            };
+            let mut fields = Vec::new();
+            let mut rsvp = Reservation {
+                id: args.id,
+                version,
+                ..Default::default()
+            };
+            if let Some(note) = args.note {
+                rsvp.note = note;
+                fields.push(ReservationField::Note);
+            }
+            if let Some(start) = args.start {
+                rsvp.start = Some(start);
+                fields.push(ReservationField::Start);
+            }
+            if let Some(end) = args.end {
+                rsvp.end = Some(end);
+                fields.push(ReservationField::End);
+            }
+            if let Some(resource) = args.resource {
+                rsvp.resource_id = resource;
+                fields.push(ReservationField::ResourceId);
+            }
+            if fields.is_empty() {
+                anyhow::bail!("nothing to update, give --note, --start, --end or --resource");
+            }
+            let rsvp = client.update(rsvp, &fields).await?;
+            output::print_reservation(format, &rsvp);
+        }
+        Command::Cancel {
+            id,
+            by,
+            reason,
+            key,
+        } => {
+            let rsvp = client.cancel_with_key(id, by, reason, key).await?;
+            output::print_reservation(format, &rsvp);
+        }
+        Command::Get { id } => {
+            let rsvp = client.get(id).await?;
+            output::print_reservation(format, &rsvp);
+        }
+        Command::Query(args) => {
+            let query = ReservationQuery {
+                user_id: args.user.unwrap_or_default(),
+                resource_id: args.resource.unwrap_or_default(),
+                start: Some(args.start),
+                end: Some(args.end),
+                status: ReservationStatus::from(args.status) as i32,
+                desc: args.desc,
+                page_size: args.page_size.unwrap_or_default(),
+                cursor: args.cursor,
+                match_mode: QueryMatchMode::from(args.mode) as i32,
+                ..Default::default()
+            };
+            if args.page_size.is_some() {
+                let page = client.query_page(query).await?;
+                output::print_page(format, &page);
+            } else {
+                let mut stream = client.query(query).await?;
+                let mut rsvps = Vec::new();
+                while let Some(rsvp) = stream.next().await {
+                    rsvps.push(rsvp?);
+                }
+                output::print_reservations(format, &rsvps);
+            }
+        }
+        Command::Availability {
+            resources,
+            start,
+            end,
+            min_duration_secs,
+        } => {
+            let query = AvailabilityQuery {
+                resource_ids: resources,
+                start: Some(start),
+                end: Some(end),
+                duration: Some(prost_types::Duration {
+                    seconds: min_duration_secs,
+                    nanos: 0,
+                }),
+            };
+            let slots = client.availability(query).await?;
+            output::print_slots(format, &slots);
+        }
+        Command::Series(command) => {
+            let occurrences = match command {
+                SeriesCommand::Get { series_id } => client.get_series(series_id).await?,
+                SeriesCommand::Cancel {
+                    series_id,
+                    by,
+                    reason,
+                } => client.cancel_series(series_id, by, reason).await?,
+                SeriesCommand::Note { series_id, note } => {
+                    client.update_series_note(series_id, note).await?
+                }
+            };
+            output::print_reservations(format, &occurrences);
+        }
+        Command::Resource(command) => {
+            let resources = match command {
+                ResourceCommand::Create(args) => vec![client.create_resource(args.into()).await?],
+                ResourceCommand::Update(args) => vec![client.update_resource(args.into()).await?],
+                ResourceCommand::Archive { id } => vec![client.archive_resource(id).await?],
+                ResourceCommand::List { include_archived } => {
+                    client.list_resources(include_archived).await?
+                }
+            };
+            output::print_resources(format, &resources);
+        }
+        Command::Watch { after } => {
+            let mut changes = client.listen(after).await?;
+            while let Some(change) = changes.next().await {
+                output::print_change(format, &change?);
+            }
+        }
+    }
+    Ok(())
+}
+
+impl From<ResourceArgs> for Resource {
+    fn from(args: ResourceArgs) -> Self {
+        let mut resource = Resource::new(args.id, args.name, args.capacity);
+        resource.description = args.description;
+        resource
+    }
+}
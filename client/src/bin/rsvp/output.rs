@@ -0,0 +1,291 @@
+use abi::{
+    FreeSlot, ListenResponse, Reservation, ReservationPage, ReservationStatus,
+    ReservationUpdateType, Resource,
+};
+use clap::ValueEnum;
+use prost_types::Timestamp;
+use serde_json::{json, Value};
+
+/// how the results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// aligned columns for people
+    Table,
+    /// one json document per result, or per line for `watch`
+    Json,
+}
+
+/// columns aligned by the widest cell
+#[derive(Debug)]
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: Vec<&'static str>) -> Self {
+        Self {
+            headers,
+            rows: Vec::new(),
+        }
+    }
+    pub fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+    pub fn render(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.len()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let line = |cells: Vec<&str>| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            cells.join("  ").trim_end().to_owned()
+        };
+        let mut lines = vec![line(self.headers.clone())];
+        lines.extend(
+            self.rows
+                .iter()
+                .map(|row| line(row.iter().map(String::as_str).collect())),
+        );
+        lines.join("\n")
+    }
+}
+
+const RESERVATION_HEADERS: [&str; 8] = [
+    "ID", "RESOURCE", "USER", "START", "END", "STATUS", "VERSION", "NOTE",
+];
+
+fn reservation_row(rsvp: &Reservation) -> Vec<String> {
+    vec![
+        rsvp.id.clone(),
+        rsvp.resource_id.clone(),
+        rsvp.user_id.clone(),
+        time(rsvp.start.as_ref()),
+        time(rsvp.end.as_ref()),
+        status(rsvp.status),
+        rsvp.version.to_string(),
+        rsvp.note.clone(),
+    ]
+}
+
+pub fn reservations_table(rsvps: &[Reservation]) -> Table {
+    let mut table = Table::new(RESERVATION_HEADERS.to_vec());
+    rsvps
+        .iter()
+        .for_each(|rsvp| table.push(reservation_row(rsvp)));
+    table
+}
+
+pub fn reservation_json(rsvp: &Reservation) -> Value {
+    json!({
+        "id": rsvp.id,
+        "user_id": rsvp.user_id,
+        "resource_id": rsvp.resource_id,
+        "status": status(rsvp.status),
+        "start": time_json(rsvp.start.as_ref()),
+        "end": time_json(rsvp.end.as_ref()),
+        "note": rsvp.note,
+        "hold_expires_at": time_json(rsvp.hold_expires_at.as_ref()),
+        "series_id": rsvp.series_id,
+        "recurrence": rsvp.recurrence,
+        "cancelled_at": time_json(rsvp.cancelled_at.as_ref()),
+        "cancelled_by": rsvp.cancelled_by,
+        "cancel_reason": rsvp.cancel_reason,
+        "version": rsvp.version,
+        "updated_at": time_json(rsvp.updated_at.as_ref()),
+    })
+}
+
+pub fn print_reservation(format: Format, rsvp: &Reservation) {
+    match format {
+        Format::Table => println!(
+            "{}",
+            reservations_table(std::slice::from_ref(rsvp)).render()
+        ),
+        Format::Json => println!("{}", reservation_json(rsvp)),
+    }
+}
+
+pub fn print_reservations(format: Format, rsvps: &[Reservation]) {
+    match format {
+        Format::Table => println!("{}", reservations_table(rsvps).render()),
+        Format::Json => {
+            let rsvps: Vec<Value> = rsvps.iter().map(reservation_json).collect();
+            println!("{}", Value::Array(rsvps));
+        }
+    }
+}
+
+pub fn print_page(format: Format, page: &ReservationPage) {
+    match format {
+        Format::Table => {
+            println!("{}", reservations_table(&page.reservations).render());
+            println!(
+                "{} of {} reservations{}",
+                page.reservations.len(),
+                page.total,
+                match page.has_more {
+                    true => format!(", next cursor: {}", page.next_cursor),
+                    false => String::new(),
+                }
+            );
+        }
+        Format::Json => {
+            let rsvps: Vec<Value> = page.reservations.iter().map(reservation_json).collect();
+            let page = json!({
+                "reservations": rsvps,
+                "next_cursor": page.next_cursor,
+                "total": page.total,
+                "has_more": page.has_more,
+            });
+            println!("{}", page);
+        }
+    }
+}
+
+pub fn resource_json(resource: &Resource) -> Value {
+    json!({
+        "id": resource.id,
+        "name": resource.name,
+        "description": resource.description,
+        "capacity": resource.capacity,
+        "archived_at": time_json(resource.archived_at.as_ref()),
+    })
+}
+
+pub fn print_resources(format: Format, resources: &[Resource]) {
+    match format {
+        Format::Table => {
+            let mut table =
+                Table::new(vec!["ID", "NAME", "CAPACITY", "ARCHIVED AT", "DESCRIPTION"]);
+            for resource in resources {
+                table.push(vec![
+                    resource.id.clone(),
+                    resource.name.clone(),
+                    resource.capacity.to_string(),
+                    time(resource.archived_at.as_ref()),
+                    resource.description.clone(),
+                ]);
+            }
+            println!("{}", table.render());
+        }
+        Format::Json => {
+            let resources: Vec<Value> = resources.iter().map(resource_json).collect();
+            println!("{}", Value::Array(resources));
+        }
+    }
+}
+
+pub fn print_slots(format: Format, slots: &[FreeSlot]) {
+    match format {
+        Format::Table => {
+            let mut table = Table::new(vec!["RESOURCE", "START", "END"]);
+            for slot in slots {
+                table.push(vec![
+                    slot.resource_id.clone(),
+                    time(slot.start.as_ref()),
+                    time(slot.end.as_ref()),
+                ]);
+            }
+            println!("{}", table.render());
+        }
+        Format::Json => {
+            let slots: Vec<Value> = slots
+                .iter()
+                .map(|slot| {
+                    json!({
+                        "resource_id": slot.resource_id,
+                        "start": time_json(slot.start.as_ref()),
+                        "end": time_json(slot.end.as_ref()),
+                    })
+                })
+                .collect();
+            println!("{}", Value::Array(slots));
+        }
+    }
+}
+
+/// the changes are printed as they come, so the table has no aligned columns
+pub fn print_change(format: Format, change: &ListenResponse) {
+    let op = ReservationUpdateType::from_i32(change.op)
+        .unwrap_or(ReservationUpdateType::Unknown)
+        .to_string();
+    let rsvp = change.reservation.clone().unwrap_or_default();
+    match format {
+        Format::Table => {
+            let mut row = vec![change.change_id.to_string(), op];
+            row.extend(reservation_row(&rsvp));
+            println!("{}", row.join("\t"));
+        }
+        Format::Json => {
+            let change = json!({
+                "change_id": change.change_id,
+                "op": op,
+                "reservation": reservation_json(&rsvp),
+            });
+            println!("{}", change);
+        }
+    }
+}
+
+fn status(status: i32) -> String {
+    ReservationStatus::from_i32(status)
+        .unwrap_or(ReservationStatus::Unknown)
+        .to_string()
+}
+
+fn time(ts: Option<&Timestamp>) -> String {
+    ts.map(Timestamp::to_string).unwrap_or_default()
+}
+
+fn time_json(ts: Option<&Timestamp>) -> Value {
+    ts.map_or(Value::Null, |ts| Value::String(ts.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rsvp() -> Reservation {
+        let mut rsvp = Reservation::new_pending(
+            "leon",
+            "ocean-view-room-777",
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "late check-in",
+        );
+        rsvp.id = "f8b5b0c4-8b1a-4b8e-9a52-0a8c0b7f4a1e".into();
+        rsvp.version = 1;
+        rsvp
+    }
+
+    #[test]
+    fn table_should_align_the_columns() {
+        let mut table = Table::new(vec!["ID", "NAME"]);
+        table.push(vec!["ocean-view-room-777".into(), "Ocean View".into()]);
+        table.push(vec!["room-1".into(), "".into()]);
+        assert_eq!(
+            table.render(),
+            "ID                   NAME\nocean-view-room-777  Ocean View\nroom-1"
+        );
+        let table = reservations_table(&[rsvp()]).render();
+        let row = table.lines().nth(1).unwrap();
+        assert!(row.contains("2023-12-25T22:00:00Z"));
+        assert!(row.contains("pending"));
+    }
+
+    #[test]
+    fn reservation_json_should_name_the_status_and_times() {
+        let value = reservation_json(&rsvp());
+        assert_eq!(value["status"], "pending");
+        assert_eq!(value["start"], "2023-12-25T22:00:00Z");
+        assert_eq!(value["cancelled_at"], Value::Null);
+        assert_eq!(value["version"], 1);
+    }
+}
@@ -0,0 +1,511 @@
+use std::pin::Pin;
+
+use abi::{
+    reservation_service_client::ReservationServiceClient, ArchiveResourceRequest,
+    AvailabilityQuery, AvailabilityRequest, CancelRequest, CancelSeriesRequest, ChangeId,
+    ConfirmRequest, CreateResourceRequest, Error, FreeSlot, GetRequest, GetSeriesRequest,
+    ListResourcesRequest, ListenRequest, ListenResponse, QueryRequest, Reservation,
+    ReservationField, ReservationId, ReservationPage, ReservationQuery, ReservationRequest,
+    ReservationStatus, ReserveBatchRequest, Resource, ResourceId, TransitionRequest, UpdateRequest,
+    UpdateResourceRequest, UpdateSeriesRequest, UserId,
+};
+use futures::{Stream, TryStreamExt};
+use prost_types::FieldMask;
+use tonic::transport::{Channel, Endpoint};
+
+/// stream of the reservations returned by `query`
+pub type ReservationStream = Pin<Box<dyn Stream<Item = Result<Reservation, Error>> + Send>>;
+/// stream of the reservation changes returned by `listen`
+pub type ReservationChangeStream =
+    Pin<Box<dyn Stream<Item = Result<ListenResponse, Error>> + Send>>;
+
+/// typed client of the reservation service,
+/// errors returned by the server are translated back into `abi::Error`.
+#[derive(Debug, Clone)]
+pub struct ReservationClient {
+    inner: ReservationServiceClient<Channel>,
+}
+
+impl ReservationClient {
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            inner: ReservationServiceClient::new(channel),
+        }
+    }
+    /// to connect to the server at `dst`, e.g. `http://localhost:50051`
+    pub async fn connect(dst: impl Into<String>) -> Result<Self, tonic::transport::Error> {
+        let channel = Endpoint::from_shared(dst.into())?.connect().await?;
+        Ok(Self::new(channel))
+    }
+    /// to make a reservation
+    pub async fn reserve(&mut self, rsvp: Reservation) -> Result<Reservation, Error> {
+        self.reserve_with_key(rsvp, "").await
+    }
+    /// to make a reservation which could be retried safely,
+    /// the retries with the same key get the reservation made by the first one
+    pub async fn reserve_with_key(
+        &mut self,
+        rsvp: Reservation,
+        idempotency_key: impl Into<String>,
+    ) -> Result<Reservation, Error> {
+        let res = self
+            .inner
+            .reserve(ReservationRequest {
+                reservation: Some(rsvp),
+                idempotency_key: idempotency_key.into(),
+            })
+            .await?
+            .into_inner();
+        expect_reservation(res.reservation)
+    }
+    /// to make several reservations at once, all of them are made or none
+    pub async fn reserve_batch(
+        &mut self,
+        rsvps: Vec<Reservation>,
+    ) -> Result<Vec<Reservation>, Error> {
+        let res = self
+            .inner
+            .reserve_batch(ReserveBatchRequest {
+                reservations: rsvps,
+            })
+            .await?
+            .into_inner();
+        Ok(res.reservations)
+    }
+    /// to make a recurring reservation, all the occurrences are returned
+    pub async fn reserve_series(&mut self, rsvp: Reservation) -> Result<Vec<Reservation>, Error> {
+        self.reserve_series_with_key(rsvp, "").await
+    }
+    /// to make a recurring reservation which could be retried safely,
+    /// the retries with the same key get the occurrences made by the first one
+    pub async fn reserve_series_with_key(
+        &mut self,
+        rsvp: Reservation,
+        idempotency_key: impl Into<String>,
+    ) -> Result<Vec<Reservation>, Error> {
+        let res = self
+            .inner
+            .reserve_series(ReservationRequest {
+                reservation: Some(rsvp),
+                idempotency_key: idempotency_key.into(),
+            })
+            .await?
+            .into_inner();
+        Ok(res.reservations)
+    }
+    /// to get the occurrences of a series
+    pub async fn get_series(
+        &mut self,
+        series_id: impl Into<String>,
+    ) -> Result<Vec<Reservation>, Error> {
+        let res = self
+            .inner
+            .get_series(GetSeriesRequest {
+                series_id: series_id.into(),
+            })
+            .await?
+            .into_inner();
+        Ok(res.reservations)
+    }
+    /// to cancel the occurrences of a series which are not over yet
+    pub async fn cancel_series(
+        &mut self,
+        series_id: impl Into<String>,
+        cancelled_by: impl Into<UserId>,
+        reason: impl Into<String>,
+    ) -> Result<Vec<Reservation>, Error> {
+        let res = self
+            .inner
+            .cancel_series(CancelSeriesRequest {
+                series_id: series_id.into(),
+                cancelled_by: cancelled_by.into(),
+                reason: reason.into(),
+            })
+            .await?
+            .into_inner();
+        Ok(res.reservations)
+    }
+    /// to update the note of the occurrences of a series
+    pub async fn update_series_note(
+        &mut self,
+        series_id: impl Into<String>,
+        note: impl Into<String>,
+    ) -> Result<Vec<Reservation>, Error> {
+        let res = self
+            .inner
+            .update_series(UpdateSeriesRequest {
+                series_id: series_id.into(),
+                note: note.into(),
+            })
+            .await?
+            .into_inner();
+        Ok(res.reservations)
+    }
+    /// to confirm a pending reservation
+    pub async fn confirm(&mut self, id: impl Into<ReservationId>) -> Result<Reservation, Error> {
+        self.confirm_with_key(id, "").await
+    }
+    /// to confirm a pending reservation, the retries with the same key get the same result
+    pub async fn confirm_with_key(
+        &mut self,
+        id: impl Into<ReservationId>,
+        idempotency_key: impl Into<String>,
+    ) -> Result<Reservation, Error> {
+        let res = self
+            .inner
+            .confirm(ConfirmRequest {
+                reservation: Some(Reservation {
+                    id: id.into(),
+                    ..Default::default()
+                }),
+                idempotency_key: idempotency_key.into(),
+            })
+            .await?
+            .into_inner();
+        expect_reservation(res.reservation)
+    }
+    /// to update the note of a reservation of the given version,
+    /// `VersionMismatch` is returned if it has been updated since
+    pub async fn update_note(
+        &mut self,
+        id: impl Into<ReservationId>,
+        note: impl Into<String>,
+        version: i64,
+    ) -> Result<Reservation, Error> {
+        let res = self
+            .inner
+            .update(UpdateRequest {
+                reservation: Some(Reservation {
+                    id: id.into(),
+                    note: note.into(),
+                    version,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+        expect_reservation(res.reservation)
+    }
+    /// to update the `fields` of a reservation to the ones of `rsvp`, e.g. to reschedule it.
+    /// the id and the version of `rsvp` should be the ones read last.
+    pub async fn update(
+        &mut self,
+        rsvp: Reservation,
+        fields: &[ReservationField],
+    ) -> Result<Reservation, Error> {
+        let res = self
+            .inner
+            .update(UpdateRequest {
+                reservation: Some(rsvp),
+                update_mask: Some(FieldMask {
+                    paths: fields.iter().map(ToString::to_string).collect(),
+                }),
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+        expect_reservation(res.reservation)
+    }
+    /// to cancel a reservation, the cancelled reservation is returned
+    pub async fn cancel(
+        &mut self,
+        id: impl Into<ReservationId>,
+        cancelled_by: impl Into<UserId>,
+        reason: impl Into<String>,
+    ) -> Result<Reservation, Error> {
+        self.cancel_with_key(id, cancelled_by, reason, "").await
+    }
+    /// to cancel a reservation, the retries with the same key get the same result
+    pub async fn cancel_with_key(
+        &mut self,
+        id: impl Into<ReservationId>,
+        cancelled_by: impl Into<UserId>,
+        reason: impl Into<String>,
+        idempotency_key: impl Into<String>,
+    ) -> Result<Reservation, Error> {
+        let res = self
+            .inner
+            .cancel(CancelRequest {
+                id: id.into(),
+                cancelled_by: cancelled_by.into(),
+                reason: reason.into(),
+                idempotency_key: idempotency_key.into(),
+            })
+            .await?
+            .into_inner();
+        expect_reservation(res.reservation)
+    }
+    /// to move a reservation to another status, e.g. to check it in
+    pub async fn transition(
+        &mut self,
+        id: impl Into<ReservationId>,
+        to: ReservationStatus,
+    ) -> Result<Reservation, Error> {
+        let res = self
+            .inner
+            .transition(TransitionRequest {
+                id: id.into(),
+                status: to as i32,
+            })
+            .await?
+            .into_inner();
+        expect_reservation(res.reservation)
+    }
+    /// to get a reservation by its id
+    pub async fn get(&mut self, id: impl Into<ReservationId>) -> Result<Reservation, Error> {
+        let res = self
+            .inner
+            .get(GetRequest { id: id.into() })
+            .await?
+            .into_inner();
+        expect_reservation(res.reservation)
+    }
+    /// to query reservations, the results are streamed back
+    pub async fn query(&mut self, query: ReservationQuery) -> Result<ReservationStream, Error> {
+        let stream = self
+            .inner
+            .query(QueryRequest::from(query))
+            .await?
+            .into_inner();
+        Ok(Box::pin(stream.map_err(Error::from)))
+    }
+    /// to query a page of reservations, the next page is queried with its `next_cursor`
+    pub async fn query_page(&mut self, query: ReservationQuery) -> Result<ReservationPage, Error> {
+        let page = self
+            .inner
+            .query_page(QueryRequest::from(query))
+            .await?
+            .into_inner();
+        Ok(page)
+    }
+    /// to listen to the reservation changes.
+    /// if `after` is given, the changes recorded after it are replayed first.
+    pub async fn listen(
+        &mut self,
+        after: Option<ChangeId>,
+    ) -> Result<ReservationChangeStream, Error> {
+        let stream = self
+            .inner
+            .listen(ListenRequest {
+                after_change_id: after,
+            })
+            .await?
+            .into_inner();
+        Ok(Box::pin(stream.map_err(Error::from)))
+    }
+    /// to find the free time slots of the resources
+    pub async fn availability(&mut self, query: AvailabilityQuery) -> Result<Vec<FreeSlot>, Error> {
+        let res = self
+            .inner
+            .availability(AvailabilityRequest { query: Some(query) })
+            .await?
+            .into_inner();
+        Ok(res.slots)
+    }
+    /// to register a resource which could be reserved
+    pub async fn create_resource(&mut self, resource: Resource) -> Result<Resource, Error> {
+        let res = self
+            .inner
+            .create_resource(CreateResourceRequest {
+                resource: Some(resource),
+            })
+            .await?
+            .into_inner();
+        expect_resource(res.resource)
+    }
+    /// to update the name, description and capacity of a resource
+    pub async fn update_resource(&mut self, resource: Resource) -> Result<Resource, Error> {
+        let res = self
+            .inner
+            .update_resource(UpdateResourceRequest {
+                resource: Some(resource),
+            })
+            .await?
+            .into_inner();
+        expect_resource(res.resource)
+    }
+    /// to archive a resource, so it could no longer be reserved
+    pub async fn archive_resource(&mut self, id: impl Into<ResourceId>) -> Result<Resource, Error> {
+        let res = self
+            .inner
+            .archive_resource(ArchiveResourceRequest { id: id.into() })
+            .await?
+            .into_inner();
+        expect_resource(res.resource)
+    }
+    /// to list the resources ordered by id
+    pub async fn list_resources(&mut self, include_archived: bool) -> Result<Vec<Resource>, Error> {
+        let res = self
+            .inner
+            .list_resources(ListResourcesRequest { include_archived })
+            .await?
+            .into_inner();
+        Ok(res.resources)
+    }
+}
+
+fn expect_reservation(rsvp: Option<Reservation>) -> Result<Reservation, Error> {
+    rsvp.ok_or(Error::Unknown)
+}
+
+fn expect_resource(resource: Option<Resource>) -> Result<Resource, Error> {
+    resource.ok_or(Error::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi::{
+        reservation_service_server::ReservationServiceServer, ReservationConflictInfo,
+        ReservationQueryBuilder, ReservationUpdateType,
+    };
+    use futures::StreamExt;
+    use prost_types::Timestamp;
+    use reservation::ReservationManager;
+    use service::RsvpService;
+    use sqlx::{types::Uuid, PgPool};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn client_should_reserve_confirm_and_cancel() {
+        let mut client = start_server(migrated_pool.clone()).await;
+        let rsvp = client.reserve(new_reservation()).await.unwrap();
+        assert!(!rsvp.id.is_empty());
+        let rsvp = client.confirm(rsvp.id).await.unwrap();
+        assert_eq!(rsvp.status, ReservationStatus::Confirmed as i32);
+        let updated = client
+            .update_note(rsvp.id.clone(), "late arrival", rsvp.version)
+            .await
+            .unwrap();
+        assert_eq!(client.get(rsvp.id.clone()).await.unwrap(), updated);
+        let err = client
+            .update_note(rsvp.id, "early arrival", rsvp.version)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::VersionMismatch {
+                expected: rsvp.version,
+                found: updated.version
+            }
+        );
+        let rsvp = updated;
+        let err = client
+            .transition(rsvp.id.clone(), ReservationStatus::Completed)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::InvalidStatusTransition {
+                from: ReservationStatus::Confirmed,
+                to: ReservationStatus::Completed,
+            }
+        );
+        let cancelled = client
+            .cancel(rsvp.id.clone(), "leon", "change of plan")
+            .await
+            .unwrap();
+        assert_eq!(cancelled.status, ReservationStatus::Cancelled as i32);
+        assert_eq!(cancelled.cancelled_by, "leon");
+        assert_eq!(client.get(rsvp.id).await.unwrap(), cancelled);
+        let err = client.get(Uuid::new_v4().to_string()).await.unwrap_err();
+        assert_eq!(err, Error::NotFound);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn client_should_rebuild_conflict_info() {
+        let mut client = start_server(migrated_pool.clone()).await;
+        let rsvp = client.reserve(new_reservation()).await.unwrap();
+        let err = client.reserve(new_reservation()).await.unwrap_err();
+        match err {
+            Error::ConflictingReservation(ReservationConflictInfo::Parsed(conflict)) => {
+                assert_eq!(conflict.new.rid, "ocean-view-room-777");
+                assert_eq!(conflict.old.rid, "ocean-view-room-777");
+                assert_eq!(conflict.old.start.to_rfc3339(), "2023-12-25T22:00:00+00:00");
+                assert_eq!(conflict.blocking_id(), Some(rsvp.id.as_str()));
+                assert_eq!(conflict.blocking, vec![rsvp]);
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+        let err = client.get("not-a-uuid").await.unwrap_err();
+        assert_eq!(err, Error::InvalidReservationId("not-a-uuid".into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn client_should_manage_resources() {
+        let mut client = start_server(migrated_pool.clone()).await;
+        let err = client
+            .create_resource(Resource::new("ocean-view-room-777", "", 1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::ResourceAlreadyExists("ocean-view-room-777".into())
+        );
+        let resource = client
+            .update_resource(Resource::new("ocean-view-room-777", "Ocean View Suite", 2))
+            .await
+            .unwrap();
+        assert_eq!(resource.capacity, 2);
+        let archived = client
+            .archive_resource("ocean-view-room-777")
+            .await
+            .unwrap();
+        assert!(archived.is_archived());
+        assert_eq!(client.list_resources(true).await.unwrap(), vec![archived]);
+        let err = client.reserve(new_reservation()).await.unwrap_err();
+        assert_eq!(err, Error::ResourceNotFound("ocean-view-room-777".into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn client_should_query_and_listen() {
+        let mut client = start_server(migrated_pool.clone()).await;
+        let mut changes = client.listen(None).await.unwrap();
+        let rsvp = client.reserve(new_reservation()).await.unwrap();
+        let query = ReservationQueryBuilder::default()
+            .user_id("leon")
+            .start("2023-12-24T15:00:00-0700".parse::<Timestamp>().unwrap())
+            .end("2023-12-30T12:00:00-0700".parse::<Timestamp>().unwrap())
+            .status(ReservationStatus::Pending as i32)
+            .build()
+            .unwrap();
+        let rsvps: Vec<_> = client.query(query).await.unwrap().collect().await;
+        assert_eq!(rsvps, vec![Ok(rsvp.clone())]);
+        let change = tokio::time::timeout(Duration::from_secs(5), changes.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.op, ReservationUpdateType::Create as i32);
+        assert_eq!(change.reservation, Some(rsvp));
+    }
+
+    fn new_reservation() -> Reservation {
+        Reservation::new_pending(
+            "leon",
+            "ocean-view-room-777",
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "test reservation",
+        )
+    }
+    async fn start_server(pool: PgPool) -> ReservationClient {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let svc = RsvpService::new(ReservationManager::new(pool));
+        tokio::spawn(
+            Server::builder()
+                .add_service(ReservationServiceServer::new(svc))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = ReservationClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        client
+            .create_resource(Resource::new("ocean-view-room-777", "Ocean View", 1))
+            .await
+            .unwrap();
+        client
+    }
+}
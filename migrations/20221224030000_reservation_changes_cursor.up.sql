@@ -0,0 +1,8 @@
+-- to make the change id usable as a cursor of the change feed
+ALTER TABLE rsvp.reservation_changes ALTER COLUMN id TYPE BIGINT;
+ALTER SEQUENCE rsvp.reservation_changes_id_seq AS BIGINT;
+ALTER TABLE rsvp.reservation_changes ADD CONSTRAINT reservation_changes_pkey PRIMARY KEY (id);
+
+-- to record when the change happened, so old changes could be pruned
+ALTER TABLE rsvp.reservation_changes ADD COLUMN changed_at TIMESTAMPTZ NOT NULL DEFAULT now();
+CREATE INDEX reservation_changes_changed_at_idx ON rsvp.reservation_changes (changed_at);
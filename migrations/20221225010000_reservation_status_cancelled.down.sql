@@ -0,0 +1,3 @@
+-- postgres could not drop a value from an enum,
+-- so 'cancelled' is kept in rsvp.reservation_status.
+SELECT 1;
@@ -0,0 +1,3 @@
+-- a new enum value could not be used in the transaction adding it,
+-- so the constraint using it is updated in the next migration.
+ALTER TYPE rsvp.reservation_status ADD VALUE 'cancelled';
@@ -0,0 +1,6 @@
+-- only the active reservations block the time slot,
+-- the cancelled ones are kept for the records.
+ALTER TABLE rsvp.reservations DROP CONSTRAINT reservations_conflict;
+ALTER TABLE rsvp.reservations ADD CONSTRAINT reservations_conflict
+    EXCLUDE USING gist (resource_id WITH =, timespan WITH &&)
+    WHERE (status IN ('pending', 'confirmed', 'blocked'));
@@ -0,0 +1,3 @@
+ALTER TABLE rsvp.reservations DROP COLUMN cancel_reason;
+ALTER TABLE rsvp.reservations DROP COLUMN cancelled_by;
+ALTER TABLE rsvp.reservations DROP COLUMN cancelled_at;
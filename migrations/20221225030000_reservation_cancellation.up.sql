@@ -0,0 +1,4 @@
+-- to keep the trace of the cancelled reservations
+ALTER TABLE rsvp.reservations ADD COLUMN cancelled_at TIMESTAMPTZ;
+ALTER TABLE rsvp.reservations ADD COLUMN cancelled_by VARCHAR(64);
+ALTER TABLE rsvp.reservations ADD COLUMN cancel_reason TEXT;
@@ -0,0 +1,3 @@
+-- postgres could not drop a value from an enum,
+-- so 'checked_in', 'completed' and 'no_show' are kept in rsvp.reservation_status.
+SELECT 1;
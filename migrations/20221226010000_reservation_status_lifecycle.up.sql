@@ -0,0 +1,5 @@
+-- a new enum value could not be used in the transaction adding it,
+-- so the constraint using it is updated in the next migration.
+ALTER TYPE rsvp.reservation_status ADD VALUE 'checked_in';
+ALTER TYPE rsvp.reservation_status ADD VALUE 'completed';
+ALTER TYPE rsvp.reservation_status ADD VALUE 'no_show';
@@ -0,0 +1,6 @@
+-- checked-in and completed reservations keep the time slot,
+-- while cancelled and no-show ones release it.
+ALTER TABLE rsvp.reservations DROP CONSTRAINT reservations_conflict;
+ALTER TABLE rsvp.reservations ADD CONSTRAINT reservations_conflict
+    EXCLUDE USING gist (resource_id WITH =, timespan WITH &&)
+    WHERE (status IN ('pending', 'confirmed', 'blocked', 'checked_in', 'completed'));
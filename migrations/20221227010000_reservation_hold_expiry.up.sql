@@ -0,0 +1,3 @@
+-- pending reservations holding the time slot until this time are cancelled by the reaper
+ALTER TABLE rsvp.reservations ADD COLUMN hold_expires_at TIMESTAMPTZ;
+CREATE INDEX reservations_hold_expires_at_idx ON rsvp.reservations (hold_expires_at) WHERE status = 'pending';
@@ -0,0 +1,2 @@
+ALTER TABLE rsvp.reservations DROP CONSTRAINT reservations_resource_fkey;
+DROP TABLE rsvp.resources;
@@ -0,0 +1,17 @@
+-- resources which could be reserved
+CREATE TABLE rsvp.resources(
+    id VARCHAR(64) NOT NULL,
+    name TEXT NOT NULL DEFAULT '',
+    description TEXT,
+    capacity INTEGER NOT NULL DEFAULT 1,
+    archived_at TIMESTAMPTZ,
+
+    CONSTRAINT resources_pkey PRIMARY KEY (id),
+    CONSTRAINT resources_capacity CHECK (capacity > 0)
+);
+
+-- to register the resources already reserved
+INSERT INTO rsvp.resources (id) SELECT DISTINCT resource_id FROM rsvp.reservations;
+
+ALTER TABLE rsvp.reservations ADD CONSTRAINT reservations_resource_fkey
+    FOREIGN KEY (resource_id) REFERENCES rsvp.resources (id);
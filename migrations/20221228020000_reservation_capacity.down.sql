@@ -0,0 +1,6 @@
+DROP TRIGGER reservations_capacity_trigger ON rsvp.reservations;
+DROP FUNCTION rsvp.reservations_capacity_trigger();
+DROP INDEX rsvp.reservations_resource_timespan_idx;
+ALTER TABLE rsvp.reservations ADD CONSTRAINT reservations_conflict
+    EXCLUDE USING gist (resource_id WITH =, timespan WITH &&)
+    WHERE (status IN ('pending', 'confirmed', 'blocked', 'checked_in', 'completed'));
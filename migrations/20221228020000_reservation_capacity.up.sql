@@ -0,0 +1,72 @@
+-- the exclusion constraint allows only one reservation at a time,
+-- it is replaced by a trigger allowing up to `capacity` overlapping reservations per resource.
+ALTER TABLE rsvp.reservations DROP CONSTRAINT reservations_conflict;
+CREATE INDEX reservations_resource_timespan_idx ON rsvp.reservations USING gist (resource_id, timespan);
+
+CREATE OR REPLACE FUNCTION rsvp.reservations_capacity_trigger() RETURNS TRIGGER AS $$
+DECLARE
+    _blocking rsvp.reservation_status[] := ARRAY['pending', 'confirmed', 'blocked', 'checked_in', 'completed'];
+    _capacity integer;
+    _point timestamptz;
+    _count bigint;
+    _old tstzrange;
+BEGIN
+    IF NOT NEW.status = ANY(_blocking) THEN
+        RETURN NEW;
+    END IF;
+    -- nothing more is occupied than before
+    IF TG_OP = 'UPDATE' AND OLD.status = ANY(_blocking)
+        AND OLD.resource_id = NEW.resource_id AND OLD.timespan = NEW.timespan THEN
+        RETURN NEW;
+    END IF;
+
+    -- to lock the resource, so the reservations on it are checked one by one,
+    -- the same as the exclusion constraint did.
+    SELECT capacity INTO _capacity FROM rsvp.resources WHERE id = NEW.resource_id FOR UPDATE;
+    IF NOT FOUND THEN
+        -- the foreign key will report it
+        RETURN NEW;
+    END IF;
+
+    -- the most overlapping reservations are found at the start of the new one,
+    -- or at the start of one of the overlapping ones.
+    SELECT p.point, (
+        SELECT count(*) FROM rsvp.reservations r
+        WHERE r.resource_id = NEW.resource_id AND r.id <> NEW.id
+            AND r.status = ANY(_blocking) AND r.timespan @> p.point
+    ) AS cnt INTO _point, _count
+    FROM (
+        SELECT lower(NEW.timespan) AS point
+        UNION
+        SELECT lower(r.timespan) FROM rsvp.reservations r
+        WHERE r.resource_id = NEW.resource_id AND r.id <> NEW.id
+            AND r.status = ANY(_blocking) AND r.timespan && NEW.timespan
+            AND lower(r.timespan) > lower(NEW.timespan)
+    ) p
+    ORDER BY cnt DESC, p.point
+    LIMIT 1;
+
+    IF _count >= _capacity THEN
+        SELECT r.timespan INTO _old FROM rsvp.reservations r
+        WHERE r.resource_id = NEW.resource_id AND r.id <> NEW.id
+            AND r.status = ANY(_blocking) AND r.timespan @> _point
+        ORDER BY lower(r.timespan)
+        LIMIT 1;
+        -- to report it the same way as the exclusion constraint
+        RAISE EXCEPTION 'conflicting key value violates exclusion constraint "reservations_conflict"'
+            USING ERRCODE = 'exclusion_violation',
+                DETAIL = format(
+                    'Key (resource_id, timespan)=(%s, %s) conflicts with existing key (resource_id, timespan)=(%s, %s).',
+                    NEW.resource_id, NEW.timespan, NEW.resource_id, _old
+                ),
+                SCHEMA = 'rsvp',
+                TABLE = 'reservations',
+                CONSTRAINT = 'reservations_conflict';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reservations_capacity_trigger
+    BEFORE INSERT OR UPDATE OF resource_id, timespan, status ON rsvp.reservations
+    FOR EACH ROW EXECUTE PROCEDURE rsvp.reservations_capacity_trigger();
@@ -0,0 +1,4 @@
+-- occurrences of a recurring reservation share the same series id and recurrence rule
+ALTER TABLE rsvp.reservations ADD COLUMN series_id UUID;
+ALTER TABLE rsvp.reservations ADD COLUMN recurrence TEXT;
+CREATE INDEX reservations_series_id_idx ON rsvp.reservations (series_id);
@@ -0,0 +1,9 @@
+-- responses of the mutating requests, to replay them when a request is retried with the same key
+CREATE TABLE rsvp.idempotency_keys (
+    key VARCHAR(128) NOT NULL,
+    operation VARCHAR(32) NOT NULL,
+    response BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CONSTRAINT idempotency_keys_pkey PRIMARY KEY (key, operation)
+);
+CREATE INDEX idempotency_keys_created_at_idx ON rsvp.idempotency_keys (created_at);
@@ -0,0 +1,2 @@
+-- the queries are paged by the keyset (lower(timespan), id)
+CREATE INDEX reservations_start_id_idx ON rsvp.reservations (lower(timespan), id);
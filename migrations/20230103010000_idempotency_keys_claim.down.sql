@@ -0,0 +1,3 @@
+DELETE FROM rsvp.idempotency_keys WHERE response IS NULL;
+ALTER TABLE rsvp.idempotency_keys DROP COLUMN request_hash;
+ALTER TABLE rsvp.idempotency_keys ALTER COLUMN response SET NOT NULL;
@@ -0,0 +1,6 @@
+-- the key is claimed before the request is run, the response is saved once it is done,
+-- so a concurrent retry sees the claim instead of running the request again.
+ALTER TABLE rsvp.idempotency_keys ALTER COLUMN response DROP NOT NULL;
+-- the hash of the request, to reject the key reused with another request.
+-- it is null for the keys saved before it was recorded.
+ALTER TABLE rsvp.idempotency_keys ADD COLUMN request_hash BYTEA;
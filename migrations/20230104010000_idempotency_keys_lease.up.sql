@@ -0,0 +1,3 @@
+-- when the key was last claimed, a claim without a response is taken over once its lease is over,
+-- so a request which never finished does not hold the key until it is pruned.
+ALTER TABLE rsvp.idempotency_keys ADD COLUMN claimed_at TIMESTAMPTZ NOT NULL DEFAULT now();
@@ -0,0 +1,3 @@
+DROP INDEX rsvp.reservation_changes_position_idx;
+ALTER TABLE rsvp.reservation_changes DROP COLUMN position;
+DROP SEQUENCE rsvp.reservation_changes_position_seq;
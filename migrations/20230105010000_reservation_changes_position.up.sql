@@ -0,0 +1,10 @@
+-- the cursor of the change feed, given to the changes in the order they are committed.
+-- the id is taken before the commit, so a change could be committed after one with a larger id
+-- and a listener past that id would miss it.
+-- the position is null until the change is sequenced by a listener.
+CREATE SEQUENCE rsvp.reservation_changes_position_seq AS BIGINT;
+ALTER TABLE rsvp.reservation_changes ADD COLUMN position BIGINT;
+-- the existing changes keep their id as their position, so the cursors given out stay valid
+UPDATE rsvp.reservation_changes SET position = id;
+SELECT setval('rsvp.reservation_changes_position_seq', COALESCE(MAX(id), 0) + 1, false) FROM rsvp.reservation_changes;
+CREATE UNIQUE INDEX reservation_changes_position_idx ON rsvp.reservation_changes (position);
@@ -0,0 +1,2 @@
+-- the queries are built by the service since the cursor pagination, nothing calls it any more
+DROP FUNCTION IF EXISTS rsvp.query;
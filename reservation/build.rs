@@ -0,0 +1,4 @@
+fn main() {
+    // the migrations are embedded, so a new one should rebuild the crate
+    println!("cargo:rerun-if-changed=../migrations");
+}
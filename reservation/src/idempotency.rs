@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use abi::Error;
+use async_trait::async_trait;
+
+use crate::{IdempotencyClaim, IdempotencyStore, ReservationManager};
+
+/// a key could not be longer than this
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 128;
+/// how long a claim without a response holds the key, e.g. the server crashed during the request.
+/// it is taken over by the next request after that, so it should be longer than any request.
+pub const IDEMPOTENCY_CLAIM_LEASE: Duration = Duration::from_secs(60);
+
+#[async_trait]
+impl IdempotencyStore for ReservationManager {
+    async fn claim(
+        &self,
+        key: &str,
+        operation: &str,
+        request_hash: &[u8],
+    ) -> Result<IdempotencyClaim, Error> {
+        validate_key(key)?;
+        // only one of the concurrent requests inserts the row or takes over the lapsed claim,
+        // the others see it
+        let claimed = sqlx::query(
+            "INSERT INTO rsvp.idempotency_keys (key, operation, request_hash) VALUES ($1, $2, $3) ON CONFLICT (key, operation) DO UPDATE SET request_hash = EXCLUDED.request_hash, claimed_at = now(), created_at = now() WHERE idempotency_keys.response IS NULL AND idempotency_keys.claimed_at < now() - $4",
+        )
+        .bind(key)
+        .bind(operation)
+        .bind(request_hash)
+        .bind(IDEMPOTENCY_CLAIM_LEASE)
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            == 1;
+        if claimed {
+            return Ok(IdempotencyClaim::Claimed);
+        }
+        let saved: Option<(Option<Vec<u8>>, Option<Vec<u8>>)> = sqlx::query_as(
+            "SELECT request_hash, response FROM rsvp.idempotency_keys WHERE key = $1 AND operation = $2",
+        )
+        .bind(key)
+        .bind(operation)
+        .fetch_optional(&self.pool)
+        .await?;
+        match saved {
+            Some((hash, response)) => check_claim(key, request_hash, hash.as_deref(), response),
+            // released meanwhile, to claim it again
+            None => self.claim(key, operation, request_hash).await,
+        }
+    }
+    async fn save_response(
+        &self,
+        key: &str,
+        operation: &str,
+        response: Vec<u8>,
+    ) -> Result<(), Error> {
+        validate_key(key)?;
+        sqlx::query(
+            "UPDATE rsvp.idempotency_keys SET response = $3 WHERE key = $1 AND operation = $2",
+        )
+        .bind(key)
+        .bind(operation)
+        .bind(response)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+    async fn release(&self, key: &str, operation: &str) -> Result<(), Error> {
+        validate_key(key)?;
+        sqlx::query(
+            "DELETE FROM rsvp.idempotency_keys WHERE key = $1 AND operation = $2 AND response IS NULL",
+        )
+        .bind(key)
+        .bind(operation)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+impl ReservationManager {
+    /// to remove the keys saved more than `retention` ago, the number of removed keys is returned.
+    /// the keys claimed by the requests which never finished are removed as well.
+    pub async fn prune_idempotency_keys(&self, retention: Duration) -> Result<u64, Error> {
+        let result = sqlx::query("DELETE FROM rsvp.idempotency_keys WHERE created_at < now() - $1")
+            .bind(retention)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// to tell what a request finds on a key claimed before,
+/// the keys saved without a hash are taken as claimed for the same request.
+pub(crate) fn check_claim(
+    key: &str,
+    request_hash: &[u8],
+    saved_hash: Option<&[u8]>,
+    response: Option<Vec<u8>>,
+) -> Result<IdempotencyClaim, Error> {
+    if saved_hash.is_some_and(|hash| hash != request_hash) {
+        return Err(Error::IdempotencyKeyReused(key.to_owned()));
+    }
+    match response {
+        Some(response) => Ok(IdempotencyClaim::Saved(response)),
+        None => Err(Error::IdempotencyKeyInUse(key.to_owned())),
+    }
+}
+
+pub(crate) fn validate_key(key: &str) -> Result<(), Error> {
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(Error::InvalidIdempotencyKey(key.to_owned()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn claim_should_replay_the_saved_response() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let claim = manager.claim("key", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Claimed);
+        // a concurrent retry could not run it again
+        let err = manager
+            .claim("key", "reserve", b"request")
+            .await
+            .unwrap_err();
+        assert_eq!(err, Error::IdempotencyKeyInUse("key".into()));
+        manager
+            .save_response("key", "reserve", b"first".to_vec())
+            .await
+            .unwrap();
+        let claim = manager.claim("key", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Saved(b"first".to_vec()));
+        let err = manager
+            .claim("key", "reserve", b"another request")
+            .await
+            .unwrap_err();
+        assert_eq!(err, Error::IdempotencyKeyReused("key".into()));
+        let claim = manager.claim("key", "cancel", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Claimed);
+        let err = manager.claim("", "reserve", b"request").await.unwrap_err();
+        assert_eq!(err, Error::InvalidIdempotencyKey("".into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn release_should_allow_the_key_to_be_claimed_again() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager.claim("key", "reserve", b"request").await.unwrap();
+        manager.release("key", "reserve").await.unwrap();
+        let claim = manager.claim("key", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Claimed);
+        // the saved response is never released
+        manager
+            .save_response("key", "reserve", b"first".to_vec())
+            .await
+            .unwrap();
+        manager.release("key", "reserve").await.unwrap();
+        let claim = manager.claim("key", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Saved(b"first".to_vec()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn claim_should_take_over_the_lapsed_claim() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let lapse = || async {
+            sqlx::query("UPDATE rsvp.idempotency_keys SET claimed_at = now() - $1")
+                .bind(IDEMPOTENCY_CLAIM_LEASE * 2)
+                .execute(&migrated_pool)
+                .await
+                .unwrap();
+        };
+        manager.claim("key", "reserve", b"request").await.unwrap();
+        // the request which claimed it never finished
+        lapse().await;
+        let claim = manager.claim("key", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Claimed);
+        let err = manager
+            .claim("key", "reserve", b"request")
+            .await
+            .unwrap_err();
+        assert_eq!(err, Error::IdempotencyKeyInUse("key".into()));
+        // the saved response never lapses
+        manager
+            .save_response("key", "reserve", b"first".to_vec())
+            .await
+            .unwrap();
+        lapse().await;
+        let claim = manager.claim("key", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Saved(b"first".to_vec()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn prune_idempotency_keys_should_remove_old_keys() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        for key in ["old", "new"] {
+            manager.claim(key, "reserve", b"request").await.unwrap();
+            manager.save_response(key, "reserve", vec![]).await.unwrap();
+        }
+        sqlx::query("UPDATE rsvp.idempotency_keys SET created_at = now() - interval '2 days' WHERE key = 'old'")
+            .execute(&migrated_pool)
+            .await
+            .unwrap();
+        let pruned = manager
+            .prune_idempotency_keys(Duration::from_secs(24 * 60 * 60))
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        let claim = manager.claim("new", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Saved(vec![]));
+    }
+}
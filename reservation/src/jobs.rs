@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use tokio::{task::JoinHandle, time};
+
+use crate::ReservationManager;
+
+/// to spawn the job pruning the changes older than `retention` every `interval`
+pub fn spawn_change_compaction(
+    manager: ReservationManager,
+    retention: Duration,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = manager.prune_changes(retention).await {
+                eprintln!("Failed to prune reservation changes: {}", e);
+            }
+        }
+    })
+}
+
+/// to spawn the job cancelling the pending reservations whose hold has expired every `interval`
+pub fn spawn_hold_reaper(manager: ReservationManager, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = manager.expire_holds().await {
+                eprintln!("Failed to expire reservation holds: {}", e);
+            }
+        }
+    })
+}
+
+/// to spawn the job pruning the idempotency keys older than `retention` every `interval`
+pub fn spawn_idempotency_key_pruning(
+    manager: ReservationManager,
+    retention: Duration,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = manager.prune_idempotency_keys(retention).await {
+                eprintln!("Failed to prune idempotency keys: {}", e);
+            }
+        }
+    })
+}
@@ -1,27 +1,181 @@
-use abi::{Error, Reservation, ReservationId, ReservationQuery};
+use std::pin::Pin;
+
+use abi::{
+    AvailabilityQuery, ChangeId, Error, FreeSlot, ListenResponse, PaginationConfig, Reservation,
+    ReservationField, ReservationId, ReservationPage, ReservationQuery, ReservationStatus,
+    Resource, ResourceId, UserId,
+};
 use async_trait::async_trait;
+use futures::Stream;
 use sqlx::PgPool;
 
+mod idempotency;
+mod jobs;
 mod manager;
+mod memory;
+mod migration;
+mod resource;
+
+pub use idempotency::{IDEMPOTENCY_CLAIM_LEASE, MAX_IDEMPOTENCY_KEY_LEN};
+pub use jobs::*;
+pub use memory::MemoryReservationManager;
+pub use migration::MIGRATOR;
 
-#[derive(Debug)]
+/// stream of the reservations matching a query
+pub type ReservationQueryStream = Pin<Box<dyn Stream<Item = Result<Reservation, Error>> + Send>>;
+/// stream of the changes made to the reservations
+pub type ReservationChangeStream =
+    Pin<Box<dyn Stream<Item = Result<ListenResponse, Error>> + Send>>;
+
+#[derive(Debug, Clone)]
 pub struct ReservationManager {
     pool: PgPool,
+    pagination: PaginationConfig,
 }
 
 #[async_trait]
 pub trait Rsvp {
-    /// to make a reservation
+    /// to make a reservation on a registered resource which is not archived.
+    /// on conflict, the overlapping reservations are returned in the error.
     async fn reserve(&self, rsvp: Reservation) -> Result<Reservation, Error>;
+    /// to make several reservations in one transaction, all of them are made or none.
+    /// if any conflicts, the conflicts of all of them are returned.
+    async fn reserve_batch(&self, rsvps: Vec<Reservation>) -> Result<Vec<Reservation>, Error>;
+    /// to make a recurring reservation, it is expanded into the occurrences sharing a series id.
+    /// if any of them conflicts, none is created.
+    async fn reserve_series(&self, rsvp: Reservation) -> Result<Vec<Reservation>, Error>;
+    /// to get the occurrences of a series ordered by start time
+    async fn get_series(&self, series_id: String) -> Result<Vec<Reservation>, Error>;
+    /// to cancel the occurrences of a series which are not over yet,
+    /// all the occurrences are returned.
+    async fn cancel_series(
+        &self,
+        series_id: String,
+        cancelled_by: UserId,
+        reason: String,
+    ) -> Result<Vec<Reservation>, Error>;
+    /// to update the note of all the occurrences of a series.
+    /// no version is checked, the note is the same for the whole series and the last one wins,
+    /// the occurrences edited one by one since are overwritten on purpose.
+    async fn update_series_note(
+        &self,
+        series_id: String,
+        note: String,
+    ) -> Result<Vec<Reservation>, Error>;
     /// to change reservation status.
     /// if the current status is pending, to changed it to confirmed.
     async fn change_status(&self, id: ReservationId) -> Result<Reservation, Error>;
-    // to update note
-    async fn update_note(&self, id: ReservationId, note: String) -> Result<Reservation, Error>;
-    // to delete one reservation
+    /// to move the reservation to the `to` status.
+    /// if it is not allowed from the current status, `Error::InvalidStatusTransition` is returned.
+    async fn transition(
+        &self,
+        id: ReservationId,
+        to: ReservationStatus,
+    ) -> Result<Reservation, Error>;
+    /// to update note, the `version` should be the current one of the reservation,
+    /// otherwise it has been updated meanwhile and `VersionMismatch` is returned.
+    async fn update_note(
+        &self,
+        id: ReservationId,
+        note: String,
+        version: i64,
+    ) -> Result<Reservation, Error>;
+    /// to update the `fields` of a reservation, e.g. to move it to another time or resource,
+    /// all of them are updated at once or none. the version should be the current one.
+    /// the new time slot is checked the same as a new reservation,
+    /// on conflict the reservation is kept untouched.
+    async fn update(
+        &self,
+        rsvp: Reservation,
+        fields: Vec<ReservationField>,
+    ) -> Result<Reservation, Error>;
+    /// to cancel a reservation, the cancelled reservation is kept with who cancelled it and why,
+    /// but no longer blocks the time slot.
+    async fn cancel(
+        &self,
+        id: ReservationId,
+        cancelled_by: UserId,
+        reason: String,
+    ) -> Result<Reservation, Error>;
+    // to delete one reservation permanently
     async fn delete(&self, id: ReservationId) -> Result<Reservation, Error>;
     /// to get the reservation by id
     async fn get(&self, id: ReservationId) -> Result<Reservation, Error>;
-    /// query reservations
-    async fn query(&self, query: ReservationQuery) -> Result<Vec<Reservation>, Error>;
+    /// query a page of reservations, ordered by start time then id.
+    /// the page carries the cursor of the next one and the total number of the matching ones.
+    async fn query(&self, query: ReservationQuery) -> Result<ReservationPage, Error>;
+    /// to stream all the reservations matching the query in the same order as `query`,
+    /// starting after the cursor if any, the page and the page size are ignored.
+    /// the reservations are read no faster than they are consumed.
+    async fn query_stream(&self, query: ReservationQuery) -> Result<ReservationQueryStream, Error>;
+    /// to find the free time slots of the resources in the query window,
+    /// when fewer reservations than the capacity of the resource are active.
+    async fn find_free_slots(&self, query: AvailabilityQuery) -> Result<Vec<FreeSlot>, Error>;
+    /// to subscribe to the changes made to the reservations.
+    /// if `after` is given, the changes recorded after it are replayed first.
+    async fn listen(&self, after: Option<ChangeId>) -> Result<ReservationChangeStream, Error>;
+}
+
+/// registry of the resources which could be reserved
+#[async_trait]
+pub trait ResourceRegistry {
+    /// to register a resource, the capacity defaults to 1
+    async fn create_resource(&self, resource: Resource) -> Result<Resource, Error>;
+    /// to update the name, description and capacity of a resource
+    async fn update_resource(&self, resource: Resource) -> Result<Resource, Error>;
+    /// to archive a resource, so it could no longer be reserved.
+    /// the existing reservations on it are kept.
+    async fn archive_resource(&self, id: ResourceId) -> Result<Resource, Error>;
+    /// to get the resource by id
+    async fn get_resource(&self, id: ResourceId) -> Result<Resource, Error>;
+    /// to list the resources ordered by id
+    async fn list_resources(&self, include_archived: bool) -> Result<Vec<Resource>, Error>;
+}
+
+/// what a request finds when it claims its idempotency key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// the key is claimed by this request, which should run and save its response
+    Claimed,
+    /// the encoded response saved by the request which claimed the key before
+    Saved(Vec<u8>),
+}
+
+/// to remember the responses of the mutating requests by their idempotency key,
+/// so a retried request gets the original response instead of being applied again.
+/// the keys of different operations are independent.
+#[async_trait]
+pub trait IdempotencyStore {
+    /// to claim the key before the request is run, so the concurrent retries could not run it again.
+    /// `Error::IdempotencyKeyInUse` is returned while the request which claimed it is in progress,
+    /// `Error::IdempotencyKeyReused` if it was claimed for a request of another hash.
+    /// a claim without a response is taken over after `IDEMPOTENCY_CLAIM_LEASE`.
+    async fn claim(
+        &self,
+        key: &str,
+        operation: &str,
+        request_hash: &[u8],
+    ) -> Result<IdempotencyClaim, Error>;
+    /// to save the encoded response for the claimed key
+    async fn save_response(
+        &self,
+        key: &str,
+        operation: &str,
+        response: Vec<u8>,
+    ) -> Result<(), Error>;
+    /// to release the claimed key without a response, e.g. the request failed,
+    /// so it could be retried with the same key.
+    async fn release(&self, key: &str, operation: &str) -> Result<(), Error>;
+}
+
+/// everything the service needs from a storage backend,
+/// e.g. `ReservationManager` on postgres or `MemoryReservationManager` in memory.
+pub trait Storage:
+    Rsvp + ResourceRegistry + IdempotencyStore + Clone + Send + Sync + 'static
+{
+}
+
+impl<T> Storage for T where
+    T: Rsvp + ResourceRegistry + IdempotencyStore + Clone + Send + Sync + 'static
+{
 }
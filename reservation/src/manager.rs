@@ -1,58 +1,271 @@
-use abi::{Error, Reservation, ReservationQuery, ReservationStatus, Validator};
+use std::{collections::HashMap, ops::Bound, time::Duration};
+
+use abi::{
+    convert_to_utc_time, find_free_slots, validate_range, AvailabilityQuery, BatchConflict,
+    ChangeId, Config, Error, FreeSlot, ListenResponse, PaginationConfig, QueryCursor,
+    QueryMatchMode, Reservation, ReservationConflictInfo, ReservationField, ReservationPage,
+    ReservationQuery, ReservationStatus, ReservationUpdateType, ReservationWindow, RsvpUpdateType,
+    Validator,
+};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::{postgres::types::PgRange, types::Uuid, PgPool, Row};
+use futures::StreamExt;
+use sqlx::{
+    postgres::{types::PgRange, PgExecutor, PgListener, PgPoolOptions},
+    types::Uuid,
+    FromRow, PgConnection, PgPool, Postgres, QueryBuilder, Row,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    ReservationChangeStream, ReservationId, ReservationManager, ReservationQueryStream, Rsvp,
+    UserId,
+};
 
-use crate::{ReservationId, ReservationManager, Rsvp};
+/// the channel notified by the `reservations_trigger`
+const CHANGE_CHANNEL: &str = "reservation_update";
+pub(crate) const CHANGE_BUFFER_SIZE: usize = 128;
+/// how many reservations of a query stream are read ahead of the subscriber
+pub(crate) const QUERY_BUFFER_SIZE: usize = 64;
+/// the cancel reason recorded for the reservations whose hold has expired
+pub(crate) const HOLD_EXPIRED_REASON: &str = "hold expired";
 
 #[async_trait]
 impl Rsvp for ReservationManager {
-    async fn reserve(&self, mut rsvp: Reservation) -> Result<Reservation, Error> {
-        if rsvp.start.is_none() || rsvp.end.is_none() {
-            return Err(Error::InvalidTime);
-        }
-        rsvp.validate()?;
-        let status = ReservationStatus::from_i32(rsvp.status).unwrap_or(ReservationStatus::Pending);
-        let timespan: PgRange<DateTime<Utc>> = rsvp.get_timespan().into();
-        // to run the query
-        let id: Uuid = sqlx::query("INSERT INTO rsvp.reservations (user_id, resource_id, timespan, note, status) VALUES ($1, $2, $3, $4, $5::rsvp.reservation_status) RETURNING id")
-        .bind(rsvp.user_id.to_owned())
-        .bind(rsvp.resource_id.to_owned())
-        .bind(timespan)
-        .bind(rsvp.note.to_owned())
-        .bind(status.to_string())
-        .fetch_one(&self.pool)
-        .await?
-        .get(0);
-        rsvp.id = id.to_string();
-        Ok(rsvp)
+    async fn reserve(&self, rsvp: Reservation) -> Result<Reservation, Error> {
+        if rsvp.is_recurring() {
+            let mut occurrences = self.reserve_series(rsvp).await?;
+            return Ok(occurrences.remove(0));
+        }
+        let rsvp = prepare(rsvp)?;
+        match insert_reservation(&self.pool, &rsvp).await {
+            Ok(Some(rsvp)) => Ok(rsvp),
+            // the resource is unknown or archived
+            Ok(None) => Err(Error::ResourceNotFound(rsvp.resource_id)),
+            Err(e) => Err(self.find_blocking(e.into(), None).await),
+        }
+    }
+    async fn reserve_batch(&self, rsvps: Vec<Reservation>) -> Result<Vec<Reservation>, Error> {
+        let mut rsvps = rsvps
+            .into_iter()
+            .map(|rsvp| match rsvp.is_recurring() {
+                true => Err(Error::InvalidRecurrence(rsvp.recurrence)),
+                false => prepare(rsvp),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tx = self.pool.begin().await?;
+        let mut conflicts = Vec::new();
+        for (index, rsvp) in rsvps.iter_mut().enumerate() {
+            // a failed insert aborts the transaction, so every one is made in a savepoint
+            // to go on checking the rest.
+            sqlx::query("SAVEPOINT batch_item").execute(&mut tx).await?;
+            match insert_reservation(&mut tx, rsvp).await {
+                Ok(Some(created)) => *rsvp = created,
+                Ok(None) => {
+                    tx.rollback().await?;
+                    return Err(Error::ResourceNotFound(rsvp.resource_id.clone()));
+                }
+                Err(e) => {
+                    sqlx::query("ROLLBACK TO SAVEPOINT batch_item")
+                        .execute(&mut tx)
+                        .await?;
+                    match e.into() {
+                        Error::ConflictingReservation(info) => conflicts.push((index, info)),
+                        e => {
+                            tx.rollback().await?;
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            tx.commit().await?;
+            return Ok(rsvps);
+        }
+        // the blocking reservations are looked up before the rollback,
+        // so the earlier ones in the batch are found too.
+        let mut batch_conflicts = Vec::with_capacity(conflicts.len());
+        for (index, info) in conflicts {
+            let info = match find_blocking(&mut tx, Error::ConflictingReservation(info), None).await
+            {
+                Error::ConflictingReservation(info) => info,
+                e => return Err(e),
+            };
+            batch_conflicts.push(batch_conflict(index, info, &rsvps));
+        }
+        tx.rollback().await?;
+        Err(Error::ConflictingBatch(batch_conflicts))
+    }
+    async fn reserve_series(&self, rsvp: Reservation) -> Result<Vec<Reservation>, Error> {
+        if !rsvp.is_recurring() {
+            return Err(Error::InvalidRecurrence(rsvp.recurrence));
+        }
+        let mut rsvp = prepare(rsvp)?;
+        rsvp.series_id = Uuid::new_v4().to_string();
+        let mut occurrences = rsvp.occurrences()?;
+        if occurrences.is_empty() {
+            return Err(Error::InvalidRecurrence(rsvp.recurrence));
+        }
+        // all the occurrences are created or none
+        let mut tx = self.pool.begin().await?;
+        for occurrence in occurrences.iter_mut() {
+            match insert_reservation(&mut tx, occurrence).await {
+                Ok(Some(created)) => *occurrence = created,
+                Ok(None) => {
+                    tx.rollback().await?;
+                    return Err(Error::ResourceNotFound(rsvp.resource_id));
+                }
+                Err(e) => {
+                    tx.rollback().await?;
+                    return Err(self.find_blocking(e.into(), None).await);
+                }
+            }
+        }
+        tx.commit().await?;
+        Ok(occurrences)
     }
     async fn change_status(&self, id: ReservationId) -> Result<Reservation, Error> {
         // if the current status is pending,
         // to change it to confirmed,
-        // otherwise to do nothing
-        let id: Uuid = Uuid::parse_str(&id).map_err(|_| Error::InvalidReservationId(id.clone()))?;
-        let rsvp: Reservation = sqlx::query_as("UPDATE rsvp.reservations SET status = 'confirmed' WHERE id = $1 AND status = 'pending' RETURNING *")
-            .bind(id)
-            .fetch_one(&self.pool)
-            .await?;
-        // println!("{:?}", rsvp);
-        Ok(rsvp)
+        // otherwise to fail with an invalid transition
+        self.transition(id, ReservationStatus::Confirmed).await
+    }
+    async fn transition(
+        &self,
+        id: ReservationId,
+        to: ReservationStatus,
+    ) -> Result<Reservation, Error> {
+        let uuid = parse_id(&id)?;
+        match self.update_status(uuid, to, None, None).await? {
+            Some(rsvp) => Ok(rsvp),
+            None => Err(self.invalid_transition(id, to).await),
+        }
     }
     /// to update the note of the reservation
-    async fn update_note(&self, id: ReservationId, note: String) -> Result<Reservation, Error> {
-        id.validate()?;
-        let rsvp: Reservation =
-            sqlx::query_as("UPDATE rsvp.reservations SET note = $1 WHERE id = $2 RETURNING *")
-                .bind(note)
+    async fn update_note(
+        &self,
+        id: ReservationId,
+        note: String,
+        version: i64,
+    ) -> Result<Reservation, Error> {
+        let uuid = parse_id(&id)?;
+        let rsvp: Option<Reservation> = sqlx::query_as(
+            "UPDATE rsvp.reservations SET note = $1 WHERE id = $2 AND version = $3 RETURNING *",
+        )
+        .bind(note)
+        .bind(uuid)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+        match rsvp {
+            Some(rsvp) => Ok(rsvp),
+            None => Err(self.version_mismatch(id, version).await),
+        }
+    }
+    async fn update(
+        &self,
+        rsvp: Reservation,
+        fields: Vec<ReservationField>,
+    ) -> Result<Reservation, Error> {
+        let id = parse_id(&rsvp.id)?;
+        if fields.is_empty() {
+            return Err(Error::InvalidUpdateMask(String::new()));
+        }
+        let mut tx = self.pool.begin().await?;
+        // the reservation is locked, so the version could not change until it is updated
+        let current: Reservation =
+            sqlx::query_as("SELECT * FROM rsvp.reservations WHERE id = $1 FOR UPDATE")
                 .bind(id)
-                .fetch_one(&self.pool)
+                .fetch_one(&mut tx)
                 .await?;
-        Ok(rsvp)
+        if current.version != rsvp.version {
+            tx.rollback().await?;
+            return Err(Error::VersionMismatch {
+                expected: rsvp.version,
+                found: current.version,
+            });
+        }
+        let status = ReservationStatus::from_i32(current.status).unwrap_or_default();
+        if let Err(e) = ReservationField::check_update(&fields, status) {
+            tx.rollback().await?;
+            return Err(e);
+        }
+        let mut updated = current.clone();
+        fields
+            .iter()
+            .for_each(|field| field.apply(&rsvp, &mut updated));
+        if let Err(e) = updated
+            .validate()
+            .and(validate_range(updated.start.as_ref(), updated.end.as_ref()))
+        {
+            tx.rollback().await?;
+            return Err(e);
+        }
+        if updated.resource_id != current.resource_id {
+            let resource: Option<String> = sqlx::query_scalar(
+                "SELECT id FROM rsvp.resources WHERE id = $1 AND archived_at IS NULL FOR UPDATE",
+            )
+            .bind(&updated.resource_id)
+            .fetch_optional(&mut tx)
+            .await?;
+            if resource.is_none() {
+                tx.rollback().await?;
+                return Err(Error::ResourceNotFound(updated.resource_id));
+            }
+        }
+        // the capacity trigger checks the new time slot, not counting the reservation itself
+        let result = sqlx::query_as(
+            "UPDATE rsvp.reservations SET resource_id = $2, timespan = $3, note = $4 WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .bind(&updated.resource_id)
+        .bind(updated.get_timespan())
+        .bind(&updated.note)
+        .fetch_one(&mut tx)
+        .await;
+        match result {
+            Ok(updated) => {
+                tx.commit().await?;
+                Ok(updated)
+            }
+            // the original is kept
+            Err(e) => {
+                tx.rollback().await?;
+                Err(self.find_blocking(e.into(), Some(id)).await)
+            }
+        }
+    }
+    /// to cancel the reservation by its id
+    async fn cancel(
+        &self,
+        id: ReservationId,
+        cancelled_by: UserId,
+        reason: String,
+    ) -> Result<Reservation, Error> {
+        let uuid = parse_id(&id)?;
+        let to = ReservationStatus::Cancelled;
+        let rsvp = self
+            .update_status(
+                uuid,
+                to,
+                str_to_option(&cancelled_by),
+                str_to_option(&reason),
+            )
+            .await?;
+        match rsvp {
+            Some(rsvp) => Ok(rsvp),
+            None => match self.get(id.clone()).await? {
+                // already cancelled, the original cancellation is kept
+                rsvp if rsvp.status == to as i32 => Ok(rsvp),
+                _ => Err(self.invalid_transition(id, to).await),
+            },
+        }
     }
     /// to delete the reservation by its id
     async fn delete(&self, id: ReservationId) -> Result<Reservation, Error> {
-        id.validate()?;
+        let id = parse_id(&id)?;
         let rsvp: Reservation =
             sqlx::query_as("DELETE FROM rsvp.reservations WHERE id = $1 RETURNING *")
                 .bind(id)
@@ -62,51 +275,584 @@ impl Rsvp for ReservationManager {
     }
     // to get one reservation by its id
     async fn get(&self, id: ReservationId) -> Result<Reservation, Error> {
-        id.validate()?;
-        let rsvp: Reservation = sqlx::query_as("SELECT FROM rsvp.reservations WHERE id = $id")
+        let id = parse_id(&id)?;
+        let rsvp: Reservation = sqlx::query_as("SELECT * FROM rsvp.reservations WHERE id = $1")
             .bind(id)
             .fetch_one(&self.pool)
             .await?;
         Ok(rsvp)
     }
     // to query reservation(s) by `query`
-    async fn query(&self, query: ReservationQuery) -> Result<Vec<Reservation>, Error> {
-        let user_id = str_to_option(&query.user_id);
-        let resource_id = str_to_option(&query.resource_id);
-        let range: PgRange<DateTime<Utc>> = query.get_timespan();
-        let status =
-            ReservationStatus::from_i32(query.status).unwrap_or(ReservationStatus::Pending);
-        let rsvp = sqlx::query_as(
-            "SELECT * FROM rsvp.query($1, $2, $3, $4::rsvp.reservation_status, $5, $6, $7)",
+    async fn query(&self, query: ReservationQuery) -> Result<ReservationPage, Error> {
+        let page_size = query.get_page_size(&self.pagination)?;
+        let cursor = query.get_cursor()?;
+        validate_range(query.start.as_ref(), query.end.as_ref())?;
+
+        let mut builder = QueryBuilder::new("SELECT count(*) FROM rsvp.reservations WHERE ");
+        push_query_filters(&mut builder, &query);
+        let (total,): (i64,) = builder.build_query_as().fetch_one(&self.pool).await?;
+
+        let mut builder = QueryBuilder::new("SELECT * FROM rsvp.reservations WHERE ");
+        push_query_filters(&mut builder, &query);
+        push_query_order(&mut builder, &query, cursor.as_ref());
+        builder
+            .push(" LIMIT ")
+            // one more to tell if there is a next page
+            .push_bind(page_size as i64 + 1);
+        if cursor.is_none() && query.page > 1 {
+            builder
+                .push(" OFFSET ")
+                .push_bind((query.page as i64 - 1) * page_size as i64);
+        }
+        let mut reservations: Vec<Reservation> =
+            builder.build_query_as().fetch_all(&self.pool).await?;
+        let has_more = reservations.len() > page_size as usize;
+        reservations.truncate(page_size as usize);
+        let next_cursor = match reservations.last() {
+            Some(last) if has_more => QueryCursor::after(last)?.to_string(),
+            _ => String::new(),
+        };
+        Ok(ReservationPage {
+            reservations,
+            next_cursor,
+            total,
+            has_more,
+        })
+    }
+    async fn query_stream(&self, query: ReservationQuery) -> Result<ReservationQueryStream, Error> {
+        let cursor = query.get_cursor()?;
+        validate_range(query.start.as_ref(), query.end.as_ref())?;
+        let (tx, rx) = mpsc::channel(QUERY_BUFFER_SIZE);
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let mut builder = QueryBuilder::new("SELECT * FROM rsvp.reservations WHERE ");
+            push_query_filters(&mut builder, &query);
+            push_query_order(&mut builder, &query, cursor.as_ref());
+            let mut rows = builder.build_query_as::<Reservation>().fetch(&pool);
+            // the next row is read only when the last one is taken by the subscriber
+            while let Some(row) = rows.next().await {
+                let failed = row.is_err();
+                if tx.send(row.map_err(Error::from)).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Ok(Box::pin(ReceiverStream::new(rx)))
+    }
+    async fn find_free_slots(&self, query: AvailabilityQuery) -> Result<Vec<FreeSlot>, Error> {
+        query.validate()?;
+        let capacities: HashMap<String, i32> = sqlx::query_as(
+            "SELECT id, capacity FROM rsvp.resources WHERE id = ANY($1) AND archived_at IS NULL",
         )
-        .bind(user_id)
-        .bind(resource_id)
-        .bind(range)
-        .bind(status.to_string())
-        .bind(query.page)
-        .bind(query.desc)
-        .bind(query.page_size)
+        .bind(&query.resource_ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+        let rsvps: Vec<Reservation> = sqlx::query_as(
+            "SELECT * FROM rsvp.reservations WHERE resource_id = ANY($1) AND timespan && $2 AND status::text = ANY($3)",
+        )
+        .bind(&query.resource_ids)
+        .bind(query.get_timespan())
+        .bind(blocking_statuses())
         .fetch_all(&self.pool)
         .await?;
-        Ok(rsvp)
+        let (start, end) = match (query.start.as_ref(), query.end.as_ref()) {
+            (Some(start), Some(end)) => (convert_to_utc_time(start), convert_to_utc_time(end)),
+            _ => return Err(Error::InvalidTime),
+        };
+        let mut slots = Vec::new();
+        for rid in &query.resource_ids {
+            let capacity = capacities
+                .get(rid)
+                .ok_or_else(|| Error::ResourceNotFound(rid.clone()))?;
+            let spans: Vec<_> = rsvps
+                .iter()
+                .filter(|rsvp| &rsvp.resource_id == rid)
+                .map(Reservation::get_span)
+                .collect();
+            slots.extend(find_free_slots(
+                rid,
+                start,
+                end,
+                &spans,
+                *capacity as usize,
+                query.min_duration(),
+            ));
+        }
+        Ok(slots)
+    }
+    async fn get_series(&self, series_id: String) -> Result<Vec<Reservation>, Error> {
+        let series_id = parse_series_id(&series_id)?;
+        let occurrences: Vec<Reservation> = sqlx::query_as(
+            "SELECT * FROM rsvp.reservations WHERE series_id = $1 ORDER BY lower(timespan)",
+        )
+        .bind(series_id)
+        .fetch_all(&self.pool)
+        .await?;
+        if occurrences.is_empty() {
+            return Err(Error::NotFound);
+        }
+        Ok(occurrences)
+    }
+    async fn cancel_series(
+        &self,
+        series_id: String,
+        cancelled_by: UserId,
+        reason: String,
+    ) -> Result<Vec<Reservation>, Error> {
+        let uuid = parse_series_id(&series_id)?;
+        let from: Vec<String> = ReservationStatus::previous_statuses(ReservationStatus::Cancelled)
+            .iter()
+            .map(|status| status.to_string())
+            .collect();
+        sqlx::query(
+            "UPDATE rsvp.reservations SET status = 'cancelled', cancelled_at = now(), cancelled_by = $2, cancel_reason = $3 WHERE series_id = $1 AND status::text = ANY($4) AND upper(timespan) > now()",
+        )
+        .bind(uuid)
+        .bind(str_to_option(&cancelled_by))
+        .bind(str_to_option(&reason))
+        .bind(from)
+        .execute(&self.pool)
+        .await?;
+        self.get_series(series_id).await
+    }
+    async fn update_series_note(
+        &self,
+        series_id: String,
+        note: String,
+    ) -> Result<Vec<Reservation>, Error> {
+        let uuid = parse_series_id(&series_id)?;
+        sqlx::query("UPDATE rsvp.reservations SET note = $2 WHERE series_id = $1")
+            .bind(uuid)
+            .bind(note)
+            .execute(&self.pool)
+            .await?;
+        self.get_series(series_id).await
+    }
+    // to subscribe to the changes recorded in `rsvp.reservation_changes`
+    async fn listen(&self, after: Option<ChangeId>) -> Result<ReservationChangeStream, Error> {
+        // to listen before reading the table, so no change falls in between
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(CHANGE_CHANNEL).await?;
+        let last_id = match after {
+            Some(id) => id,
+            // without a cursor, changes made before the subscription are not replayed
+            None => {
+                self.sequence_changes().await?;
+                sqlx::query_scalar(
+                    "SELECT COALESCE(MAX(position), 0) FROM rsvp.reservation_changes",
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+        };
+        let (tx, rx) = mpsc::channel(CHANGE_BUFFER_SIZE);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut last_id = last_id;
+            loop {
+                let changes = match manager.fetch_changes(last_id).await {
+                    Ok(changes) => changes,
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                };
+                for change in changes {
+                    last_id = change.change_id;
+                    if tx.send(Ok(change)).await.is_err() {
+                        return;
+                    }
+                }
+                tokio::select! {
+                    notification = listener.recv() => {
+                        if let Err(e) = notification {
+                            let _ = tx.send(Err(e.into())).await;
+                            return;
+                        }
+                    }
+                    // the subscriber has gone away
+                    _ = tx.closed() => return,
+                }
+            }
+        });
+        Ok(Box::pin(ReceiverStream::new(rx)))
+    }
+}
+
+/// to validate the new reservation, and to drop what is not stored for its status
+pub(crate) fn prepare(mut rsvp: Reservation) -> Result<Reservation, Error> {
+    if rsvp.start.is_none() || rsvp.end.is_none() {
+        return Err(Error::InvalidTime);
+    }
+    rsvp.validate()?;
+    // the id is given by the storage once it is made
+    rsvp.id.clear();
+    let status = ReservationStatus::initial(rsvp.status)?;
+    rsvp.status = status as i32;
+    // only a pending reservation is held until it is confirmed
+    if status != ReservationStatus::Pending {
+        rsvp.hold_expires_at = None;
+    }
+    Ok(rsvp)
+}
+
+/// to insert the reservation, `None` is returned if the resource is unknown or archived
+async fn insert_reservation<'e>(
+    executor: impl PgExecutor<'e>,
+    rsvp: &Reservation,
+) -> Result<Option<Reservation>, sqlx::Error> {
+    let status = ReservationStatus::from_i32(rsvp.status).unwrap_or(ReservationStatus::Pending);
+    let timespan: PgRange<DateTime<Utc>> = rsvp.get_timespan();
+    let hold_expires_at = rsvp.hold_expires_at.as_ref().map(convert_to_utc_time);
+    let series_id = Uuid::parse_str(&rsvp.series_id).ok();
+    // the resource row is locked, so it could not be archived meanwhile,
+    // and the capacity trigger sees the reservations on it one by one.
+    sqlx::query_as("INSERT INTO rsvp.reservations (user_id, resource_id, timespan, note, status, hold_expires_at, series_id, recurrence) SELECT $1, id, $3, $4, $5::rsvp.reservation_status, $6, $7, $8 FROM rsvp.resources WHERE id = $2 AND archived_at IS NULL FOR UPDATE RETURNING *")
+        .bind(&rsvp.user_id)
+        .bind(&rsvp.resource_id)
+        .bind(timespan)
+        .bind(&rsvp.note)
+        .bind(status.to_string())
+        .bind(hold_expires_at)
+        .bind(series_id)
+        .bind(str_to_option(&rsvp.recurrence))
+        .fetch_optional(executor)
+        .await
+}
+
+pub(crate) fn parse_series_id(id: &str) -> Result<Uuid, Error> {
+    Uuid::parse_str(id).map_err(|_| Error::InvalidReservationId(id.to_owned()))
+}
+
+pub(crate) fn parse_id(id: &ReservationId) -> Result<Uuid, Error> {
+    id.validate()?;
+    Uuid::parse_str(id).map_err(|_| Error::InvalidReservationId(id.clone()))
+}
+
+/// to look up the reservations saturating the capacity of the resource in a conflict,
+/// those made earlier in the same transaction on `conn` included.
+/// `except` is the reservation being updated, which never blocks itself
+async fn find_blocking(conn: &mut PgConnection, err: Error, except: Option<Uuid>) -> Error {
+    match err {
+        Error::ConflictingReservation(ReservationConflictInfo::Parsed(mut conflict)) => {
+            let timespan = PgRange {
+                start: Bound::Included(conflict.new.start),
+                end: Bound::Excluded(conflict.new.end),
+            };
+            // the conflict is still reported even if the lookup fails
+            // only the statuses checked by the `reservations_conflict` constraint block others
+            let overlapping: Vec<Reservation> = sqlx::query_as(
+                "SELECT * FROM rsvp.reservations WHERE resource_id = $1 AND timespan && $2 AND status::text = ANY($3) AND id IS DISTINCT FROM $4 ORDER BY lower(timespan)",
+            )
+            .bind(&conflict.new.rid)
+            .bind(timespan)
+            .bind(blocking_statuses())
+            .bind(except)
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap_or_default();
+            let capacity: i32 =
+                sqlx::query_scalar("SELECT capacity FROM rsvp.resources WHERE id = $1")
+                    .bind(&conflict.new.rid)
+                    .fetch_one(&mut *conn)
+                    .await
+                    .unwrap_or(1);
+            conflict.blocking = saturating(overlapping, &conflict.new, capacity as usize);
+            Error::ConflictingReservation(ReservationConflictInfo::Parsed(conflict))
+        }
+        err => err,
+    }
+}
+
+/// to split the blocking reservations in the conflict of the batch item `index`
+/// into the existing ones and the earlier items of the `batch`, which are rolled back with it.
+pub(crate) fn batch_conflict(
+    index: usize,
+    info: ReservationConflictInfo,
+    batch: &[Reservation],
+) -> BatchConflict {
+    let mut blocking_indexes = Vec::new();
+    let info = match info {
+        ReservationConflictInfo::Parsed(mut conflict) => {
+            conflict.blocking.retain(|rsvp| {
+                match batch
+                    .iter()
+                    .position(|item| !item.id.is_empty() && item.id == rsvp.id)
+                {
+                    Some(i) => {
+                        blocking_indexes.push(i);
+                        false
+                    }
+                    None => true,
+                }
+            });
+            ReservationConflictInfo::Parsed(conflict)
+        }
+        info => info,
+    };
+    blocking_indexes.sort_unstable();
+    BatchConflict {
+        index,
+        info,
+        blocking_indexes,
+    }
+}
+
+/// the reservations in `overlapping` which take part in using up the `capacity`
+/// at some point during `window`, in the same order.
+pub(crate) fn saturating(
+    overlapping: Vec<Reservation>,
+    window: &ReservationWindow,
+    capacity: usize,
+) -> Vec<Reservation> {
+    let spans: Vec<_> = overlapping.iter().map(Reservation::get_span).collect();
+    // the most overlapping reservations are found at the start of the window,
+    // or at the start of one of them.
+    let points = std::iter::once(window.start).chain(
+        spans
+            .iter()
+            .map(|(start, _)| *start)
+            .filter(|start| *start > window.start && *start < window.end),
+    );
+    let mut saturated = vec![false; spans.len()];
+    for point in points {
+        let covering: Vec<usize> = spans
+            .iter()
+            .enumerate()
+            .filter(|(_, (start, end))| *start <= point && point < *end)
+            .map(|(i, _)| i)
+            .collect();
+        if covering.len() >= capacity {
+            covering.into_iter().for_each(|i| saturated[i] = true);
+        }
     }
+    overlapping
+        .into_iter()
+        .zip(saturated)
+        .filter_map(|(rsvp, saturated)| saturated.then_some(rsvp))
+        .collect()
 }
 
-fn str_to_option(s: &str) -> Option<&str> {
-    return if s.is_empty() { None } else { Some(s) };
+/// to push the conditions of the query, shared by the count and the page
+fn push_query_filters(builder: &mut QueryBuilder<'_, Postgres>, query: &ReservationQuery) {
+    let status = ReservationStatus::from_i32(query.status).unwrap_or(ReservationStatus::Pending);
+    builder
+        .push("status = ")
+        .push_bind(status.to_string())
+        .push("::rsvp.reservation_status AND ")
+        .push_bind(query.get_timespan());
+    // the time window and the timespans are both half open
+    builder.push(match query.get_match_mode() {
+        QueryMatchMode::Contained => " @> timespan",
+        QueryMatchMode::Overlapping => " && timespan",
+        QueryMatchMode::StartingWithin => " @> lower(timespan)",
+    });
+    if let Some(user_id) = str_to_option(&query.user_id) {
+        builder
+            .push(" AND user_id = ")
+            .push_bind(user_id.to_owned());
+    }
+    if let Some(resource_id) = str_to_option(&query.resource_id) {
+        builder
+            .push(" AND resource_id = ")
+            .push_bind(resource_id.to_owned());
+    }
+}
+
+/// to push the keyset condition after the cursor and the order of the query
+fn push_query_order(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    query: &ReservationQuery,
+    cursor: Option<&QueryCursor>,
+) {
+    // keyset pagination, the next page starts right after the cursor
+    if let Some(cursor) = cursor {
+        builder
+            .push(if query.desc {
+                " AND (lower(timespan), id) < ("
+            } else {
+                " AND (lower(timespan), id) > ("
+            })
+            .push_bind(cursor.start)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+    let order = if query.desc { "DESC" } else { "ASC" };
+    builder.push(format_args!(
+        " ORDER BY lower(timespan) {}, id {}",
+        order, order
+    ));
+}
+
+/// names of the statuses which keep the time slot, to bind in the queries
+pub(crate) fn blocking_statuses() -> Vec<String> {
+    ReservationStatus::BLOCKING
+        .iter()
+        .map(|status| status.to_string())
+        .collect()
+}
+
+pub(crate) fn str_to_option(s: &str) -> Option<&str> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
 }
 
 impl ReservationManager {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            pagination: PaginationConfig::default(),
+        }
+    }
+    /// to limit the page size of the queries other than the default limits
+    pub fn with_pagination(mut self, pagination: PaginationConfig) -> Self {
+        self.pagination = pagination;
+        self
+    }
+    /// to move the reservation to `to` if its current status allows it,
+    /// `None` is returned if it is not found or not allowed.
+    async fn update_status(
+        &self,
+        id: Uuid,
+        to: ReservationStatus,
+        cancelled_by: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<Option<Reservation>, Error> {
+        let from: Vec<String> = ReservationStatus::previous_statuses(to)
+            .iter()
+            .map(|status| status.to_string())
+            .collect();
+        // the current status is checked in the same statement, so no transition is lost to a race
+        let rsvp = sqlx::query_as(
+            "UPDATE rsvp.reservations SET status = $2::rsvp.reservation_status, cancelled_at = CASE WHEN $2 = 'cancelled' THEN now() ELSE cancelled_at END, cancelled_by = COALESCE($4, cancelled_by), cancel_reason = COALESCE($5, cancel_reason) WHERE id = $1 AND status::text = ANY($3) RETURNING *",
+        )
+        .bind(id)
+        .bind(to.to_string())
+        .bind(from)
+        .bind(cancelled_by)
+        .bind(reason)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(rsvp)
+    }
+    /// to tell why the update of the given version matched nothing
+    async fn version_mismatch(&self, id: ReservationId, expected: i64) -> Error {
+        match self.get(id).await {
+            Ok(current) => Error::VersionMismatch {
+                expected,
+                found: current.version,
+            },
+            Err(e) => e,
+        }
+    }
+    /// the error for a failed transition to `to`, `Error::NotFound` if the reservation is gone
+    async fn invalid_transition(&self, id: ReservationId, to: ReservationStatus) -> Error {
+        match self.get(id).await {
+            Ok(rsvp) => Error::InvalidStatusTransition {
+                from: ReservationStatus::from_i32(rsvp.status).unwrap_or_default(),
+                to,
+            },
+            Err(e) => e,
+        }
+    }
+    /// to look up the existing reservations saturating the capacity of the resource in a conflict
+    /// `except` is the reservation being updated, which never blocks itself
+    async fn find_blocking(&self, err: Error, except: Option<Uuid>) -> Error {
+        // the conflict is still reported even if the lookup fails
+        match self.pool.acquire().await {
+            Ok(mut conn) => find_blocking(&mut conn, err, except).await,
+            Err(_) => err,
+        }
+    }
+    /// to give the committed changes their position in the feed, in the order they are seen.
+    /// the listeners take turns, so the changes seen by a later turn are always placed after
+    /// the ones of the earlier turns, even if they were recorded before them.
+    async fn sequence_changes(&self) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('rsvp.reservation_changes'))")
+            .execute(&mut tx)
+            .await?;
+        sqlx::query("UPDATE rsvp.reservation_changes c SET position = s.position FROM (SELECT id, nextval('rsvp.reservation_changes_position_seq') AS position FROM (SELECT id FROM rsvp.reservation_changes WHERE position IS NULL ORDER BY id) unsequenced) s WHERE c.id = s.id")
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+    /// to fetch the changes placed after the position `last_id`, in the order they were committed
+    async fn fetch_changes(&self, last_id: ChangeId) -> Result<Vec<ListenResponse>, Error> {
+        self.sequence_changes().await?;
+        // the reservation may have been deleted since the change was recorded,
+        // in that case only the id is populated.
+        let rows = sqlx::query(
+            "SELECT c.position AS change_id, c.reservation_id, c.op, r.* FROM rsvp.reservation_changes c LEFT JOIN rsvp.reservations r ON r.id = c.reservation_id AND c.op <> 'delete' WHERE c.position > $1 ORDER BY c.position",
+        )
+        .bind(last_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut responses = Vec::with_capacity(rows.len());
+        for row in rows {
+            let reservation_id: Uuid = row.try_get("reservation_id")?;
+            let op: RsvpUpdateType = row.try_get("op")?;
+            let found: Option<Uuid> = row.try_get("id")?;
+            let rsvp = match found {
+                Some(_) => Reservation::from_row(&row)?,
+                None => Reservation {
+                    id: reservation_id.to_string(),
+                    ..Default::default()
+                },
+            };
+            responses.push(ListenResponse {
+                op: ReservationUpdateType::from(op) as i32,
+                reservation: Some(rsvp),
+                change_id: row.try_get("change_id")?,
+            });
+        }
+        Ok(responses)
+    }
+    /// to prune the changes recorded more than `retention` ago,
+    /// returns the number of the pruned changes.
+    pub async fn prune_changes(&self, retention: Duration) -> Result<u64, Error> {
+        let result =
+            sqlx::query("DELETE FROM rsvp.reservation_changes WHERE changed_at < now() - $1")
+                .bind(retention)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected())
+    }
+    /// to cancel the pending reservations whose hold has expired, so their time slots are released.
+    /// the changes are recorded by the trigger like any other cancellation,
+    /// returns the number of the expired reservations.
+    pub async fn expire_holds(&self) -> Result<u64, Error> {
+        let result = sqlx::query(
+            "UPDATE rsvp.reservations SET status = 'cancelled', cancelled_at = now(), cancel_reason = $1 WHERE status = 'pending' AND hold_expires_at <= now()",
+        )
+        .bind(HOLD_EXPIRED_REASON)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+    /// to connect to the database, with the page size limited by the config
+    pub async fn from_config(config: &Config) -> Result<Self, Error> {
+        let pool = PgPoolOptions::default()
+            .max_connections(config.db.max_connections)
+            .connect_with(config.db.connect_options())
+            .await?;
+        Ok(Self::new(pool).with_pagination(config.pagination.clone()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ResourceRegistry;
     use abi::{Error::*, ReservationQueryBuilder};
-    use abi::{ReservationConflict, ReservationConflictInfo, ReservationWindow};
+    use abi::{ReservationConflict, Resource};
     use prost_types::Timestamp;
     const DUMMY_USER_ID_LEON: &str = "dummy_user_id_leon";
     const DUMMY_USER_ID_ALICE: &str = "dummy_user_id_alice";
@@ -117,8 +863,32 @@ mod tests {
         assert!(!rsvp.id.is_empty());
     }
     #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_should_reject_statuses_reached_by_transition_only() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        ensure_resource(&manager, DUMMY_ROOM_NAME).await;
+        let mut rsvp = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "",
+        );
+        rsvp.status = ReservationStatus::Completed as i32;
+        let err = manager.reserve(rsvp.clone()).await.unwrap_err();
+        assert_eq!(
+            err,
+            InvalidStatusTransition {
+                from: ReservationStatus::Unknown,
+                to: ReservationStatus::Completed
+            }
+        );
+        rsvp.status = ReservationStatus::Confirmed as i32;
+        let rsvp = manager.reserve(rsvp).await.unwrap();
+        assert_eq!(rsvp.status, ReservationStatus::Confirmed as i32);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
     async fn reserve_should_reject_conflicting_reservations() {
-        let (_rsvp1, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let (rsvp1, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
         let rsvp2 = Reservation::new_pending(
             DUMMY_USER_ID_ALICE,
             DUMMY_ROOM_NAME,
@@ -138,6 +908,7 @@ mod tests {
                 start: "2023-12-25T15:00:00-0700".parse().unwrap(),
                 end: "2023-12-28T12:00:00-0700".parse().unwrap(),
             },
+            blocking: vec![rsvp1],
         });
         assert_eq!(err, ConflictingReservation(info));
     }
@@ -151,11 +922,69 @@ mod tests {
         );
     }
     #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
-    async fn reserve_change_staus_not_pending_should_do_nothing() {
+    async fn reserve_change_staus_not_pending_should_be_rejected() {
         let (rsvp_pending, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
         let rsvp = manager.change_status(rsvp_pending.id).await.unwrap();
         let err = manager.change_status(rsvp.id).await.unwrap_err();
-        assert_eq!(err, Error::NotFound);
+        assert_eq!(
+            err,
+            InvalidStatusTransition {
+                from: ReservationStatus::Confirmed,
+                to: ReservationStatus::Confirmed,
+            }
+        );
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn transition_should_follow_the_lifecycle() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        for to in [
+            ReservationStatus::Confirmed,
+            ReservationStatus::CheckedIn,
+            ReservationStatus::Completed,
+        ] {
+            let rsvp = manager.transition(rsvp.id.clone(), to).await.unwrap();
+            assert_eq!(rsvp.status, to as i32);
+        }
+        let err = manager
+            .cancel(rsvp.id.clone(), DUMMY_USER_ID_LEON.into(), String::new())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            InvalidStatusTransition {
+                from: ReservationStatus::Completed,
+                to: ReservationStatus::Cancelled,
+            }
+        );
+        let err = manager
+            .transition(
+                "00000000-0000-0000-0000-000000000000".into(),
+                ReservationStatus::Confirmed,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err, NotFound);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn no_show_should_free_the_slot() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        manager.change_status(rsvp.id.clone()).await.unwrap();
+        let rsvp = manager
+            .transition(rsvp.id, ReservationStatus::NoShow)
+            .await
+            .unwrap();
+        assert_eq!(rsvp.status, ReservationStatus::NoShow as i32);
+        assert!(rsvp.cancelled_at.is_none());
+        let (rsvp, _manager) = make_reservation(
+            migrated_pool.clone(),
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-26T15:00:00-0700",
+            "2023-12-30T12:00:00-0700",
+            "hello",
+        )
+        .await;
+        assert_eq!(rsvp.status, ReservationStatus::Pending as i32);
     }
     #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
     async fn query_reservations_should_work() {
@@ -167,7 +996,7 @@ mod tests {
             .status(abi::ReservationStatus::Pending as i32)
             .build()
             .unwrap();
-        let rsvps = manager.query(query).await.unwrap();
+        let rsvps = manager.query(query).await.unwrap().reservations;
         assert_eq!(rsvps.len(), 1);
         assert_eq!(rsvps[0], rsvp);
 
@@ -180,7 +1009,7 @@ mod tests {
             .status(abi::ReservationStatus::Pending as i32)
             .build()
             .unwrap();
-        let rsvps = manager.query(query).await.unwrap();
+        let rsvps = manager.query(query).await.unwrap().reservations;
         assert_eq!(rsvps.len(), 0);
 
         // to change the status to confirmed, the result should not be empty.
@@ -191,13 +1020,1032 @@ mod tests {
             .status(abi::ReservationStatus::Confirmed as i32)
             .build()
             .unwrap();
-        let rsvps = manager.query(query.clone()).await.unwrap();
+        let rsvps = manager.query(query.clone()).await.unwrap().reservations;
         assert_eq!(rsvps.len(), 0);
         let rsvp = manager.change_status(rsvp.id).await.unwrap();
-        let rsvps = manager.query(query).await.unwrap();
+        let rsvps = manager.query(query).await.unwrap().reservations;
         assert_eq!(rsvps.len(), 1);
         assert_eq!(rsvps[0], rsvp);
     }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn query_stream_should_return_all_the_matching_ones() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let mut made = Vec::new();
+        // more than a page
+        for day in 1..=12 {
+            let (rsvp, _) = make_reservation(
+                migrated_pool.clone(),
+                DUMMY_USER_ID_LEON,
+                DUMMY_ROOM_NAME,
+                &format!("2024-01-{:02}T15:00:00-0700", day),
+                &format!("2024-01-{:02}T18:00:00-0700", day),
+                "",
+            )
+            .await;
+            made.push(rsvp);
+        }
+        let mut query = ReservationQueryBuilder::default()
+            .resource_id(DUMMY_ROOM_NAME.to_owned())
+            .start("2024-01-01T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .end("2024-02-01T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .status(abi::ReservationStatus::Pending as i32)
+            .desc(true)
+            .build()
+            .unwrap();
+        let streamed: Vec<Reservation> = manager
+            .query_stream(query.clone())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        made.reverse();
+        assert_eq!(streamed, made);
+        // to resume after a cursor
+        query.cursor = QueryCursor::after(&made[9]).unwrap().to_string();
+        let streamed: Vec<Reservation> = manager
+            .query_stream(query)
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(streamed, made[10..]);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn query_should_match_by_the_mode() {
+        // on different rooms, so they do not conflict
+        let bookings = [
+            (
+                "straddling-start",
+                "2023-12-24T22:00:00+0000",
+                "2023-12-25T02:00:00+0000",
+            ),
+            (
+                "inside",
+                "2023-12-25T10:00:00+0000",
+                "2023-12-25T12:00:00+0000",
+            ),
+            (
+                "straddling-end",
+                "2023-12-25T22:00:00+0000",
+                "2023-12-26T02:00:00+0000",
+            ),
+            (
+                "ending-at-start",
+                "2023-12-24T20:00:00+0000",
+                "2023-12-25T00:00:00+0000",
+            ),
+            (
+                "starting-at-end",
+                "2023-12-26T00:00:00+0000",
+                "2023-12-26T02:00:00+0000",
+            ),
+            (
+                "whole-window",
+                "2023-12-25T00:00:00+0000",
+                "2023-12-26T00:00:00+0000",
+            ),
+        ];
+        let mut manager = None;
+        for (rid, start, end) in bookings {
+            let (_, m) = make_reservation(
+                migrated_pool.clone(),
+                DUMMY_USER_ID_LEON,
+                rid,
+                start,
+                end,
+                "",
+            )
+            .await;
+            manager = Some(m);
+        }
+        let manager = manager.unwrap();
+        let query = |mode: QueryMatchMode| {
+            ReservationQueryBuilder::default()
+                .user_id(DUMMY_USER_ID_LEON.to_owned())
+                .start("2023-12-25T00:00:00+0000".parse::<Timestamp>().unwrap())
+                .end("2023-12-26T00:00:00+0000".parse::<Timestamp>().unwrap())
+                .status(abi::ReservationStatus::Pending as i32)
+                .match_mode(mode as i32)
+                .build()
+                .unwrap()
+        };
+        for (mode, expected) in [
+            (QueryMatchMode::Contained, vec!["whole-window", "inside"]),
+            (
+                QueryMatchMode::Overlapping,
+                vec![
+                    "straddling-start",
+                    "whole-window",
+                    "inside",
+                    "straddling-end",
+                ],
+            ),
+            (
+                QueryMatchMode::StartingWithin,
+                vec!["whole-window", "inside", "straddling-end"],
+            ),
+        ] {
+            let page = manager.query(query(mode)).await.unwrap();
+            let rids: Vec<_> = page
+                .reservations
+                .iter()
+                .map(|rsvp| rsvp.resource_id.as_str())
+                .collect();
+            assert_eq!(rids, expected, "{:?}", mode);
+        }
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn query_should_page_by_cursor() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let mut made = Vec::new();
+        // two of them start at the same time, they are ordered by id
+        for (rid, day) in [
+            ("room-1", 25),
+            ("room-2", 25),
+            ("room-1", 26),
+            ("room-1", 27),
+        ] {
+            let (rsvp, _) = make_reservation(
+                migrated_pool.clone(),
+                DUMMY_USER_ID_LEON,
+                rid,
+                &format!("2023-12-{}T15:00:00-0700", day),
+                &format!("2023-12-{}T18:00:00-0700", day),
+                "",
+            )
+            .await;
+            made.push(rsvp);
+        }
+        made.sort_by_key(|rsvp| (rsvp.get_span().0, Uuid::parse_str(&rsvp.id).unwrap()));
+        let mut query = ReservationQueryBuilder::default()
+            .user_id(DUMMY_USER_ID_LEON.to_owned())
+            .start("2023-12-24T15:00:00-0700".parse::<Timestamp>().unwrap())
+            .end("2023-12-30T12:00:00-0700".parse::<Timestamp>().unwrap())
+            .status(abi::ReservationStatus::Pending as i32)
+            .page_size(3)
+            .build()
+            .unwrap();
+        let page = manager.query(query.clone()).await.unwrap();
+        assert_eq!(page.total, 4);
+        assert!(page.has_more);
+        assert_eq!(page.reservations, made[..3]);
+        // a reservation made before the cursor does not shift the next page
+        make_reservation(
+            migrated_pool.clone(),
+            DUMMY_USER_ID_LEON,
+            "room-3",
+            "2023-12-24T18:00:00-0700",
+            "2023-12-24T19:00:00-0700",
+            "",
+        )
+        .await;
+        query.cursor = page.next_cursor;
+        let page = manager.query(query.clone()).await.unwrap();
+        assert_eq!(page.total, 5);
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, "");
+        assert_eq!(page.reservations, made[3..]);
+        query.desc = true;
+        query.cursor = QueryCursor::after(&made[2]).unwrap().to_string();
+        let page = manager.query(query.clone()).await.unwrap();
+        assert_eq!(page.reservations.len(), 3);
+        assert_eq!(page.reservations[..2], [made[1].clone(), made[0].clone()]);
+        query.cursor = "not-a-cursor".into();
+        let err = manager.query(query).await.unwrap_err();
+        assert_eq!(err, InvalidCursor("not-a-cursor".into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn update_note_should_work() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        assert_eq!(rsvp.version, 1);
+        let updated = manager
+            .update_note(
+                rsvp.id.clone(),
+                "I will arrive at 5PM.".into(),
+                rsvp.version,
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.note, "I will arrive at 5PM.");
+        assert_eq!(updated.version, 2);
+        let updated_at =
+            |rsvp: &Reservation| convert_to_utc_time(rsvp.updated_at.as_ref().unwrap());
+        assert!(updated_at(&updated) >= updated_at(&rsvp));
+        // the second edit based on the same version is stale
+        let err = manager
+            .update_note(
+                rsvp.id.clone(),
+                "I will arrive at 6PM.".into(),
+                rsvp.version,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::VersionMismatch {
+                expected: 1,
+                found: 2
+            }
+        );
+        assert_eq!(manager.get(rsvp.id).await.unwrap(), updated);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn update_should_reschedule_and_move_the_reservation() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        // a day later still overlaps the original window, which should not block itself
+        let mut later = rsvp.clone();
+        later.start = Some("2023-12-26T15:00:00-0700".parse::<Timestamp>().unwrap());
+        later.end = Some("2023-12-29T12:00:00-0700".parse::<Timestamp>().unwrap());
+        later.note = "ignored".into();
+        let updated = manager
+            .update(
+                later.clone(),
+                vec![ReservationField::Start, ReservationField::End],
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.start, later.start);
+        assert_eq!(updated.end, later.end);
+        assert_eq!(updated.note, rsvp.note);
+        assert_eq!(updated.version, 2);
+        ensure_resource(&manager, "ocean-view-room-778").await;
+        let mut moved = updated.clone();
+        moved.resource_id = "ocean-view-room-778".into();
+        let moved = manager
+            .update(moved, vec![ReservationField::ResourceId])
+            .await
+            .unwrap();
+        assert_eq!(moved.resource_id, "ocean-view-room-778");
+        assert_eq!(manager.get(rsvp.id.clone()).await.unwrap(), moved);
+        // the old version is stale
+        let err = manager
+            .update(updated, vec![ReservationField::Note])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            VersionMismatch {
+                expected: 2,
+                found: 3
+            }
+        );
+        let mut unknown = moved.clone();
+        unknown.resource_id = "unknown-room".into();
+        let err = manager
+            .update(unknown, vec![ReservationField::ResourceId])
+            .await
+            .unwrap_err();
+        assert_eq!(err, ResourceNotFound("unknown-room".into()));
+        let mut invalid = moved.clone();
+        invalid.end = invalid.start.clone();
+        let err = manager
+            .update(invalid, vec![ReservationField::End])
+            .await
+            .unwrap_err();
+        assert_eq!(err, InvalidTime);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn update_conflict_should_keep_the_original() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let (blocking, _) = make_reservation(
+            migrated_pool.clone(),
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-29T15:00:00-0700",
+            "2023-12-30T12:00:00-0700",
+            "",
+        )
+        .await;
+        let mut longer = rsvp.clone();
+        longer.end = Some("2023-12-29T18:00:00-0700".parse::<Timestamp>().unwrap());
+        let err = manager
+            .update(longer, vec![ReservationField::End])
+            .await
+            .unwrap_err();
+        match err {
+            ConflictingReservation(ReservationConflictInfo::Parsed(conflict)) => {
+                assert_eq!(conflict.blocking, vec![blocking]);
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+        assert_eq!(manager.get(rsvp.id.clone()).await.unwrap(), rsvp);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn update_should_reject_rescheduling_a_cancelled_reservation() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let cancelled = manager
+            .cancel(rsvp.id.clone(), "".into(), "".into())
+            .await
+            .unwrap();
+        let mut later = cancelled.clone();
+        later.start = Some("2023-12-26T15:00:00-0700".parse::<Timestamp>().unwrap());
+        later.end = Some("2023-12-29T12:00:00-0700".parse::<Timestamp>().unwrap());
+        let err = manager
+            .update(later, vec![ReservationField::Start, ReservationField::End])
+            .await
+            .unwrap_err();
+        assert_eq!(err, InactiveReservation(ReservationStatus::Cancelled));
+        assert_eq!(manager.get(rsvp.id.clone()).await.unwrap(), cancelled);
+        // the note could still be changed
+        let mut noted = cancelled.clone();
+        noted.note = "refunded".into();
+        let noted = manager
+            .update(noted, vec![ReservationField::Note])
+            .await
+            .unwrap();
+        assert_eq!(noted.note, "refunded");
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn get_reservation_should_work() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let rsvp_got = manager.get(rsvp.id.clone()).await.unwrap();
+        assert_eq!(rsvp_got, rsvp);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn get_reservation_should_reject_invalid_id() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let err = manager.get("not-a-uuid".into()).await.unwrap_err();
+        assert_eq!(err, InvalidReservationId("not-a-uuid".into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn delete_reservation_should_work() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        manager.delete(rsvp.id.clone()).await.unwrap();
+        let err = manager.get(rsvp.id).await.unwrap_err();
+        assert_eq!(err, NotFound);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn cancel_reservation_should_keep_it_and_free_the_slot() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let cancelled = manager
+            .cancel(
+                rsvp.id.clone(),
+                DUMMY_USER_ID_LEON.into(),
+                "change of plan".into(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(cancelled.status, ReservationStatus::Cancelled as i32);
+        assert!(cancelled.cancelled_at.is_some());
+        assert_eq!(cancelled.cancelled_by, DUMMY_USER_ID_LEON);
+        assert_eq!(cancelled.cancel_reason, "change of plan");
+        assert_eq!(manager.get(rsvp.id.clone()).await.unwrap(), cancelled);
+        // to cancel again should keep the original cancellation
+        let again = manager
+            .cancel(rsvp.id, DUMMY_USER_ID_ALICE.into(), "oops".into())
+            .await
+            .unwrap();
+        assert_eq!(again, cancelled);
+        // the slot could be reserved again
+        let (rsvp, _manager) = make_reservation(
+            migrated_pool.clone(),
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-26T15:00:00-0700",
+            "2023-12-30T12:00:00-0700",
+            "hello",
+        )
+        .await;
+        assert_eq!(rsvp.status, ReservationStatus::Pending as i32);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn query_should_filter_cancelled_reservations() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let query = ReservationQueryBuilder::default()
+            .user_id(DUMMY_USER_ID_LEON.to_owned())
+            .start("2023-12-24T15:00:00-0700".parse::<Timestamp>().unwrap())
+            .end("2023-12-30T12:00:00-0700".parse::<Timestamp>().unwrap())
+            .status(abi::ReservationStatus::Cancelled as i32)
+            .build()
+            .unwrap();
+        assert!(manager
+            .query(query.clone())
+            .await
+            .unwrap()
+            .reservations
+            .is_empty());
+        let cancelled = manager
+            .cancel(rsvp.id, DUMMY_USER_ID_LEON.into(), "change of plan".into())
+            .await
+            .unwrap();
+        let rsvps = manager.query(query).await.unwrap().reservations;
+        assert_eq!(rsvps, vec![cancelled]);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_conflict_should_ignore_cancelled_reservations() {
+        let (rsvp1, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let (rsvp2, _manager) = make_reservation(
+            migrated_pool.clone(),
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-28T12:00:00-0700",
+            "2023-12-29T12:00:00-0700",
+            "hello",
+        )
+        .await;
+        manager
+            .cancel(rsvp1.id, DUMMY_USER_ID_LEON.into(), String::new())
+            .await
+            .unwrap();
+        let rsvp3 = Reservation::new_pending(
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-24T15:00:00-0700".parse().unwrap(),
+            "2023-12-30T12:00:00-0700".parse().unwrap(),
+            "hello",
+        );
+        match manager.reserve(rsvp3).await.unwrap_err() {
+            ConflictingReservation(ReservationConflictInfo::Parsed(conflict)) => {
+                assert_eq!(conflict.blocking, vec![rsvp2]);
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn listen_should_receive_changes() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let mut stream = manager.listen(None).await.unwrap();
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let rsvp = manager.change_status(rsvp.id).await.unwrap();
+        manager.delete(rsvp.id.clone()).await.unwrap();
+
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.op, ReservationUpdateType::Create as i32);
+        assert_eq!(change.reservation.unwrap().id, rsvp.id);
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.op, ReservationUpdateType::Update as i32);
+        assert_eq!(change.reservation.unwrap().id, rsvp.id);
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.op, ReservationUpdateType::Delete as i32);
+        let deleted = change.reservation.unwrap();
+        assert_eq!(deleted.id, rsvp.id);
+        assert!(deleted.user_id.is_empty());
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn listen_should_replay_changes_after_cursor() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let rsvp = manager.change_status(rsvp.id).await.unwrap();
+
+        let mut stream = manager.listen(Some(0)).await.unwrap();
+        let created = next_change(&mut stream).await;
+        assert_eq!(created.op, ReservationUpdateType::Create as i32);
+        let updated = next_change(&mut stream).await;
+        assert_eq!(updated.op, ReservationUpdateType::Update as i32);
+        assert_eq!(updated.reservation, Some(rsvp.clone()));
+        assert!(updated.change_id > created.change_id);
+
+        // to resume after the first change, then to switch to live changes
+        let mut stream = manager.listen(Some(created.change_id)).await.unwrap();
+        assert_eq!(next_change(&mut stream).await, updated);
+        manager.delete(rsvp.id).await.unwrap();
+        let deleted = next_change(&mut stream).await;
+        assert_eq!(deleted.op, ReservationUpdateType::Delete as i32);
+        assert!(deleted.change_id > updated.change_id);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn listen_should_replay_changes_of_deleted_reservations() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let (kept, _) = make_reservation(
+            migrated_pool.clone(),
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2024-01-02T15:00:00-0700",
+            "2024-01-03T12:00:00-0700",
+            "",
+        )
+        .await;
+        manager.delete(rsvp.id.clone()).await.unwrap();
+
+        let mut stream = manager.listen(Some(0)).await.unwrap();
+        // the reservation is gone, only its id is left in its changes
+        let created = next_change(&mut stream).await;
+        assert_eq!(created.op, ReservationUpdateType::Create as i32);
+        assert_eq!(
+            created.reservation,
+            Some(Reservation {
+                id: rsvp.id.clone(),
+                ..Default::default()
+            })
+        );
+        let created = next_change(&mut stream).await;
+        assert_eq!(created.op, ReservationUpdateType::Create as i32);
+        assert_eq!(created.reservation, Some(kept));
+        let deleted = next_change(&mut stream).await;
+        assert_eq!(deleted.op, ReservationUpdateType::Delete as i32);
+        assert_eq!(deleted.reservation.unwrap().id, rsvp.id);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn listen_should_receive_changes_committed_out_of_order() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        ensure_resource(&manager, DUMMY_ROOM_NAME).await;
+        ensure_resource(&manager, "projector-1").await;
+        let rsvp = |rid: &str| {
+            prepare(Reservation::new_pending(
+                DUMMY_USER_ID_LEON,
+                rid,
+                "2023-12-25T15:00:00-0700".parse().unwrap(),
+                "2023-12-28T12:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .unwrap()
+        };
+        let mut stream = manager.listen(None).await.unwrap();
+        // the first one takes the smaller change id, but it is committed last
+        let mut first = migrated_pool.begin().await.unwrap();
+        let early = insert_reservation(&mut first, &rsvp(DUMMY_ROOM_NAME))
+            .await
+            .unwrap()
+            .unwrap();
+        let mut second = migrated_pool.begin().await.unwrap();
+        let late = insert_reservation(&mut second, &rsvp("projector-1"))
+            .await
+            .unwrap()
+            .unwrap();
+        second.commit().await.unwrap();
+        let seen = next_change(&mut stream).await;
+        assert_eq!(seen.reservation.unwrap().id, late.id);
+        first.commit().await.unwrap();
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.reservation.as_ref().unwrap().id, early.id);
+        assert!(change.change_id > seen.change_id);
+
+        // the one committed last is still replayed after the cursor of the other
+        let mut stream = manager.listen(Some(seen.change_id)).await.unwrap();
+        assert_eq!(next_change(&mut stream).await, change);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn listen_should_receive_note_and_schedule_updates() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let mut stream = manager.listen(None).await.unwrap();
+        let noted = manager
+            .update_note(rsvp.id.clone(), "late check-in".into(), rsvp.version)
+            .await
+            .unwrap();
+        let mut later = noted.clone();
+        later.start = Some("2023-12-26T15:00:00-0700".parse::<Timestamp>().unwrap());
+        later.end = Some("2023-12-29T12:00:00-0700".parse::<Timestamp>().unwrap());
+        let rescheduled = manager
+            .update(later, vec![ReservationField::Start, ReservationField::End])
+            .await
+            .unwrap();
+        manager.delete(rsvp.id).await.unwrap();
+
+        // one change each, the delete right after the two updates
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.op, ReservationUpdateType::Update as i32);
+        assert_eq!(change.reservation.unwrap().id, noted.id);
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.op, ReservationUpdateType::Update as i32);
+        assert_eq!(change.reservation.unwrap().id, rescheduled.id);
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.op, ReservationUpdateType::Delete as i32);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn prune_changes_should_remove_old_changes() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        manager.change_status(rsvp.id).await.unwrap();
+        sqlx::query("UPDATE rsvp.reservation_changes SET changed_at = now() - interval '2 days' WHERE op = 'create'")
+            .execute(&migrated_pool)
+            .await
+            .unwrap();
+        let pruned = manager
+            .prune_changes(Duration::from_secs(24 * 60 * 60))
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        let mut stream = manager.listen(Some(0)).await.unwrap();
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.op, ReservationUpdateType::Update as i32);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn expire_holds_should_cancel_lapsed_pending_reservations() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        ensure_resource(&manager, DUMMY_ROOM_NAME).await;
+        let lapsed = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "",
+        )
+        .with_hold_expires_at("2022-12-01T12:00:00-0700".parse().unwrap());
+        let lapsed = manager.reserve(lapsed).await.unwrap();
+        assert!(lapsed.hold_expires_at.is_some());
+        let held = Reservation::new_pending(
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-29T15:00:00-0700".parse().unwrap(),
+            "2023-12-30T12:00:00-0700".parse().unwrap(),
+            "",
+        )
+        .with_hold_expires_at("2099-12-01T12:00:00-0700".parse().unwrap());
+        let held = manager.reserve(held).await.unwrap();
+        let mut stream = manager.listen(None).await.unwrap();
+
+        assert_eq!(manager.expire_holds().await.unwrap(), 1);
+        let expired = manager.get(lapsed.id.clone()).await.unwrap();
+        assert_eq!(expired.status, ReservationStatus::Cancelled as i32);
+        assert_eq!(expired.cancel_reason, HOLD_EXPIRED_REASON);
+        assert!(expired.cancelled_at.is_some());
+        let change = next_change(&mut stream).await;
+        assert_eq!(change.op, ReservationUpdateType::Update as i32);
+        assert_eq!(change.reservation, Some(expired));
+        assert_eq!(manager.get(held.id.clone()).await.unwrap(), held);
+        // to run again should do nothing
+        assert_eq!(manager.expire_holds().await.unwrap(), 0);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_should_reject_unknown_or_archived_resources() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let rsvp = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "",
+        );
+        let err = manager.reserve(rsvp.clone()).await.unwrap_err();
+        assert_eq!(err, ResourceNotFound(DUMMY_ROOM_NAME.into()));
+        ensure_resource(&manager, DUMMY_ROOM_NAME).await;
+        manager
+            .archive_resource(DUMMY_ROOM_NAME.into())
+            .await
+            .unwrap();
+        let err = manager.reserve(rsvp).await.unwrap_err();
+        assert_eq!(err, ResourceNotFound(DUMMY_ROOM_NAME.into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_should_allow_overlapping_reservations_within_capacity() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager
+            .create_resource(Resource::new(DUMMY_ROOM_NAME, "", 2))
+            .await
+            .unwrap();
+        let reserve = |uid: &str, start: &str, end: &str| {
+            manager.reserve(Reservation::new_pending(
+                uid,
+                DUMMY_ROOM_NAME,
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "",
+            ))
+        };
+        let rsvp1 = reserve(
+            DUMMY_USER_ID_LEON,
+            "2023-12-25T15:00:00-0700",
+            "2023-12-28T12:00:00-0700",
+        )
+        .await
+        .unwrap();
+        let rsvp2 = reserve(
+            DUMMY_USER_ID_ALICE,
+            "2023-12-27T15:00:00-0700",
+            "2023-12-29T12:00:00-0700",
+        )
+        .await
+        .unwrap();
+        // overlapping with rsvp2 only
+        reserve(
+            DUMMY_USER_ID_LEON,
+            "2023-12-28T12:00:00-0700",
+            "2023-12-30T12:00:00-0700",
+        )
+        .await
+        .unwrap();
+        let err = reserve(
+            DUMMY_USER_ID_ALICE,
+            "2023-12-26T15:00:00-0700",
+            "2023-12-27T18:00:00-0700",
+        )
+        .await
+        .unwrap_err();
+        match err {
+            ConflictingReservation(ReservationConflictInfo::Parsed(conflict)) => {
+                assert_eq!(conflict.blocking, vec![rsvp1, rsvp2]);
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn concurrent_reserve_should_not_exceed_capacity() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager
+            .create_resource(Resource::new(DUMMY_ROOM_NAME, "", 3))
+            .await
+            .unwrap();
+        let tasks: Vec<_> = (0..10)
+            .map(|i| {
+                let manager = manager.clone();
+                tokio::spawn(async move {
+                    let rsvp = Reservation::new_pending(
+                        format!("user-{}", i),
+                        DUMMY_ROOM_NAME,
+                        "2023-12-25T15:00:00-0700".parse().unwrap(),
+                        "2023-12-28T12:00:00-0700".parse().unwrap(),
+                        "",
+                    );
+                    manager.reserve(rsvp).await
+                })
+            })
+            .collect();
+        let mut reserved = 0;
+        for task in tasks {
+            match task.await.unwrap() {
+                Ok(_) => reserved += 1,
+                Err(ConflictingReservation(_)) => {}
+                Err(e) => panic!("unexpected error: {:?}", e),
+            }
+        }
+        assert_eq!(reserved, 3);
+    }
+    #[test]
+    fn saturating_should_skip_reservations_below_capacity() {
+        let rsvp = |start: &str, end: &str| {
+            Reservation::new_pending(
+                DUMMY_USER_ID_LEON,
+                DUMMY_ROOM_NAME,
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "",
+            )
+        };
+        let a = rsvp("2023-12-25T00:00:00+0000", "2023-12-27T00:00:00+0000");
+        let b = rsvp("2023-12-26T00:00:00+0000", "2023-12-28T00:00:00+0000");
+        let c = rsvp("2023-12-29T00:00:00+0000", "2023-12-30T00:00:00+0000");
+        let window = ReservationWindow {
+            rid: DUMMY_ROOM_NAME.into(),
+            start: "2023-12-24T00:00:00Z".parse().unwrap(),
+            end: "2023-12-31T00:00:00Z".parse().unwrap(),
+        };
+        let all = vec![a.clone(), b.clone(), c.clone()];
+        assert_eq!(saturating(all.clone(), &window, 2), vec![a, b]);
+        assert_eq!(saturating(all.clone(), &window, 1), all);
+        assert!(saturating(all, &window, 3).is_empty());
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn find_free_slots_should_skip_reserved_periods() {
+        let (rsvp, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let (cancelled, _manager) = make_reservation(
+            migrated_pool.clone(),
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-29T12:00:00-0700",
+            "2023-12-30T12:00:00-0700",
+            "",
+        )
+        .await;
+        manager
+            .cancel(cancelled.id, DUMMY_USER_ID_ALICE.into(), String::new())
+            .await
+            .unwrap();
+        ensure_resource(&manager, "garden-view-room-101").await;
+        let query = AvailabilityQuery::new(
+            vec![DUMMY_ROOM_NAME.into(), "garden-view-room-101".into()],
+            "2023-12-24T12:00:00-0700".parse().unwrap(),
+            "2023-12-31T12:00:00-0700".parse().unwrap(),
+            chrono::Duration::hours(2),
+        );
+        let slots = manager.find_free_slots(query.clone()).await.unwrap();
+        assert_eq!(
+            slots,
+            vec![
+                FreeSlot {
+                    resource_id: DUMMY_ROOM_NAME.into(),
+                    start: query.start.clone(),
+                    end: rsvp.start,
+                },
+                FreeSlot {
+                    resource_id: DUMMY_ROOM_NAME.into(),
+                    start: rsvp.end,
+                    end: query.end.clone(),
+                },
+                FreeSlot {
+                    resource_id: "garden-view-room-101".into(),
+                    start: query.start.clone(),
+                    end: query.end.clone(),
+                },
+            ]
+        );
+        let mut query = query;
+        query.resource_ids.push("no-such-room".into());
+        let err = manager.find_free_slots(query).await.unwrap_err();
+        assert_eq!(err, ResourceNotFound("no-such-room".into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_series_should_create_all_occurrences() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        ensure_resource(&manager, DUMMY_ROOM_NAME).await;
+        let rsvp = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2035-01-01T09:00:00+0000".parse().unwrap(),
+            "2035-01-01T10:00:00+0000".parse().unwrap(),
+            "weekly sync",
+        )
+        .with_recurrence("FREQ=WEEKLY;BYDAY=MO;COUNT=3");
+        let occurrences = manager.reserve_series(rsvp).await.unwrap();
+        assert_eq!(occurrences.len(), 3);
+        let series_id = occurrences[0].series_id.clone();
+        assert!(!series_id.is_empty());
+        assert!(occurrences.iter().all(|o| o.series_id == series_id));
+        assert_eq!(
+            occurrences[2].start,
+            Some("2035-01-15T09:00:00Z".parse::<Timestamp>().unwrap())
+        );
+        assert_eq!(
+            manager.get_series(series_id.clone()).await.unwrap(),
+            occurrences
+        );
+
+        let updated = manager
+            .update_series_note(series_id.clone(), "moved online".into())
+            .await
+            .unwrap();
+        assert!(updated.iter().all(|o| o.note == "moved online"));
+        // the occurrences are not over yet, so they are all cancelled
+        let cancelled = manager
+            .cancel_series(
+                series_id,
+                DUMMY_USER_ID_LEON.into(),
+                "team disbanded".into(),
+            )
+            .await
+            .unwrap();
+        assert!(cancelled
+            .iter()
+            .all(|o| o.status == ReservationStatus::Cancelled as i32
+                && o.cancel_reason == "team disbanded"));
+        let err = manager
+            .get_series(Uuid::nil().to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(err, NotFound);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_series_should_create_nothing_on_conflict() {
+        let (blocking, manager) = make_reservation(
+            migrated_pool.clone(),
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2024-01-01T09:30:00+0000",
+            "2024-01-01T11:00:00+0000",
+            "",
+        )
+        .await;
+        let rsvp = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2023-12-25T09:00:00+0000".parse().unwrap(),
+            "2023-12-25T10:00:00+0000".parse().unwrap(),
+            "",
+        )
+        .with_recurrence("FREQ=WEEKLY;COUNT=3");
+        match manager.reserve(rsvp).await.unwrap_err() {
+            ConflictingReservation(ReservationConflictInfo::Parsed(conflict)) => {
+                assert_eq!(
+                    conflict.new.start,
+                    "2024-01-01T09:00:00Z".parse::<DateTime<Utc>>().unwrap()
+                );
+                assert_eq!(conflict.blocking, vec![blocking]);
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+        let count: i64 = sqlx::query_scalar("SELECT count(*) FROM rsvp.reservations")
+            .fetch_one(&migrated_pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        let rsvp = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2023-12-25T09:00:00+0000".parse().unwrap(),
+            "2023-12-25T10:00:00+0000".parse().unwrap(),
+            "",
+        )
+        .with_recurrence("FREQ=WEEKLY");
+        let err = manager.reserve(rsvp).await.unwrap_err();
+        assert_eq!(err, InvalidRecurrence("FREQ=WEEKLY".into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_batch_should_make_all_reservations() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let rsvps: Vec<_> = [DUMMY_ROOM_NAME, "projector-1", "parking-spot-42"]
+            .into_iter()
+            .map(|rid| {
+                Reservation::new_pending(
+                    DUMMY_USER_ID_LEON,
+                    rid,
+                    "2023-12-25T15:00:00-0700".parse().unwrap(),
+                    "2023-12-25T18:00:00-0700".parse().unwrap(),
+                    "conference",
+                )
+            })
+            .collect();
+        for rsvp in &rsvps {
+            ensure_resource(&manager, &rsvp.resource_id).await;
+        }
+        let created = manager.reserve_batch(rsvps).await.unwrap();
+        assert_eq!(created.len(), 3);
+        for rsvp in created {
+            assert_eq!(manager.get(rsvp.id.clone()).await.unwrap(), rsvp);
+        }
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_batch_should_report_every_conflict() {
+        let (blocking, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        ensure_resource(&manager, "projector-1").await;
+        let rsvp = |rid: &str| {
+            Reservation::new_pending(
+                DUMMY_USER_ID_ALICE,
+                rid,
+                "2023-12-26T15:00:00-0700".parse().unwrap(),
+                "2023-12-27T12:00:00-0700".parse().unwrap(),
+                "",
+            )
+        };
+        // the third one conflicts with the second one in the same batch
+        let rsvps = vec![
+            rsvp(DUMMY_ROOM_NAME),
+            rsvp("projector-1"),
+            rsvp("projector-1"),
+        ];
+        match manager.reserve_batch(rsvps).await.unwrap_err() {
+            ConflictingBatch(conflicts) => {
+                let indexes: Vec<_> = conflicts.iter().map(|c| c.index).collect();
+                assert_eq!(indexes, vec![0, 2]);
+                match &conflicts[0].info {
+                    ReservationConflictInfo::Parsed(conflict) => {
+                        assert_eq!(conflict.blocking, vec![blocking]);
+                    }
+                    info => panic!("unexpected conflict: {:?}", info),
+                }
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+        // nothing is made
+        let count: i64 = sqlx::query_scalar("SELECT count(*) FROM rsvp.reservations")
+            .fetch_one(&migrated_pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_batch_should_report_conflicts_within_the_batch() {
+        let (blocking, manager) = make_reservation_for_leon(migrated_pool.clone()).await;
+        ensure_resource(&manager, "projector-1").await;
+        let rsvp = |rid: &str| {
+            Reservation::new_pending(
+                DUMMY_USER_ID_ALICE,
+                rid,
+                "2023-12-26T15:00:00-0700".parse().unwrap(),
+                "2023-12-27T12:00:00-0700".parse().unwrap(),
+                "",
+            )
+        };
+        let rsvps = vec![
+            rsvp("projector-1"),
+            rsvp(DUMMY_ROOM_NAME),
+            rsvp("projector-1"),
+        ];
+        match manager.reserve_batch(rsvps).await.unwrap_err() {
+            ConflictingBatch(conflicts) => {
+                let indexes: Vec<_> = conflicts.iter().map(|c| c.index).collect();
+                assert_eq!(indexes, vec![1, 2]);
+                // the existing reservation blocks the second one
+                assert!(conflicts[0].blocking_indexes.is_empty());
+                match &conflicts[0].info {
+                    ReservationConflictInfo::Parsed(conflict) => {
+                        assert_eq!(conflict.blocking, vec![blocking]);
+                    }
+                    info => panic!("unexpected conflict: {:?}", info),
+                }
+                // the first one in the batch blocks the third one
+                assert_eq!(conflicts[1].blocking_indexes, vec![0]);
+                match &conflicts[1].info {
+                    ReservationConflictInfo::Parsed(conflict) => {
+                        assert!(conflict.blocking.is_empty());
+                    }
+                    info => panic!("unexpected conflict: {:?}", info),
+                }
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+    async fn ensure_resource(manager: &ReservationManager, rid: &str) {
+        match manager.create_resource(Resource::new(rid, rid, 1)).await {
+            Ok(_) | Err(ResourceAlreadyExists(_)) => {}
+            Err(e) => panic!("failed to create the resource: {:?}", e),
+        }
+    }
+    async fn next_change(stream: &mut ReservationChangeStream) -> ListenResponse {
+        tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("change should arrive in time")
+            .unwrap()
+            .unwrap()
+    }
     async fn make_reservation_for_leon(pool: PgPool) -> (Reservation, ReservationManager) {
         make_reservation(
             pool,
@@ -217,6 +2065,7 @@ mod tests {
         note: &str,
     ) -> (Reservation, ReservationManager) {
         let manager = ReservationManager::new(pool.clone());
+        ensure_resource(&manager, rid).await;
         let rsvp =
             Reservation::new_pending(uid, rid, start.parse().unwrap(), end.parse().unwrap(), note);
         let res = manager.reserve(rsvp).await;
@@ -0,0 +1,1634 @@
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use abi::{
+    convert_to_timestamp, convert_to_utc_time, find_free_slots, max_overlap, validate_range,
+    AvailabilityQuery, ChangeId, Error, FreeSlot, ListenResponse, PaginationConfig, QueryCursor,
+    QueryMatchMode, Reservation, ReservationConflict, ReservationConflictInfo, ReservationField,
+    ReservationId, ReservationPage, ReservationQuery, ReservationStatus, ReservationUpdateType,
+    ReservationWindow, Resource, ResourceId, UserId, Validator,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::types::Uuid;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    idempotency::{check_claim, validate_key},
+    manager::{
+        batch_conflict, parse_id, parse_series_id, prepare, saturating, str_to_option,
+        CHANGE_BUFFER_SIZE, HOLD_EXPIRED_REASON, QUERY_BUFFER_SIZE,
+    },
+    IdempotencyClaim, IdempotencyStore, ReservationChangeStream, ReservationQueryStream,
+    ResourceRegistry, Rsvp, IDEMPOTENCY_CLAIM_LEASE,
+};
+
+/// the reservations and resources kept in memory, for the tests and the demos without a database.
+/// the reservations are checked the same as by `ReservationManager`,
+/// but nothing survives the process.
+/// no job runs on it, the holds expire and the changes and keys are pruned only when
+/// `expire_holds`, `prune_changes` and `prune_idempotency_keys` are called.
+#[derive(Debug, Clone)]
+pub struct MemoryReservationManager {
+    state: Arc<Mutex<State>>,
+    /// the id of the last change, to wake up the listeners
+    last_change: Arc<watch::Sender<ChangeId>>,
+    pagination: PaginationConfig,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    resources: BTreeMap<ResourceId, Resource>,
+    reservations: HashMap<Uuid, Reservation>,
+    /// the change with id `n` is at `n - 1 - pruned`
+    changes: Vec<RecordedChange>,
+    /// the number of the changes pruned from the front
+    pruned: usize,
+    /// the claimed keys by key and operation
+    responses: HashMap<(String, String), SavedRequest>,
+}
+
+#[derive(Debug)]
+struct RecordedChange {
+    change: ListenResponse,
+    changed_at: DateTime<Utc>,
+}
+
+/// the request which claimed an idempotency key
+#[derive(Debug)]
+struct SavedRequest {
+    hash: Vec<u8>,
+    /// `None` until the request is done
+    response: Option<Vec<u8>>,
+    claimed_at: DateTime<Utc>,
+}
+
+impl SavedRequest {
+    fn claimed(request_hash: &[u8]) -> Self {
+        Self {
+            hash: request_hash.to_vec(),
+            response: None,
+            claimed_at: Utc::now(),
+        }
+    }
+    /// a claim without a response lapses after the lease, the same as on postgres
+    fn lapsed(&self, now: DateTime<Utc>) -> bool {
+        self.response.is_none()
+            && matches!((now - self.claimed_at).to_std(), Ok(held) if held > IDEMPOTENCY_CLAIM_LEASE)
+    }
+}
+
+#[async_trait]
+impl Rsvp for MemoryReservationManager {
+    async fn reserve(&self, rsvp: Reservation) -> Result<Reservation, Error> {
+        if rsvp.is_recurring() {
+            let mut occurrences = self.reserve_series(rsvp).await?;
+            return Ok(occurrences.remove(0));
+        }
+        let rsvp = prepare(rsvp)?;
+        let mut state = self.lock();
+        let rsvp = state.insert(rsvp)?;
+        self.notify(&state);
+        Ok(rsvp)
+    }
+    async fn reserve_batch(&self, rsvps: Vec<Reservation>) -> Result<Vec<Reservation>, Error> {
+        let mut rsvps = rsvps
+            .into_iter()
+            .map(|rsvp| match rsvp.is_recurring() {
+                true => Err(Error::InvalidRecurrence(rsvp.recurrence)),
+                false => prepare(rsvp),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut state = self.lock();
+        let savepoint = state.changes.len();
+        let mut conflicts = Vec::new();
+        for (index, rsvp) in rsvps.iter_mut().enumerate() {
+            match state.insert(rsvp.clone()) {
+                Ok(created) => *rsvp = created,
+                Err(Error::ConflictingReservation(info)) => conflicts.push((index, info)),
+                Err(e) => {
+                    state.rollback_to(savepoint);
+                    return Err(e);
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            self.notify(&state);
+            return Ok(rsvps);
+        }
+        // the blocking reservations are looked up before the rollback,
+        // so the earlier ones in the batch are found too.
+        let conflicts = conflicts
+            .into_iter()
+            .map(|(index, info)| batch_conflict(index, state.with_blocking(info, None), &rsvps))
+            .collect();
+        state.rollback_to(savepoint);
+        Err(Error::ConflictingBatch(conflicts))
+    }
+    async fn reserve_series(&self, rsvp: Reservation) -> Result<Vec<Reservation>, Error> {
+        if !rsvp.is_recurring() {
+            return Err(Error::InvalidRecurrence(rsvp.recurrence));
+        }
+        let mut rsvp = prepare(rsvp)?;
+        rsvp.series_id = Uuid::new_v4().to_string();
+        let occurrences = rsvp.occurrences()?;
+        if occurrences.is_empty() {
+            return Err(Error::InvalidRecurrence(rsvp.recurrence));
+        }
+        // all the occurrences are created or none
+        let mut state = self.lock();
+        let savepoint = state.changes.len();
+        let mut created = Vec::with_capacity(occurrences.len());
+        for occurrence in occurrences {
+            match state.insert(occurrence) {
+                Ok(occurrence) => created.push(occurrence),
+                Err(e) => {
+                    state.rollback_to(savepoint);
+                    return Err(match e {
+                        Error::ConflictingReservation(info) => {
+                            Error::ConflictingReservation(state.with_blocking(info, None))
+                        }
+                        e => e,
+                    });
+                }
+            }
+        }
+        self.notify(&state);
+        Ok(created)
+    }
+    async fn get_series(&self, series_id: String) -> Result<Vec<Reservation>, Error> {
+        let series_id = parse_series_id(&series_id)?;
+        self.lock().series(series_id)
+    }
+    async fn cancel_series(
+        &self,
+        series_id: String,
+        cancelled_by: UserId,
+        reason: String,
+    ) -> Result<Vec<Reservation>, Error> {
+        let uuid = parse_series_id(&series_id)?;
+        let now = Utc::now();
+        let mut state = self.lock();
+        // only the occurrences which are not over yet
+        let ids: Vec<Uuid> = state
+            .reservations
+            .iter()
+            .filter(|(_, rsvp)| in_series(rsvp, uuid))
+            .filter(|(_, rsvp)| rsvp.get_span().1 > now)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            state.update_status(
+                id,
+                ReservationStatus::Cancelled,
+                str_to_option(&cancelled_by),
+                str_to_option(&reason),
+            );
+        }
+        self.notify(&state);
+        state.series(uuid)
+    }
+    async fn update_series_note(
+        &self,
+        series_id: String,
+        note: String,
+    ) -> Result<Vec<Reservation>, Error> {
+        let uuid = parse_series_id(&series_id)?;
+        let mut state = self.lock();
+        let occurrences: Vec<Reservation> = state
+            .reservations
+            .values()
+            .filter(|rsvp| in_series(rsvp, uuid))
+            .cloned()
+            .collect();
+        for mut occurrence in occurrences {
+            occurrence.note = note.clone();
+            state.save(occurrence);
+        }
+        self.notify(&state);
+        state.series(uuid)
+    }
+    async fn change_status(&self, id: ReservationId) -> Result<Reservation, Error> {
+        self.transition(id, ReservationStatus::Confirmed).await
+    }
+    async fn transition(
+        &self,
+        id: ReservationId,
+        to: ReservationStatus,
+    ) -> Result<Reservation, Error> {
+        let uuid = parse_id(&id)?;
+        let mut state = self.lock();
+        match state.update_status(uuid, to, None, None) {
+            Some(rsvp) => {
+                self.notify(&state);
+                Ok(rsvp)
+            }
+            None => Err(state.invalid_transition(uuid, to)),
+        }
+    }
+    async fn update_note(
+        &self,
+        id: ReservationId,
+        note: String,
+        version: i64,
+    ) -> Result<Reservation, Error> {
+        let uuid = parse_id(&id)?;
+        let mut state = self.lock();
+        let mut rsvp = state.get(uuid)?.clone();
+        if rsvp.version != version {
+            return Err(Error::VersionMismatch {
+                expected: version,
+                found: rsvp.version,
+            });
+        }
+        rsvp.note = note;
+        let rsvp = state.save(rsvp);
+        self.notify(&state);
+        Ok(rsvp)
+    }
+    async fn update(
+        &self,
+        rsvp: Reservation,
+        fields: Vec<ReservationField>,
+    ) -> Result<Reservation, Error> {
+        let id = parse_id(&rsvp.id)?;
+        if fields.is_empty() {
+            return Err(Error::InvalidUpdateMask(String::new()));
+        }
+        let mut state = self.lock();
+        let current = state.get(id)?.clone();
+        if current.version != rsvp.version {
+            return Err(Error::VersionMismatch {
+                expected: rsvp.version,
+                found: current.version,
+            });
+        }
+        let status = ReservationStatus::from_i32(current.status).unwrap_or_default();
+        ReservationField::check_update(&fields, status)?;
+        let mut updated = current.clone();
+        fields
+            .iter()
+            .for_each(|field| field.apply(&rsvp, &mut updated));
+        updated.validate()?;
+        validate_range(updated.start.as_ref(), updated.end.as_ref())?;
+        if updated.resource_id != current.resource_id {
+            state.active_resource(&updated.resource_id)?;
+        }
+        // the same as the capacity trigger, only a new time slot is checked
+        if updated.resource_id != current.resource_id || updated.get_span() != current.get_span() {
+            state.check_capacity(&updated, Some(id))?;
+        }
+        let updated = state.save(updated);
+        self.notify(&state);
+        Ok(updated)
+    }
+    async fn cancel(
+        &self,
+        id: ReservationId,
+        cancelled_by: UserId,
+        reason: String,
+    ) -> Result<Reservation, Error> {
+        let uuid = parse_id(&id)?;
+        let to = ReservationStatus::Cancelled;
+        let mut state = self.lock();
+        let rsvp = state.update_status(
+            uuid,
+            to,
+            str_to_option(&cancelled_by),
+            str_to_option(&reason),
+        );
+        match rsvp {
+            Some(rsvp) => {
+                self.notify(&state);
+                Ok(rsvp)
+            }
+            None => match state.get(uuid)? {
+                // already cancelled, the original cancellation is kept
+                rsvp if rsvp.status == to as i32 => Ok(rsvp.clone()),
+                _ => Err(state.invalid_transition(uuid, to)),
+            },
+        }
+    }
+    async fn delete(&self, id: ReservationId) -> Result<Reservation, Error> {
+        let uuid = parse_id(&id)?;
+        let mut state = self.lock();
+        let rsvp = state.reservations.remove(&uuid).ok_or(Error::NotFound)?;
+        // the same as the changes of the database, a deleted one is only known by its id
+        state.record(
+            ReservationUpdateType::Delete,
+            Reservation {
+                id,
+                ..Default::default()
+            },
+        );
+        self.notify(&state);
+        Ok(rsvp)
+    }
+    async fn get(&self, id: ReservationId) -> Result<Reservation, Error> {
+        let uuid = parse_id(&id)?;
+        self.lock().get(uuid).cloned()
+    }
+    async fn query(&self, query: ReservationQuery) -> Result<ReservationPage, Error> {
+        let page_size = query.get_page_size(&self.pagination)? as usize;
+        let cursor = query.get_cursor()?;
+        validate_range(query.start.as_ref(), query.end.as_ref())?;
+        let state = self.lock();
+        let matching = state.select(&query);
+        let total = matching.len() as i64;
+        // the page number only counts without a cursor
+        let offset = match cursor {
+            None if query.page > 1 => (query.page as usize - 1) * page_size,
+            _ => 0,
+        };
+        let mut reservations: Vec<Reservation> = matching
+            .into_iter()
+            .filter(|(key, _)| after_cursor(&query, cursor.as_ref(), key))
+            .skip(offset)
+            // one more to tell if there is a next page
+            .take(page_size + 1)
+            .map(|(_, rsvp)| rsvp.clone())
+            .collect();
+        let has_more = reservations.len() > page_size;
+        reservations.truncate(page_size);
+        let next_cursor = match reservations.last() {
+            Some(last) if has_more => QueryCursor::after(last)?.to_string(),
+            _ => String::new(),
+        };
+        Ok(ReservationPage {
+            reservations,
+            next_cursor,
+            total,
+            has_more,
+        })
+    }
+    async fn query_stream(&self, query: ReservationQuery) -> Result<ReservationQueryStream, Error> {
+        let cursor = query.get_cursor()?;
+        validate_range(query.start.as_ref(), query.end.as_ref())?;
+        let (tx, rx) = mpsc::channel(QUERY_BUFFER_SIZE);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut cursor = cursor;
+            loop {
+                // page by page after the cursor, the lock is not held while the subscriber reads
+                let page: Vec<(QueryCursor, Reservation)> = manager
+                    .lock()
+                    .select(&query)
+                    .into_iter()
+                    .filter(|(key, _)| after_cursor(&query, cursor.as_ref(), key))
+                    .take(QUERY_BUFFER_SIZE)
+                    .map(|(key, rsvp)| (key, rsvp.clone()))
+                    .collect();
+                let last_page = page.len() < QUERY_BUFFER_SIZE;
+                for (key, rsvp) in page {
+                    cursor = Some(key);
+                    if tx.send(Ok(rsvp)).await.is_err() {
+                        return;
+                    }
+                }
+                if last_page {
+                    return;
+                }
+            }
+        });
+        Ok(Box::pin(ReceiverStream::new(rx)))
+    }
+    async fn find_free_slots(&self, query: AvailabilityQuery) -> Result<Vec<FreeSlot>, Error> {
+        query.validate()?;
+        let (start, end) = match (query.start.as_ref(), query.end.as_ref()) {
+            (Some(start), Some(end)) => (convert_to_utc_time(start), convert_to_utc_time(end)),
+            _ => return Err(Error::InvalidTime),
+        };
+        let state = self.lock();
+        let mut slots = Vec::new();
+        for rid in &query.resource_ids {
+            let capacity = state.active_resource(rid)?.effective_capacity();
+            let window = ReservationWindow {
+                rid: rid.clone(),
+                start,
+                end,
+            };
+            let spans: Vec<_> = state
+                .overlapping(&window, None)
+                .iter()
+                .map(Reservation::get_span)
+                .collect();
+            slots.extend(find_free_slots(
+                rid,
+                start,
+                end,
+                &spans,
+                capacity as usize,
+                query.min_duration(),
+            ));
+        }
+        Ok(slots)
+    }
+    async fn listen(&self, after: Option<ChangeId>) -> Result<ReservationChangeStream, Error> {
+        // to subscribe before reading the changes, so no change falls in between
+        let mut changed = self.last_change.subscribe();
+        let last_id = match after {
+            Some(id) => id,
+            // without a cursor, changes made before the subscription are not replayed
+            None => self.lock().last_change_id(),
+        };
+        let (tx, rx) = mpsc::channel(CHANGE_BUFFER_SIZE);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut last_id = last_id;
+            loop {
+                let changes = manager.lock().changes_after(last_id);
+                for change in changes {
+                    last_id = change.change_id;
+                    if tx.send(Ok(change)).await.is_err() {
+                        return;
+                    }
+                }
+                tokio::select! {
+                    notification = changed.changed() => {
+                        if notification.is_err() {
+                            return;
+                        }
+                    }
+                    // the subscriber has gone away
+                    _ = tx.closed() => return,
+                }
+            }
+        });
+        Ok(Box::pin(ReceiverStream::new(rx)))
+    }
+}
+
+#[async_trait]
+impl ResourceRegistry for MemoryReservationManager {
+    async fn create_resource(&self, resource: Resource) -> Result<Resource, Error> {
+        resource.validate()?;
+        let mut state = self.lock();
+        if state.resources.contains_key(&resource.id) {
+            return Err(Error::ResourceAlreadyExists(resource.id));
+        }
+        let resource = Resource {
+            capacity: resource.effective_capacity(),
+            archived_at: None,
+            ..resource
+        };
+        state
+            .resources
+            .insert(resource.id.clone(), resource.clone());
+        Ok(resource)
+    }
+    async fn update_resource(&self, resource: Resource) -> Result<Resource, Error> {
+        resource.validate()?;
+        let mut state = self.lock();
+        let current = state
+            .resources
+            .get(&resource.id)
+            .ok_or_else(|| Error::ResourceNotFound(resource.id.clone()))?;
+        let capacity = resource.updated_capacity(current.capacity);
+        if capacity < current.capacity {
+            // the reservations which are over already do not count
+            let window = ReservationWindow {
+                rid: resource.id.clone(),
+                start: Utc::now(),
+                end: DateTime::<Utc>::MAX_UTC,
+            };
+            let spans: Vec<_> = state
+                .overlapping(&window, None)
+                .iter()
+                .map(Reservation::get_span)
+                .collect();
+            let overlapping = max_overlap(&spans);
+            if overlapping > capacity as usize {
+                return Err(Error::CapacityBelowReservations {
+                    capacity,
+                    overlapping,
+                });
+            }
+        }
+        let current = state
+            .resources
+            .get_mut(&resource.id)
+            .ok_or(Error::ResourceNotFound(resource.id))?;
+        current.name = resource.name;
+        current.description = resource.description;
+        current.capacity = capacity;
+        Ok(current.clone())
+    }
+    async fn archive_resource(&self, id: ResourceId) -> Result<Resource, Error> {
+        let mut state = self.lock();
+        let resource = state
+            .resources
+            .get_mut(&id)
+            .ok_or(Error::ResourceNotFound(id))?;
+        // to archive again should keep the original time
+        if resource.archived_at.is_none() {
+            resource.archived_at = Some(convert_to_timestamp(Utc::now()));
+        }
+        Ok(resource.clone())
+    }
+    async fn get_resource(&self, id: ResourceId) -> Result<Resource, Error> {
+        let state = self.lock();
+        state
+            .resources
+            .get(&id)
+            .cloned()
+            .ok_or(Error::ResourceNotFound(id))
+    }
+    async fn list_resources(&self, include_archived: bool) -> Result<Vec<Resource>, Error> {
+        let state = self.lock();
+        let resources = state
+            .resources
+            .values()
+            .filter(|resource| include_archived || !resource.is_archived())
+            .cloned()
+            .collect();
+        Ok(resources)
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for MemoryReservationManager {
+    async fn claim(
+        &self,
+        key: &str,
+        operation: &str,
+        request_hash: &[u8],
+    ) -> Result<IdempotencyClaim, Error> {
+        validate_key(key)?;
+        let mut state = self.lock();
+        match state
+            .responses
+            .entry((key.to_owned(), operation.to_owned()))
+        {
+            Entry::Occupied(mut entry) => {
+                let saved = entry.get();
+                if !saved.lapsed(Utc::now()) {
+                    return check_claim(
+                        key,
+                        request_hash,
+                        Some(&saved.hash),
+                        saved.response.clone(),
+                    );
+                }
+                entry.insert(SavedRequest::claimed(request_hash));
+                Ok(IdempotencyClaim::Claimed)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(SavedRequest::claimed(request_hash));
+                Ok(IdempotencyClaim::Claimed)
+            }
+        }
+    }
+    async fn save_response(
+        &self,
+        key: &str,
+        operation: &str,
+        response: Vec<u8>,
+    ) -> Result<(), Error> {
+        validate_key(key)?;
+        let mut state = self.lock();
+        if let Some(saved) = state
+            .responses
+            .get_mut(&(key.to_owned(), operation.to_owned()))
+        {
+            saved.response = Some(response);
+        }
+        Ok(())
+    }
+    async fn release(&self, key: &str, operation: &str) -> Result<(), Error> {
+        validate_key(key)?;
+        let mut state = self.lock();
+        let key = (key.to_owned(), operation.to_owned());
+        if state
+            .responses
+            .get(&key)
+            .is_some_and(|saved| saved.response.is_none())
+        {
+            state.responses.remove(&key);
+        }
+        Ok(())
+    }
+}
+
+impl Default for MemoryReservationManager {
+    fn default() -> Self {
+        let (last_change, _) = watch::channel(0);
+        Self {
+            state: Default::default(),
+            last_change: Arc::new(last_change),
+            pagination: PaginationConfig::default(),
+        }
+    }
+}
+
+impl MemoryReservationManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// to limit the page size of the queries other than the default limits
+    pub fn with_pagination(mut self, pagination: PaginationConfig) -> Self {
+        self.pagination = pagination;
+        self
+    }
+    /// to cancel the pending reservations whose hold has expired, the same as
+    /// `ReservationManager::expire_holds`, returns the number of the expired reservations.
+    pub async fn expire_holds(&self) -> Result<u64, Error> {
+        let now = Utc::now();
+        let mut state = self.lock();
+        let mut lapsed: Vec<Uuid> = state
+            .reservations
+            .iter()
+            .filter(|(_, rsvp)| rsvp.status == ReservationStatus::Pending as i32)
+            .filter(|(_, rsvp)| {
+                rsvp.hold_expires_at
+                    .as_ref()
+                    .is_some_and(|expires_at| convert_to_utc_time(expires_at) <= now)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        lapsed.sort();
+        let mut expired = 0;
+        for id in lapsed {
+            let reason = Some(HOLD_EXPIRED_REASON);
+            if state
+                .update_status(id, ReservationStatus::Cancelled, None, reason)
+                .is_some()
+            {
+                expired += 1;
+            }
+        }
+        self.notify(&state);
+        Ok(expired)
+    }
+    /// to prune the changes recorded more than `retention` ago,
+    /// returns the number of the pruned changes.
+    pub async fn prune_changes(&self, retention: Duration) -> Result<u64, Error> {
+        let before = before(retention);
+        let mut state = self.lock();
+        // the changes are recorded in order, so the old ones are at the front
+        let old = state
+            .changes
+            .iter()
+            .take_while(|recorded| recorded.changed_at < before)
+            .count();
+        state.changes.drain(..old);
+        state.pruned += old;
+        Ok(old as u64)
+    }
+    /// to remove the keys claimed more than `retention` ago, the number of removed keys is returned.
+    pub async fn prune_idempotency_keys(&self, retention: Duration) -> Result<u64, Error> {
+        let before = before(retention);
+        let mut state = self.lock();
+        let count = state.responses.len();
+        state
+            .responses
+            .retain(|_, saved| saved.claimed_at >= before);
+        Ok((count - state.responses.len()) as u64)
+    }
+    /// the state is never left half updated, so a poisoned lock is still usable
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    /// to wake up the listeners after the changes are made
+    fn notify(&self, state: &State) {
+        self.last_change.send_replace(state.last_change_id());
+    }
+}
+
+impl State {
+    fn get(&self, id: Uuid) -> Result<&Reservation, Error> {
+        self.reservations.get(&id).ok_or(Error::NotFound)
+    }
+    /// the occurrences of a series ordered by start time
+    fn series(&self, series_id: Uuid) -> Result<Vec<Reservation>, Error> {
+        let mut occurrences: Vec<Reservation> = self
+            .reservations
+            .values()
+            .filter(|rsvp| in_series(rsvp, series_id))
+            .cloned()
+            .collect();
+        if occurrences.is_empty() {
+            return Err(Error::NotFound);
+        }
+        occurrences.sort_by_key(Reservation::get_span);
+        Ok(occurrences)
+    }
+    /// the reservations matching the query, in the order of the query
+    fn select(&self, query: &ReservationQuery) -> Vec<(QueryCursor, &Reservation)> {
+        let (start, end) = match (query.start.as_ref(), query.end.as_ref()) {
+            (Some(start), Some(end)) => (convert_to_utc_time(start), convert_to_utc_time(end)),
+            _ => (DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC),
+        };
+        let status =
+            ReservationStatus::from_i32(query.status).unwrap_or(ReservationStatus::Pending);
+        let user_id = str_to_option(&query.user_id);
+        let resource_id = str_to_option(&query.resource_id);
+        let mode = query.get_match_mode();
+        let mut matching: Vec<(QueryCursor, &Reservation)> = self
+            .reservations
+            .iter()
+            .filter(|(_, rsvp)| rsvp.status == status as i32)
+            .filter(|(_, rsvp)| user_id.map_or(true, |user_id| rsvp.user_id == user_id))
+            .filter(|(_, rsvp)| {
+                resource_id.map_or(true, |resource_id| rsvp.resource_id == resource_id)
+            })
+            .filter(|(_, rsvp)| {
+                // the time window and the timespans are both half open
+                let (rsvp_start, rsvp_end) = rsvp.get_span();
+                match mode {
+                    QueryMatchMode::Contained => start <= rsvp_start && rsvp_end <= end,
+                    QueryMatchMode::Overlapping => rsvp_start < end && start < rsvp_end,
+                    QueryMatchMode::StartingWithin => start <= rsvp_start && rsvp_start < end,
+                }
+            })
+            .map(|(id, rsvp)| {
+                let key = QueryCursor {
+                    start: rsvp.get_span().0,
+                    id: *id,
+                };
+                (key, rsvp)
+            })
+            .collect();
+        matching.sort_by_key(|(key, _)| (key.start, key.id));
+        if query.desc {
+            matching.reverse();
+        }
+        matching
+    }
+    /// the resource which could be reserved, i.e. known and not archived
+    fn active_resource(&self, id: &str) -> Result<&Resource, Error> {
+        self.resources
+            .get(id)
+            .filter(|resource| !resource.is_archived())
+            .ok_or_else(|| Error::ResourceNotFound(id.to_owned()))
+    }
+    /// to insert the prepared reservation as a new one on a resource which could be reserved
+    fn insert(&mut self, mut rsvp: Reservation) -> Result<Reservation, Error> {
+        self.active_resource(&rsvp.resource_id)?;
+        self.check_capacity(&rsvp, None)?;
+        let id = Uuid::new_v4();
+        rsvp.id = id.to_string();
+        rsvp.version = 1;
+        rsvp.updated_at = Some(convert_to_timestamp(Utc::now()));
+        rsvp.cancelled_at = None;
+        rsvp.cancelled_by = String::new();
+        rsvp.cancel_reason = String::new();
+        self.reservations.insert(id, rsvp.clone());
+        self.record(ReservationUpdateType::Create, rsvp.clone());
+        Ok(rsvp)
+    }
+    /// to store the updated reservation with the next version
+    fn save(&mut self, mut rsvp: Reservation) -> Reservation {
+        rsvp.version += 1;
+        rsvp.updated_at = Some(convert_to_timestamp(Utc::now()));
+        if let Ok(id) = Uuid::parse_str(&rsvp.id) {
+            self.reservations.insert(id, rsvp.clone());
+        }
+        self.record(ReservationUpdateType::Update, rsvp.clone());
+        rsvp
+    }
+    /// to move the reservation to `to` if its current status allows it,
+    /// `None` is returned if it is not found or not allowed.
+    fn update_status(
+        &mut self,
+        id: Uuid,
+        to: ReservationStatus,
+        cancelled_by: Option<&str>,
+        reason: Option<&str>,
+    ) -> Option<Reservation> {
+        let mut rsvp = self.reservations.get(&id)?.clone();
+        let from = ReservationStatus::from_i32(rsvp.status).unwrap_or_default();
+        rsvp.status = from.transition_to(to).ok()? as i32;
+        if to == ReservationStatus::Cancelled {
+            rsvp.cancelled_at = Some(convert_to_timestamp(Utc::now()));
+        }
+        if let Some(cancelled_by) = cancelled_by {
+            rsvp.cancelled_by = cancelled_by.to_owned();
+        }
+        if let Some(reason) = reason {
+            rsvp.cancel_reason = reason.to_owned();
+        }
+        Some(self.save(rsvp))
+    }
+    /// the error for a failed transition to `to`, `Error::NotFound` if the reservation is gone
+    fn invalid_transition(&self, id: Uuid, to: ReservationStatus) -> Error {
+        match self.get(id) {
+            Ok(rsvp) => Error::InvalidStatusTransition {
+                from: ReservationStatus::from_i32(rsvp.status).unwrap_or_default(),
+                to,
+            },
+            Err(e) => e,
+        }
+    }
+    fn last_change_id(&self) -> ChangeId {
+        (self.pruned + self.changes.len()) as ChangeId
+    }
+    fn record(&mut self, op: ReservationUpdateType, rsvp: Reservation) {
+        let change_id = self.last_change_id() + 1;
+        self.changes.push(RecordedChange {
+            change: ListenResponse {
+                op: op as i32,
+                reservation: Some(rsvp),
+                change_id,
+            },
+            changed_at: Utc::now(),
+        });
+    }
+    fn changes_after(&self, last_id: ChangeId) -> Vec<ListenResponse> {
+        let start = (last_id.max(0) as usize).saturating_sub(self.pruned);
+        self.changes
+            .get(start..)
+            .unwrap_or_default()
+            .iter()
+            .map(|recorded| recorded.change.clone())
+            .collect()
+    }
+    /// to drop the reservations created after the `savepoint`, the length of the changes then.
+    /// only the creations could be rolled back.
+    fn rollback_to(&mut self, savepoint: usize) {
+        for recorded in self.changes.drain(savepoint..) {
+            if let Some(id) = recorded
+                .change
+                .reservation
+                .and_then(|rsvp| Uuid::parse_str(&rsvp.id).ok())
+            {
+                self.reservations.remove(&id);
+            }
+        }
+    }
+    /// the blocking reservations on the resource overlapping the `window`, ordered by start time.
+    /// `except` is the reservation being updated, which never blocks itself.
+    fn overlapping(&self, window: &ReservationWindow, except: Option<Uuid>) -> Vec<Reservation> {
+        let mut overlapping: Vec<Reservation> = self
+            .reservations
+            .iter()
+            .filter(|(id, _)| Some(**id) != except)
+            .map(|(_, rsvp)| rsvp)
+            .filter(|rsvp| rsvp.resource_id == window.rid)
+            .filter(|rsvp| {
+                ReservationStatus::from_i32(rsvp.status)
+                    .unwrap_or_default()
+                    .is_blocking()
+            })
+            .filter(|rsvp| {
+                let (start, end) = rsvp.get_span();
+                start < window.end && window.start < end
+            })
+            .cloned()
+            .collect();
+        overlapping.sort_by_key(Reservation::get_span);
+        overlapping
+    }
+    fn capacity(&self, rid: &str) -> usize {
+        self.resources
+            .get(rid)
+            .map_or(1, |resource| resource.effective_capacity() as usize)
+    }
+    /// to check the reservation the same as the capacity trigger,
+    /// it conflicts if the capacity is used up at some point of its time slot.
+    fn check_capacity(&self, rsvp: &Reservation, except: Option<Uuid>) -> Result<(), Error> {
+        let status = ReservationStatus::from_i32(rsvp.status).unwrap_or_default();
+        if !status.is_blocking() {
+            return Ok(());
+        }
+        let (start, end) = rsvp.get_span();
+        let window = ReservationWindow {
+            rid: rsvp.resource_id.clone(),
+            start,
+            end,
+        };
+        let overlapping = self.overlapping(&window, except);
+        let capacity = self.capacity(&window.rid);
+        // the most overlapping reservations are found at the start of the window,
+        // or at the start of one of them, the earliest one is reported.
+        let points = std::iter::once(start).chain(
+            overlapping
+                .iter()
+                .map(|rsvp| rsvp.get_span().0)
+                .filter(|point| *point > start),
+        );
+        for point in points {
+            let covering: Vec<&Reservation> = overlapping
+                .iter()
+                .filter(|rsvp| {
+                    let (start, end) = rsvp.get_span();
+                    start <= point && point < end
+                })
+                .collect();
+            if covering.len() >= capacity {
+                let (old_start, old_end) = covering[0].get_span();
+                let conflict = ReservationConflict {
+                    old: ReservationWindow {
+                        rid: window.rid.clone(),
+                        start: old_start,
+                        end: old_end,
+                    },
+                    blocking: saturating(overlapping, &window, capacity),
+                    new: window,
+                };
+                return Err(Error::ConflictingReservation(
+                    ReservationConflictInfo::Parsed(conflict),
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// to look up the reservations saturating the capacity again, e.g. after a rollback
+    fn with_blocking(
+        &self,
+        info: ReservationConflictInfo,
+        except: Option<Uuid>,
+    ) -> ReservationConflictInfo {
+        match info {
+            ReservationConflictInfo::Parsed(mut conflict) => {
+                let overlapping = self.overlapping(&conflict.new, except);
+                let capacity = self.capacity(&conflict.new.rid);
+                conflict.blocking = saturating(overlapping, &conflict.new, capacity);
+                ReservationConflictInfo::Parsed(conflict)
+            }
+            info => info,
+        }
+    }
+}
+
+/// the time `retention` ago
+fn before(retention: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(retention)
+        .ok()
+        .and_then(|retention| Utc::now().checked_sub_signed(retention))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// the series id is compared as a uuid, as postgres does, so its case or hyphens do not matter
+fn in_series(rsvp: &Reservation, series_id: Uuid) -> bool {
+    Uuid::parse_str(&rsvp.series_id).ok() == Some(series_id)
+}
+
+/// keyset pagination, the next page starts right after the cursor
+fn after_cursor(query: &ReservationQuery, cursor: Option<&QueryCursor>, key: &QueryCursor) -> bool {
+    match cursor {
+        Some(cursor) if query.desc => (key.start, key.id) < (cursor.start, cursor.id),
+        Some(cursor) => (key.start, key.id) > (cursor.start, cursor.id),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi::{Error::*, ReservationQueryBuilder};
+    use futures::StreamExt;
+    const DUMMY_USER_ID_LEON: &str = "dummy_user_id_leon";
+    const DUMMY_USER_ID_ALICE: &str = "dummy_user_id_alice";
+    const DUMMY_ROOM_NAME: &str = "ocean-view-room-777";
+
+    #[tokio::test]
+    async fn reserve_should_reject_conflicting_reservations() {
+        let (rsvp1, manager) = make_reservation_for_leon(1).await;
+        let rsvp2 = Reservation::new_pending(
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-26T15:00:00-0700".parse().unwrap(),
+            "2023-12-30T12:00:00-0700".parse().unwrap(),
+            "hello",
+        );
+        let err = manager.reserve(rsvp2).await.unwrap_err();
+        let info = ReservationConflictInfo::Parsed(ReservationConflict {
+            new: ReservationWindow {
+                rid: DUMMY_ROOM_NAME.to_owned(),
+                start: "2023-12-26T15:00:00-0700".parse().unwrap(),
+                end: "2023-12-30T12:00:00-0700".parse().unwrap(),
+            },
+            old: ReservationWindow {
+                rid: DUMMY_ROOM_NAME.to_owned(),
+                start: "2023-12-25T15:00:00-0700".parse().unwrap(),
+                end: "2023-12-28T12:00:00-0700".parse().unwrap(),
+            },
+            blocking: vec![rsvp1],
+        });
+        assert_eq!(err, ConflictingReservation(info));
+    }
+
+    #[tokio::test]
+    async fn reserve_should_fill_the_capacity_then_conflict() {
+        let (rsvp1, manager) = make_reservation_for_leon(2).await;
+        let alice = |start: &str, end: &str| {
+            Reservation::new_pending(
+                DUMMY_USER_ID_ALICE,
+                DUMMY_ROOM_NAME,
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "",
+            )
+        };
+        let rsvp2 = manager
+            .reserve(alice(
+                "2023-12-27T15:00:00-0700",
+                "2023-12-29T12:00:00-0700",
+            ))
+            .await
+            .unwrap();
+        let err = manager
+            .reserve(alice(
+                "2023-12-26T15:00:00-0700",
+                "2023-12-28T00:00:00-0700",
+            ))
+            .await
+            .unwrap_err();
+        match err {
+            ConflictingReservation(ReservationConflictInfo::Parsed(conflict)) => {
+                assert_eq!(conflict.blocking, vec![rsvp1.clone(), rsvp2]);
+            }
+            e => panic!("expected a conflict, got {:?}", e),
+        }
+        // a cancelled reservation no longer blocks the time slot
+        manager
+            .cancel(rsvp1.id, DUMMY_USER_ID_LEON.into(), "".into())
+            .await
+            .unwrap();
+        manager
+            .reserve(alice(
+                "2023-12-26T15:00:00-0700",
+                "2023-12-28T00:00:00-0700",
+            ))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reserve_batch_should_make_all_or_none() {
+        let (rsvp1, manager) = make_reservation_for_leon(1).await;
+        let alice = |start: &str, end: &str| {
+            Reservation::new_pending(
+                DUMMY_USER_ID_ALICE,
+                DUMMY_ROOM_NAME,
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "",
+            )
+        };
+        let batch = vec![
+            alice("2024-01-02T15:00:00-0700", "2024-01-03T12:00:00-0700"),
+            alice("2023-12-27T15:00:00-0700", "2023-12-29T12:00:00-0700"),
+        ];
+        let err = manager.reserve_batch(batch).await.unwrap_err();
+        match err {
+            ConflictingBatch(conflicts) => {
+                assert_eq!(conflicts.len(), 1);
+                assert_eq!(conflicts[0].index, 1);
+            }
+            e => panic!("expected a batch conflict, got {:?}", e),
+        }
+        let page = manager.query(query_all()).await.unwrap();
+        assert_eq!(page.reservations, vec![rsvp1]);
+
+        let batch = vec![
+            alice("2024-01-02T15:00:00-0700", "2024-01-03T12:00:00-0700"),
+            alice("2024-01-03T15:00:00-0700", "2024-01-04T12:00:00-0700"),
+        ];
+        let created = manager.reserve_batch(batch).await.unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(manager.query(query_all()).await.unwrap().total, 3);
+    }
+
+    #[tokio::test]
+    async fn reserve_batch_should_report_conflicts_within_the_batch() {
+        let (rsvp1, manager) = make_reservation_for_leon(1).await;
+        let alice = |start: &str, end: &str| {
+            Reservation::new_pending(
+                DUMMY_USER_ID_ALICE,
+                DUMMY_ROOM_NAME,
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "",
+            )
+        };
+        let batch = vec![
+            alice("2024-01-02T15:00:00-0700", "2024-01-03T12:00:00-0700"),
+            alice("2023-12-27T15:00:00-0700", "2023-12-29T12:00:00-0700"),
+            alice("2024-01-03T10:00:00-0700", "2024-01-04T12:00:00-0700"),
+        ];
+        let err = manager.reserve_batch(batch).await.unwrap_err();
+        match err {
+            ConflictingBatch(conflicts) => {
+                let indexes: Vec<_> = conflicts.iter().map(|c| c.index).collect();
+                assert_eq!(indexes, vec![1, 2]);
+                assert!(conflicts[0].blocking_indexes.is_empty());
+                match &conflicts[0].info {
+                    ReservationConflictInfo::Parsed(conflict) => {
+                        assert_eq!(conflict.blocking, vec![rsvp1.clone()]);
+                    }
+                    info => panic!("unexpected conflict: {:?}", info),
+                }
+                assert_eq!(conflicts[1].blocking_indexes, vec![0]);
+                match &conflicts[1].info {
+                    ReservationConflictInfo::Parsed(conflict) => {
+                        assert!(conflict.blocking.is_empty());
+                    }
+                    info => panic!("unexpected conflict: {:?}", info),
+                }
+            }
+            e => panic!("expected a batch conflict, got {:?}", e),
+        }
+        let page = manager.query(query_all()).await.unwrap();
+        assert_eq!(page.reservations, vec![rsvp1]);
+    }
+
+    #[tokio::test]
+    async fn update_should_check_the_version_and_the_new_time_slot() {
+        let (rsvp1, manager) = make_reservation_for_leon(1).await;
+        let rsvp2 = manager
+            .reserve(Reservation::new_pending(
+                DUMMY_USER_ID_ALICE,
+                DUMMY_ROOM_NAME,
+                "2024-01-02T15:00:00-0700".parse().unwrap(),
+                "2024-01-03T12:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+        let err = manager
+            .update_note(rsvp1.id.clone(), "late".into(), rsvp1.version + 1)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            VersionMismatch {
+                expected: rsvp1.version + 1,
+                found: rsvp1.version,
+            }
+        );
+        let mut moved = rsvp2.clone();
+        moved.start = rsvp1.start.clone();
+        let err = manager
+            .update(moved, vec![ReservationField::Start])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ConflictingReservation(_)));
+        assert_eq!(manager.get(rsvp2.id.clone()).await.unwrap(), rsvp2);
+        // to shrink the reservation never conflicts with itself
+        let mut shrunk = rsvp1.clone();
+        shrunk.end = rsvp2.start.clone();
+        let updated = manager
+            .update(shrunk, vec![ReservationField::End])
+            .await
+            .unwrap();
+        assert_eq!(updated.version, rsvp1.version + 1);
+        // a cancelled one keeps its time slot
+        let cancelled = manager
+            .cancel(rsvp2.id.clone(), "".into(), "".into())
+            .await
+            .unwrap();
+        let mut moved = cancelled.clone();
+        moved.start = rsvp1.start.clone();
+        let err = manager
+            .update(moved, vec![ReservationField::Start])
+            .await
+            .unwrap_err();
+        assert_eq!(err, InactiveReservation(ReservationStatus::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn query_should_page_by_cursor() {
+        let manager = make_manager(1).await;
+        for day in 1..=5 {
+            manager
+                .reserve(Reservation::new_pending(
+                    DUMMY_USER_ID_LEON,
+                    DUMMY_ROOM_NAME,
+                    format!("2024-01-0{}T10:00:00-0700", day).parse().unwrap(),
+                    format!("2024-01-0{}T12:00:00-0700", day).parse().unwrap(),
+                    "",
+                ))
+                .await
+                .unwrap();
+        }
+        let mut query = query_all();
+        query.page_size = 2;
+        let mut starts = Vec::new();
+        loop {
+            let page = manager.query(query.clone()).await.unwrap();
+            assert_eq!(page.total, 5);
+            starts.extend(page.reservations.iter().map(|rsvp| rsvp.get_span().0));
+            if !page.has_more {
+                break;
+            }
+            query.cursor = page.next_cursor;
+        }
+        assert_eq!(starts.len(), 5);
+        assert!(starts.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[tokio::test]
+    async fn query_should_limit_the_page_size_by_the_config() {
+        let (_, manager) = make_reservation_for_leon(1).await;
+        let mut query = query_all();
+        query.page_size = 150;
+        let err = manager.query(query.clone()).await.unwrap_err();
+        assert_eq!(err, InvalidPageSize(150));
+        let manager = manager.with_pagination(PaginationConfig {
+            default_page_size: 20,
+            max_page_size: 200,
+        });
+        assert_eq!(manager.query(query).await.unwrap().total, 1);
+    }
+    #[tokio::test]
+    async fn listen_should_receive_the_changes() {
+        let manager = make_manager(1).await;
+        let mut changes = manager.listen(None).await.unwrap();
+        let (rsvp, _) = reserve_for_leon(&manager).await;
+        manager.delete(rsvp.id.clone()).await.unwrap();
+        let created = changes.next().await.unwrap().unwrap();
+        assert_eq!(created.op, ReservationUpdateType::Create as i32);
+        assert_eq!(created.reservation, Some(rsvp.clone()));
+        let deleted = changes.next().await.unwrap().unwrap();
+        assert_eq!(deleted.op, ReservationUpdateType::Delete as i32);
+        assert_eq!(deleted.reservation.unwrap().id, rsvp.id);
+        // to replay from the start
+        let mut replayed = manager.listen(Some(0)).await.unwrap();
+        assert_eq!(replayed.next().await.unwrap().unwrap(), created);
+    }
+
+    #[tokio::test]
+    async fn query_stream_should_read_page_by_page() {
+        let manager = make_manager(1).await;
+        let count = QUERY_BUFFER_SIZE * 3;
+        let start: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let mut last = None;
+        for hour in 0..count as i64 {
+            let start = start + chrono::Duration::hours(hour);
+            let rsvp = Reservation::new_pending(
+                DUMMY_USER_ID_LEON,
+                DUMMY_ROOM_NAME,
+                start.into(),
+                (start + chrono::Duration::minutes(30)).into(),
+                "",
+            );
+            last = Some(manager.reserve(rsvp).await.unwrap());
+        }
+        let mut stream = manager.query_stream(query_all()).await.unwrap();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.get_span().0, start);
+        // a page is read ahead no more than the buffer, the last one is read after it is deleted
+        manager.delete(last.unwrap().id).await.unwrap();
+        let rest: Vec<Reservation> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(rest.len(), count - 2);
+        assert!(rest
+            .windows(2)
+            .all(|pair| pair[0].get_span() < pair[1].get_span()));
+    }
+    #[tokio::test]
+    async fn expire_holds_should_cancel_lapsed_pending_reservations() {
+        let manager = make_manager(1).await;
+        let lapsed = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "",
+        )
+        .with_hold_expires_at("2022-12-01T12:00:00-0700".parse().unwrap());
+        let lapsed = manager.reserve(lapsed).await.unwrap();
+        let held = Reservation::new_pending(
+            DUMMY_USER_ID_ALICE,
+            DUMMY_ROOM_NAME,
+            "2023-12-29T15:00:00-0700".parse().unwrap(),
+            "2023-12-30T12:00:00-0700".parse().unwrap(),
+            "",
+        )
+        .with_hold_expires_at("2099-12-01T12:00:00-0700".parse().unwrap());
+        let held = manager.reserve(held).await.unwrap();
+        let mut changes = manager.listen(None).await.unwrap();
+
+        assert_eq!(manager.expire_holds().await.unwrap(), 1);
+        let expired = manager.get(lapsed.id.clone()).await.unwrap();
+        assert_eq!(expired.status, ReservationStatus::Cancelled as i32);
+        assert_eq!(expired.cancel_reason, HOLD_EXPIRED_REASON);
+        let change = changes.next().await.unwrap().unwrap();
+        assert_eq!(change.op, ReservationUpdateType::Update as i32);
+        assert_eq!(change.reservation, Some(expired));
+        assert_eq!(manager.get(held.id.clone()).await.unwrap(), held);
+        // the time slot is free again
+        let (rsvp, _) = reserve_for_leon(&manager).await;
+        assert_eq!(rsvp.status, ReservationStatus::Pending as i32);
+        assert_eq!(manager.expire_holds().await.unwrap(), 0);
+    }
+    #[tokio::test]
+    async fn prune_changes_should_remove_old_changes() {
+        let (rsvp, manager) = make_reservation_for_leon(1).await;
+        manager.change_status(rsvp.id.clone()).await.unwrap();
+        manager.lock().changes[0].changed_at -= chrono::Duration::days(2);
+        let pruned = manager
+            .prune_changes(Duration::from_secs(24 * 60 * 60))
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        let mut changes = manager.listen(Some(0)).await.unwrap();
+        let change = changes.next().await.unwrap().unwrap();
+        assert_eq!(change.op, ReservationUpdateType::Update as i32);
+        assert_eq!(change.change_id, 2);
+        // the ids go on after the pruned ones
+        manager.delete(rsvp.id).await.unwrap();
+        let change = changes.next().await.unwrap().unwrap();
+        assert_eq!(change.op, ReservationUpdateType::Delete as i32);
+        assert_eq!(change.change_id, 3);
+    }
+    #[tokio::test]
+    async fn prune_idempotency_keys_should_remove_old_keys() {
+        let manager = MemoryReservationManager::new();
+        for key in ["old", "new"] {
+            manager.claim(key, "reserve", b"request").await.unwrap();
+            manager.save_response(key, "reserve", vec![]).await.unwrap();
+        }
+        manager
+            .lock()
+            .responses
+            .get_mut(&("old".to_owned(), "reserve".to_owned()))
+            .unwrap()
+            .claimed_at -= chrono::Duration::days(2);
+        let pruned = manager
+            .prune_idempotency_keys(Duration::from_secs(24 * 60 * 60))
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        let claim = manager.claim("new", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Saved(vec![]));
+        let claim = manager.claim("old", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Claimed);
+    }
+
+    fn query_all() -> ReservationQuery {
+        ReservationQueryBuilder::default()
+            .resource_id(DUMMY_ROOM_NAME)
+            .start(
+                "2023-01-01T00:00:00-0700"
+                    .parse::<prost_types::Timestamp>()
+                    .unwrap(),
+            )
+            .end(
+                "2025-01-01T00:00:00-0700"
+                    .parse::<prost_types::Timestamp>()
+                    .unwrap(),
+            )
+            .status(ReservationStatus::Pending)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn update_resource_should_keep_the_capacity_of_the_reservations() {
+        let manager = make_manager(3).await;
+        for uid in [DUMMY_USER_ID_LEON, DUMMY_USER_ID_ALICE] {
+            manager
+                .reserve(Reservation::new_pending(
+                    uid,
+                    DUMMY_ROOM_NAME,
+                    "2030-12-26T15:00:00-0700".parse().unwrap(),
+                    "2030-12-27T12:00:00-0700".parse().unwrap(),
+                    "",
+                ))
+                .await
+                .unwrap();
+        }
+        let updated = manager
+            .update_resource(Resource::new(DUMMY_ROOM_NAME, "Ocean View", 0))
+            .await
+            .unwrap();
+        assert_eq!(updated.capacity, 3);
+        let err = manager
+            .update_resource(Resource::new(DUMMY_ROOM_NAME, "", 1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CapacityBelowReservations { .. }));
+    }
+
+    #[tokio::test]
+    async fn claim_should_take_over_the_lapsed_claim() {
+        let manager = MemoryReservationManager::new();
+        let lapse = || {
+            for saved in manager.lock().responses.values_mut() {
+                saved.claimed_at = Utc::now() - chrono::Duration::minutes(2);
+            }
+        };
+        manager.claim("key", "reserve", b"request").await.unwrap();
+        lapse();
+        let claim = manager.claim("key", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Claimed);
+        let err = manager
+            .claim("key", "reserve", b"request")
+            .await
+            .unwrap_err();
+        assert_eq!(err, IdempotencyKeyInUse("key".into()));
+        manager
+            .save_response("key", "reserve", b"first".to_vec())
+            .await
+            .unwrap();
+        lapse();
+        let claim = manager.claim("key", "reserve", b"request").await.unwrap();
+        assert_eq!(claim, IdempotencyClaim::Saved(b"first".to_vec()));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn series_should_behave_the_same_as_postgres() {
+        let expected =
+            series_transcript(crate::ReservationManager::new(migrated_pool.clone())).await;
+        let actual = series_transcript(MemoryReservationManager::new()).await;
+        // step by step, so the first one which drifts is shown
+        for (actual, expected) in actual.iter().zip(&expected) {
+            assert_eq!(actual, expected);
+        }
+        assert_eq!(actual.len(), expected.len());
+    }
+
+    /// one step of the shared scenario, with the ids and the times set by the storage left out
+    #[derive(Debug, PartialEq)]
+    enum Step {
+        Returned(Vec<Reservation>),
+        Failed(Error),
+        Changed(i32, Option<Box<Reservation>>),
+        Expired(u64),
+    }
+
+    /// the jobs run on postgres, called by hand on both backends
+    #[async_trait]
+    trait Jobs {
+        async fn expire_holds(&self) -> Result<u64, Error>;
+    }
+
+    #[async_trait]
+    impl Jobs for crate::ReservationManager {
+        async fn expire_holds(&self) -> Result<u64, Error> {
+            crate::ReservationManager::expire_holds(self).await
+        }
+    }
+
+    #[async_trait]
+    impl Jobs for MemoryReservationManager {
+        async fn expire_holds(&self) -> Result<u64, Error> {
+            MemoryReservationManager::expire_holds(self).await
+        }
+    }
+
+    /// to run the same series scenario on a backend and record what it returns and notifies
+    async fn series_transcript(manager: impl Rsvp + ResourceRegistry + Jobs) -> Vec<Step> {
+        manager
+            .create_resource(Resource::new(DUMMY_ROOM_NAME, "Ocean View", 1))
+            .await
+            .unwrap();
+        let mut changes = manager.listen(None).await.unwrap();
+        let mut steps = Vec::new();
+        let series = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2030-01-07T09:00:00-0700".parse().unwrap(),
+            "2030-01-07T10:00:00-0700".parse().unwrap(),
+            "weekly",
+        )
+        .with_recurrence("FREQ=WEEKLY;COUNT=3");
+        let created = manager.reserve_series(series).await;
+        // the series id is a uuid however it is spelled
+        let series_id = created.as_ref().unwrap()[0].series_id.to_uppercase();
+        record(&mut steps, &mut changes, created, true).await;
+        let moved = manager
+            .update_series_note(series_id.clone(), "moved".into())
+            .await;
+        record(&mut steps, &mut changes, moved, true).await;
+        let cancelled = manager
+            .cancel_series(series_id.clone(), DUMMY_USER_ID_LEON.into(), "done".into())
+            .await;
+        record(&mut steps, &mut changes, cancelled, true).await;
+        let series = manager.get_series(series_id).await;
+        record(&mut steps, &mut changes, series, false).await;
+        let invalid = manager.get_series("not-a-series".into()).await;
+        record(&mut steps, &mut changes, invalid, false).await;
+        let unknown = manager
+            .update_series_note(Uuid::new_v4().to_string(), "none".into())
+            .await;
+        record(&mut steps, &mut changes, unknown, true).await;
+        // the cancelled occurrences no longer block
+        let reserved = manager
+            .reserve(Reservation::new_pending(
+                DUMMY_USER_ID_ALICE,
+                DUMMY_ROOM_NAME,
+                "2030-01-07T09:00:00-0700".parse().unwrap(),
+                "2030-01-07T10:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .map(|rsvp| vec![rsvp]);
+        record(&mut steps, &mut changes, reserved, true).await;
+        // a lapsed hold blocks its time slot until it expires
+        let held = manager
+            .reserve(
+                Reservation::new_pending(
+                    DUMMY_USER_ID_LEON,
+                    DUMMY_ROOM_NAME,
+                    "2030-02-04T09:00:00-0700".parse().unwrap(),
+                    "2030-02-04T10:00:00-0700".parse().unwrap(),
+                    "held",
+                )
+                .with_hold_expires_at("2020-01-01T00:00:00-0700".parse().unwrap()),
+            )
+            .await
+            .map(|rsvp| vec![rsvp]);
+        let held_id = held.as_ref().map(|rsvps| rsvps[0].id.clone()).unwrap();
+        record(&mut steps, &mut changes, held, true).await;
+        steps.push(Step::Expired(manager.expire_holds().await.unwrap()));
+        // to read the change notified by the expiry
+        let expired = manager.get(held_id).await.map(|rsvp| vec![rsvp]);
+        record(&mut steps, &mut changes, expired, true).await;
+        let reserved = manager
+            .reserve(Reservation::new_pending(
+                DUMMY_USER_ID_ALICE,
+                DUMMY_ROOM_NAME,
+                "2030-02-04T09:00:00-0700".parse().unwrap(),
+                "2030-02-04T10:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .map(|rsvp| vec![rsvp]);
+        record(&mut steps, &mut changes, reserved, true).await;
+        steps
+    }
+
+    /// to record the result of a step, then the change notified for each reservation it changed.
+    /// the changes are read right away, as postgres notifies the reservation as it is when read.
+    async fn record(
+        steps: &mut Vec<Step>,
+        changes: &mut crate::ReservationChangeStream,
+        result: Result<Vec<Reservation>, Error>,
+        mutating: bool,
+    ) {
+        let notified = match result {
+            Ok(rsvps) => {
+                // a lookup changes nothing
+                let notified = if mutating { rsvps.len() } else { 0 };
+                steps.push(Step::Returned(rsvps.into_iter().map(comparable).collect()));
+                notified
+            }
+            Err(e) => {
+                steps.push(Step::Failed(e));
+                0
+            }
+        };
+        let mut changed = Vec::with_capacity(notified);
+        for _ in 0..notified {
+            let change = tokio::time::timeout(std::time::Duration::from_secs(5), changes.next())
+                .await
+                .expect("change should arrive in time")
+                .unwrap()
+                .unwrap();
+            changed.push((change.op, change.reservation.map(comparable)));
+        }
+        // the rows changed by one statement are notified in no particular order
+        changed.sort_by_key(|(_, rsvp)| rsvp.as_ref().map(Reservation::get_span));
+        steps.extend(
+            changed
+                .into_iter()
+                .map(|(op, rsvp)| Step::Changed(op, rsvp.map(Box::new))),
+        );
+    }
+
+    /// the reservation without the ids and the times set by the storage
+    fn comparable(rsvp: Reservation) -> Reservation {
+        let set = |ts: Option<prost_types::Timestamp>| ts.map(|_| Default::default());
+        Reservation {
+            id: String::new(),
+            series_id: String::new(),
+            cancelled_at: set(rsvp.cancelled_at),
+            hold_expires_at: set(rsvp.hold_expires_at),
+            updated_at: set(rsvp.updated_at),
+            ..rsvp
+        }
+    }
+
+    async fn make_manager(capacity: i32) -> MemoryReservationManager {
+        let manager = MemoryReservationManager::new();
+        manager
+            .create_resource(Resource::new(DUMMY_ROOM_NAME, "Ocean View", capacity))
+            .await
+            .unwrap();
+        manager
+    }
+
+    async fn make_reservation_for_leon(capacity: i32) -> (Reservation, MemoryReservationManager) {
+        let manager = make_manager(capacity).await;
+        reserve_for_leon(&manager).await
+    }
+
+    async fn reserve_for_leon(
+        manager: &MemoryReservationManager,
+    ) -> (Reservation, MemoryReservationManager) {
+        let rsvp = Reservation::new_pending(
+            DUMMY_USER_ID_LEON,
+            DUMMY_ROOM_NAME,
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "I'll arrive at 3pm. Please help to upgrade to executive room if possible.",
+        );
+        (manager.reserve(rsvp).await.unwrap(), manager.clone())
+    }
+}
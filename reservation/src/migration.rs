@@ -0,0 +1,116 @@
+use abi::Error;
+use sqlx::migrate::Migrator;
+
+use crate::ReservationManager;
+
+/// the migrations in `migrations/`, embedded into the binary
+pub static MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+
+impl ReservationManager {
+    /// to apply the pending migrations, the versions of the applied ones are returned.
+    /// nothing is applied if the schema is newer than the binary.
+    pub async fn migrate(&self) -> Result<Vec<i64>, Error> {
+        let pending = self.check_schema().await?;
+        if !pending.is_empty() {
+            MIGRATOR.run(&self.pool).await?;
+        }
+        Ok(pending)
+    }
+    /// to make sure the schema is up to date before serving on it,
+    /// the pending migrations are applied if `auto_migrate`, otherwise they fail it.
+    /// the versions of the applied ones are returned.
+    pub async fn ensure_schema(&self, auto_migrate: bool) -> Result<Vec<i64>, Error> {
+        if auto_migrate {
+            return self.migrate().await;
+        }
+        let pending = self.check_schema().await?;
+        if !pending.is_empty() {
+            return Err(Error::PendingMigrations(pending));
+        }
+        Ok(pending)
+    }
+    /// to check the schema against the embedded migrations,
+    /// the versions of the pending ones are returned.
+    /// if a migration unknown to the binary has been applied, `Error::SchemaTooNew` is returned.
+    pub async fn check_schema(&self) -> Result<Vec<i64>, Error> {
+        let applied = self.applied_migrations().await?;
+        let known: Vec<i64> = MIGRATOR
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .map(|m| m.version)
+            .collect();
+        let unknown = applied.iter().filter(|v| !known.contains(v)).max();
+        if let Some(&applied) = unknown {
+            return Err(Error::SchemaTooNew {
+                applied,
+                latest: known.iter().max().copied().unwrap_or_default(),
+            });
+        }
+        Ok(known.into_iter().filter(|v| !applied.contains(v)).collect())
+    }
+    /// the versions of the applied migrations, the table is created by the first run
+    async fn applied_migrations(&self) -> Result<Vec<i64>, Error> {
+        let exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(&self.pool)
+            .await?;
+        if !exists {
+            return Ok(Vec::new());
+        }
+        let versions = sqlx::query_scalar(
+            "SELECT version FROM _sqlx_migrations WHERE success ORDER BY version",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(versions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi::Error::*;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn check_schema_should_pass_when_migrated() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        assert_eq!(manager.check_schema().await.unwrap(), Vec::<i64>::new());
+        assert_eq!(manager.migrate().await.unwrap(), Vec::<i64>::new());
+    }
+    #[sqlx_database_tester::test(pool(variable = "pool", skip_migrations))]
+    async fn migrate_should_apply_all_on_empty_database() {
+        let manager = ReservationManager::new(pool.clone());
+        let pending = manager.check_schema().await.unwrap();
+        assert!(!pending.is_empty());
+        assert_eq!(manager.migrate().await.unwrap(), pending);
+        assert_eq!(manager.check_schema().await.unwrap(), Vec::<i64>::new());
+    }
+    #[sqlx_database_tester::test(pool(variable = "pool", skip_migrations))]
+    async fn ensure_schema_should_refuse_pending_migrations() {
+        let manager = ReservationManager::new(pool.clone());
+        let pending = manager.check_schema().await.unwrap();
+        let err = manager.ensure_schema(false).await.unwrap_err();
+        assert_eq!(err, PendingMigrations(pending.clone()));
+        assert_eq!(manager.ensure_schema(true).await.unwrap(), pending);
+        assert_eq!(
+            manager.ensure_schema(false).await.unwrap(),
+            Vec::<i64>::new()
+        );
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn check_schema_should_refuse_newer_schema() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        sqlx::query("INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (99991231000000, 'from the future', true, '\\x00', 0)")
+            .execute(&migrated_pool)
+            .await
+            .unwrap();
+        let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap();
+        let err = manager.migrate().await.unwrap_err();
+        assert_eq!(
+            err,
+            SchemaTooNew {
+                applied: 99991231000000,
+                latest,
+            }
+        );
+    }
+}
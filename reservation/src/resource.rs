@@ -0,0 +1,213 @@
+use abi::{max_overlap, Error, Resource, ResourceId, Validator};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::{manager::blocking_statuses, ReservationManager, ResourceRegistry};
+
+#[async_trait]
+impl ResourceRegistry for ReservationManager {
+    async fn create_resource(&self, resource: Resource) -> Result<Resource, Error> {
+        resource.validate()?;
+        let created = sqlx::query_as(
+            "INSERT INTO rsvp.resources (id, name, description, capacity) VALUES ($1, $2, $3, $4) ON CONFLICT (id) DO NOTHING RETURNING *",
+        )
+        .bind(&resource.id)
+        .bind(&resource.name)
+        .bind(&resource.description)
+        .bind(resource.effective_capacity())
+        .fetch_optional(&self.pool)
+        .await?;
+        created.ok_or(Error::ResourceAlreadyExists(resource.id))
+    }
+    async fn update_resource(&self, resource: Resource) -> Result<Resource, Error> {
+        resource.validate()?;
+        let mut tx = self.pool.begin().await?;
+        // the resource row is locked, so no reservation is made on it meanwhile
+        let current: Option<Resource> =
+            sqlx::query_as("SELECT * FROM rsvp.resources WHERE id = $1 FOR UPDATE")
+                .bind(&resource.id)
+                .fetch_optional(&mut tx)
+                .await?;
+        let current = match current {
+            Some(current) => current,
+            None => {
+                tx.rollback().await?;
+                return Err(Error::ResourceNotFound(resource.id));
+            }
+        };
+        let capacity = resource.updated_capacity(current.capacity);
+        if capacity < current.capacity {
+            // the reservations which are over already do not count
+            let spans: Vec<(DateTime<Utc>, DateTime<Utc>)> = sqlx::query_as(
+                "SELECT lower(timespan), upper(timespan) FROM rsvp.reservations WHERE resource_id = $1 AND status::text = ANY($2) AND upper(timespan) > now()",
+            )
+            .bind(&resource.id)
+            .bind(blocking_statuses())
+            .fetch_all(&mut tx)
+            .await?;
+            let overlapping = max_overlap(&spans);
+            if overlapping > capacity as usize {
+                tx.rollback().await?;
+                return Err(Error::CapacityBelowReservations {
+                    capacity,
+                    overlapping,
+                });
+            }
+        }
+        let updated = sqlx::query_as(
+            "UPDATE rsvp.resources SET name = $2, description = $3, capacity = $4 WHERE id = $1 RETURNING *",
+        )
+        .bind(&resource.id)
+        .bind(&resource.name)
+        .bind(&resource.description)
+        .bind(capacity)
+        .fetch_one(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+    async fn archive_resource(&self, id: ResourceId) -> Result<Resource, Error> {
+        // to archive again should keep the original time
+        let archived = sqlx::query_as(
+            "UPDATE rsvp.resources SET archived_at = COALESCE(archived_at, now()) WHERE id = $1 RETURNING *",
+        )
+        .bind(&id)
+        .fetch_optional(&self.pool)
+        .await?;
+        archived.ok_or(Error::ResourceNotFound(id))
+    }
+    async fn get_resource(&self, id: ResourceId) -> Result<Resource, Error> {
+        let resource = sqlx::query_as("SELECT * FROM rsvp.resources WHERE id = $1")
+            .bind(&id)
+            .fetch_optional(&self.pool)
+            .await?;
+        resource.ok_or(Error::ResourceNotFound(id))
+    }
+    async fn list_resources(&self, include_archived: bool) -> Result<Vec<Resource>, Error> {
+        let resources = sqlx::query_as(
+            "SELECT * FROM rsvp.resources WHERE $1 OR archived_at IS NULL ORDER BY id",
+        )
+        .bind(include_archived)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rsvp;
+    use abi::{Error::*, Reservation};
+    use sqlx::PgPool;
+    const DUMMY_ROOM_NAME: &str = "ocean-view-room-777";
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn create_resource_should_work() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        let resource = manager
+            .create_resource(Resource::new(DUMMY_ROOM_NAME, "Ocean View", 0))
+            .await
+            .unwrap();
+        assert_eq!(resource, Resource::new(DUMMY_ROOM_NAME, "Ocean View", 1));
+        let err = manager
+            .create_resource(Resource::new(DUMMY_ROOM_NAME, "", 1))
+            .await
+            .unwrap_err();
+        assert_eq!(err, ResourceAlreadyExists(DUMMY_ROOM_NAME.into()));
+        assert_eq!(
+            manager.get_resource(DUMMY_ROOM_NAME.into()).await.unwrap(),
+            resource
+        );
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn update_and_archive_resource_should_work() {
+        let manager = make_resources(migrated_pool.clone()).await;
+        let mut resource = Resource::new(DUMMY_ROOM_NAME, "Ocean View Suite", 2);
+        resource.description = "two beds".into();
+        assert_eq!(
+            manager.update_resource(resource.clone()).await.unwrap(),
+            resource
+        );
+        let archived = manager
+            .archive_resource(DUMMY_ROOM_NAME.into())
+            .await
+            .unwrap();
+        assert!(archived.is_archived());
+        // to archive again should keep the original time
+        let again = manager
+            .archive_resource(DUMMY_ROOM_NAME.into())
+            .await
+            .unwrap();
+        assert_eq!(again, archived);
+        let err = manager
+            .archive_resource("no-such-room".into())
+            .await
+            .unwrap_err();
+        assert_eq!(err, ResourceNotFound("no-such-room".into()));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn update_resource_should_keep_the_capacity_of_the_reservations() {
+        let manager = ReservationManager::new(migrated_pool.clone());
+        manager
+            .create_resource(Resource::new(DUMMY_ROOM_NAME, "Ocean View", 5))
+            .await
+            .unwrap();
+        for user in ["leon", "alice"] {
+            manager
+                .reserve(Reservation::new_pending(
+                    user,
+                    DUMMY_ROOM_NAME,
+                    "2030-12-25T15:00:00-0700".parse().unwrap(),
+                    "2030-12-28T12:00:00-0700".parse().unwrap(),
+                    "",
+                ))
+                .await
+                .unwrap();
+        }
+        // the capacity is kept if it is not set
+        let updated = manager
+            .update_resource(Resource::new(DUMMY_ROOM_NAME, "Ocean View Suite", 0))
+            .await
+            .unwrap();
+        assert_eq!(updated.capacity, 5);
+        let err = manager
+            .update_resource(Resource::new(DUMMY_ROOM_NAME, "", 1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            CapacityBelowReservations {
+                capacity: 1,
+                overlapping: 2
+            }
+        );
+        let updated = manager
+            .update_resource(Resource::new(DUMMY_ROOM_NAME, "", 2))
+            .await
+            .unwrap();
+        assert_eq!(updated.capacity, 2);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn list_resources_should_skip_archived_ones() {
+        let manager = make_resources(migrated_pool.clone()).await;
+        manager
+            .archive_resource(DUMMY_ROOM_NAME.into())
+            .await
+            .unwrap();
+        let resources = manager.list_resources(false).await.unwrap();
+        let ids: Vec<_> = resources.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["garden-view-room-101"]);
+        let resources = manager.list_resources(true).await.unwrap();
+        assert_eq!(resources.len(), 2);
+    }
+    async fn make_resources(pool: PgPool) -> ReservationManager {
+        let manager = ReservationManager::new(pool);
+        for id in [DUMMY_ROOM_NAME, "garden-view-room-101"] {
+            manager
+                .create_resource(Resource::new(id, "", 1))
+                .await
+                .unwrap();
+        }
+        manager
+    }
+}
@@ -0,0 +1,68 @@
+use std::pin::Pin;
+
+use abi::{
+    reservation_service_server::ReservationServiceServer, Config, ListenResponse, Reservation,
+};
+use futures::Stream;
+use reservation::{
+    spawn_change_compaction, spawn_hold_reaper, spawn_idempotency_key_pruning, ReservationManager,
+    Storage,
+};
+use tonic::{transport::Server, Status};
+
+mod service;
+
+/// stream of reservations sent back to the client by the `query` rpc
+pub type ReservationStream = Pin<Box<dyn Stream<Item = Result<Reservation, Status>> + Send>>;
+/// stream of reservation changes sent back to the client by the `listen` rpc
+pub type ListenStream = Pin<Box<dyn Stream<Item = Result<ListenResponse, Status>> + Send>>;
+
+/// gRPC frontend of the reservation system, on postgres by default.
+/// it could run on any storage, e.g. `MemoryReservationManager` for tests and demos.
+pub struct RsvpService<S = ReservationManager> {
+    manager: S,
+}
+
+impl<S: Storage> RsvpService<S> {
+    pub fn new(manager: S) -> Self {
+        Self { manager }
+    }
+}
+
+impl RsvpService {
+    pub async fn from_config(config: &Config) -> Result<Self, anyhow::Error> {
+        Ok(Self::new(ReservationManager::from_config(config).await?))
+    }
+}
+
+/// to apply the pending migrations to the database in the config,
+/// the versions of the applied ones are returned.
+pub async fn migrate(config: &Config) -> Result<Vec<i64>, anyhow::Error> {
+    let manager = ReservationManager::from_config(config).await?;
+    Ok(manager.migrate().await?)
+}
+
+/// to start the gRPC server with the given config
+pub async fn start_server(config: &Config) -> Result<(), anyhow::Error> {
+    let addr = format!("{}:{}", config.server.host, config.server.port).parse()?;
+    let svc = RsvpService::from_config(config).await?;
+    // the server never runs on a schema older or newer than it knows
+    for version in svc.manager.ensure_schema(config.db.auto_migrate).await? {
+        println!("Applied migration {}", version);
+    }
+    spawn_change_compaction(
+        svc.manager.clone(),
+        config.jobs.change_retention(),
+        config.jobs.change_compaction_interval(),
+    );
+    spawn_idempotency_key_pruning(
+        svc.manager.clone(),
+        config.jobs.idempotency_key_retention(),
+        config.jobs.idempotency_key_pruning_interval(),
+    );
+    spawn_hold_reaper(svc.manager.clone(), config.jobs.hold_reaper_interval());
+    let svc = ReservationServiceServer::new(svc);
+    println!("Listening on {}", addr);
+    Server::builder().add_service(svc).serve(addr).await?;
+    Ok(())
+}
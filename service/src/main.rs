@@ -1,3 +1,50 @@
-fn main() {
-    println!("Hello, world!");
+use std::{env, path::PathBuf};
+
+use abi::Config;
+use anyhow::{bail, Result};
+use service::{migrate, start_server};
+
+/// usage: `service [serve | migrate]`, to serve by default
+#[tokio::main]
+async fn main() -> Result<()> {
+    let filename = config_path()?;
+    let config = Config::load(filename)?;
+    match env::args().nth(1).as_deref() {
+        None | Some("serve") => start_server(&config).await,
+        Some("migrate") => {
+            let applied = migrate(&config).await?;
+            if applied.is_empty() {
+                println!("The database is up to date");
+            }
+            for version in applied {
+                println!("Applied migration {}", version);
+            }
+            Ok(())
+        }
+        Some(command) => bail!(
+            "unknown command `{}`, expected `serve` or `migrate`",
+            command
+        ),
+    }
+}
+
+/// the config file is looked up from `$RESERVATION_CONFIG`,
+/// then `reservation.yml` or `reservation.toml` in `.`, `~/.config` and `/etc`.
+/// the fields could be overridden by the `RESERVATION_*` environment variables.
+fn config_path() -> Result<PathBuf> {
+    if let Ok(filename) = env::var("RESERVATION_CONFIG") {
+        return Ok(filename.into());
+    }
+    let mut dirs = vec![PathBuf::from(".")];
+    if let Ok(home) = env::var("HOME") {
+        dirs.push(PathBuf::from(home).join(".config"));
+    }
+    dirs.push(PathBuf::from("/etc"));
+    let mut candidates = dirs
+        .into_iter()
+        .flat_map(|dir| ["reservation.yml", "reservation.toml"].map(|filename| dir.join(filename)));
+    match candidates.find(|p| p.exists()) {
+        Some(filename) => Ok(filename),
+        None => bail!("config file not found"),
+    }
 }
@@ -0,0 +1,901 @@
+use abi::{
+    reservation_service_server::ReservationService, ArchiveResourceRequest,
+    ArchiveResourceResponse, AvailabilityRequest, AvailabilityResponse, CancelRequest,
+    CancelResponse, CancelSeriesRequest, ConfirmRequest, ConfirmResponse, CreateResourceRequest,
+    CreateResourceResponse, GetRequest, GetResponse, GetSeriesRequest, ListResourcesRequest,
+    ListResourcesResponse, ListenRequest, QueryRequest, ReservationField, ReservationPage,
+    ReservationRequest, ReservationResponse, ReservationStatus, ReserveBatchRequest,
+    ReserveBatchResponse, SeriesResponse, TransitionRequest, TransitionResponse, UpdateRequest,
+    UpdateResourceRequest, UpdateResourceResponse, UpdateResponse, UpdateSeriesRequest,
+};
+use futures::{Future, TryStreamExt};
+use prost::Message;
+use reservation::{IdempotencyClaim, Storage};
+use sha2::{Digest, Sha256};
+use tonic::{async_trait, Request, Response, Status};
+
+use crate::{ListenStream, ReservationStream, RsvpService};
+
+#[async_trait]
+impl<S: Storage> ReservationService for RsvpService<S> {
+    /// make a reservation
+    async fn reserve(
+        &self,
+        request: Request<ReservationRequest>,
+    ) -> Result<Response<ReservationResponse>, Status> {
+        let request = request.into_inner();
+        let hash = request_hash(&request);
+        let rsvp = request
+            .reservation
+            .ok_or_else(|| Status::invalid_argument("missing reservation"))?;
+        let res = self
+            .idempotent(&request.idempotency_key, "reserve", hash, || async {
+                let rsvp = self.manager.reserve(rsvp).await?;
+                Ok(ReservationResponse {
+                    reservation: Some(rsvp),
+                })
+            })
+            .await?;
+        Ok(Response::new(res))
+    }
+    /// make several reservations at once, all or none
+    async fn reserve_batch(
+        &self,
+        request: Request<ReserveBatchRequest>,
+    ) -> Result<Response<ReserveBatchResponse>, Status> {
+        let rsvps = request.into_inner().reservations;
+        let reservations = self.manager.reserve_batch(rsvps).await?;
+        Ok(Response::new(ReserveBatchResponse { reservations }))
+    }
+    /// make a recurring reservation
+    async fn reserve_series(
+        &self,
+        request: Request<ReservationRequest>,
+    ) -> Result<Response<SeriesResponse>, Status> {
+        let request = request.into_inner();
+        let hash = request_hash(&request);
+        let rsvp = request
+            .reservation
+            .ok_or_else(|| Status::invalid_argument("missing reservation"))?;
+        let res = self
+            .idempotent(&request.idempotency_key, "reserve_series", hash, || async {
+                let reservations = self.manager.reserve_series(rsvp).await?;
+                Ok(SeriesResponse { reservations })
+            })
+            .await?;
+        Ok(Response::new(res))
+    }
+    /// get the occurrences of a series
+    async fn get_series(
+        &self,
+        request: Request<GetSeriesRequest>,
+    ) -> Result<Response<SeriesResponse>, Status> {
+        let reservations = self
+            .manager
+            .get_series(request.into_inner().series_id)
+            .await?;
+        Ok(Response::new(SeriesResponse { reservations }))
+    }
+    /// cancel the occurrences of a series
+    async fn cancel_series(
+        &self,
+        request: Request<CancelSeriesRequest>,
+    ) -> Result<Response<SeriesResponse>, Status> {
+        let request = request.into_inner();
+        let reservations = self
+            .manager
+            .cancel_series(request.series_id, request.cancelled_by, request.reason)
+            .await?;
+        Ok(Response::new(SeriesResponse { reservations }))
+    }
+    /// update the note of the occurrences of a series
+    async fn update_series(
+        &self,
+        request: Request<UpdateSeriesRequest>,
+    ) -> Result<Response<SeriesResponse>, Status> {
+        let request = request.into_inner();
+        let reservations = self
+            .manager
+            .update_series_note(request.series_id, request.note)
+            .await?;
+        Ok(Response::new(SeriesResponse { reservations }))
+    }
+    /// confirm a pending reservation
+    async fn confirm(
+        &self,
+        request: Request<ConfirmRequest>,
+    ) -> Result<Response<ConfirmResponse>, Status> {
+        let request = request.into_inner();
+        let hash = request_hash(&request);
+        let rsvp = request
+            .reservation
+            .ok_or_else(|| Status::invalid_argument("missing reservation"))?;
+        let res = self
+            .idempotent(&request.idempotency_key, "confirm", hash, || async {
+                let rsvp = self.manager.change_status(rsvp.id).await?;
+                Ok(ConfirmResponse {
+                    reservation: Some(rsvp),
+                })
+            })
+            .await?;
+        Ok(Response::new(res))
+    }
+    /// update the reservation note
+    async fn update(
+        &self,
+        request: Request<UpdateRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let request = request.into_inner();
+        let hash = request_hash(&request);
+        let rsvp = request
+            .reservation
+            .ok_or_else(|| Status::invalid_argument("missing reservation"))?;
+        // only the note is updated without a mask, as before the mask was added
+        let fields = match request.update_mask {
+            Some(mask) if !mask.paths.is_empty() => mask
+                .paths
+                .iter()
+                .map(|path| path.parse())
+                .collect::<Result<Vec<ReservationField>, _>>()
+                .map_err(Status::from)?,
+            _ => vec![ReservationField::Note],
+        };
+        let res = self
+            .idempotent(&request.idempotency_key, "update", hash, || async {
+                let rsvp = self.manager.update(rsvp, fields).await?;
+                Ok(UpdateResponse {
+                    reservation: Some(rsvp),
+                })
+            })
+            .await?;
+        Ok(Response::new(res))
+    }
+    /// cancel a reservation
+    async fn cancel(
+        &self,
+        request: Request<CancelRequest>,
+    ) -> Result<Response<CancelResponse>, Status> {
+        let request = request.into_inner();
+        let hash = request_hash(&request);
+        let res = self
+            .idempotent(&request.idempotency_key, "cancel", hash, || async {
+                let rsvp = self
+                    .manager
+                    .cancel(request.id, request.cancelled_by, request.reason)
+                    .await?;
+                #[allow(deprecated)]
+                Ok(CancelResponse {
+                    id: rsvp.id.clone(),
+                    reservation: Some(rsvp),
+                })
+            })
+            .await?;
+        Ok(Response::new(res))
+    }
+    /// move a reservation to another status
+    async fn transition(
+        &self,
+        request: Request<TransitionRequest>,
+    ) -> Result<Response<TransitionResponse>, Status> {
+        let request = request.into_inner();
+        let to = ReservationStatus::from_i32(request.status)
+            .ok_or_else(|| Status::invalid_argument("unknown reservation status"))?;
+        let rsvp = self.manager.transition(request.id, to).await?;
+        Ok(Response::new(TransitionResponse {
+            reservation: Some(rsvp),
+        }))
+    }
+    /// get a reservation
+    async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+        let rsvp = self.manager.get(request.into_inner().id).await?;
+        Ok(Response::new(GetResponse {
+            reservation: Some(rsvp),
+        }))
+    }
+
+    type queryStream = ReservationStream;
+    /// query reservations by resource id, user id, status, start time, end time,
+    /// all the matching ones are streamed regardless of the page size
+    async fn query(
+        &self,
+        request: Request<QueryRequest>,
+    ) -> Result<Response<Self::queryStream>, Status> {
+        let query = request.into_inner().into_query();
+        // the reservations are read as the client takes them, not all at once
+        let stream = self.manager.query_stream(query).await?;
+        Ok(Response::new(Box::pin(stream.map_err(Status::from))))
+    }
+    /// query a page of reservations, with the cursor of the next page
+    async fn query_page(
+        &self,
+        request: Request<QueryRequest>,
+    ) -> Result<Response<ReservationPage>, Status> {
+        let query = request.into_inner().into_query();
+        let page = self.manager.query(query).await?;
+        Ok(Response::new(page))
+    }
+
+    type listenStream = ListenStream;
+    /// another system monitor newly added/confirmed/cancelled reservations
+    async fn listen(
+        &self,
+        request: Request<ListenRequest>,
+    ) -> Result<Response<Self::listenStream>, Status> {
+        let after = request.into_inner().after_change_id;
+        let stream = self.manager.listen(after).await?;
+        Ok(Response::new(Box::pin(stream.map_err(Status::from))))
+    }
+    /// find the free time slots of the resources
+    async fn availability(
+        &self,
+        request: Request<AvailabilityRequest>,
+    ) -> Result<Response<AvailabilityResponse>, Status> {
+        let query = request
+            .into_inner()
+            .query
+            .ok_or_else(|| Status::invalid_argument("missing availability query"))?;
+        let slots = self.manager.find_free_slots(query).await?;
+        Ok(Response::new(AvailabilityResponse { slots }))
+    }
+    /// register a resource which could be reserved
+    async fn create_resource(
+        &self,
+        request: Request<CreateResourceRequest>,
+    ) -> Result<Response<CreateResourceResponse>, Status> {
+        let resource = request
+            .into_inner()
+            .resource
+            .ok_or_else(|| Status::invalid_argument("missing resource"))?;
+        let resource = self.manager.create_resource(resource).await?;
+        Ok(Response::new(CreateResourceResponse {
+            resource: Some(resource),
+        }))
+    }
+    /// update a resource
+    async fn update_resource(
+        &self,
+        request: Request<UpdateResourceRequest>,
+    ) -> Result<Response<UpdateResourceResponse>, Status> {
+        let resource = request
+            .into_inner()
+            .resource
+            .ok_or_else(|| Status::invalid_argument("missing resource"))?;
+        let resource = self.manager.update_resource(resource).await?;
+        Ok(Response::new(UpdateResourceResponse {
+            resource: Some(resource),
+        }))
+    }
+    /// archive a resource
+    async fn archive_resource(
+        &self,
+        request: Request<ArchiveResourceRequest>,
+    ) -> Result<Response<ArchiveResourceResponse>, Status> {
+        let resource = self
+            .manager
+            .archive_resource(request.into_inner().id)
+            .await?;
+        Ok(Response::new(ArchiveResourceResponse {
+            resource: Some(resource),
+        }))
+    }
+    /// list the resources
+    async fn list_resources(
+        &self,
+        request: Request<ListResourcesRequest>,
+    ) -> Result<Response<ListResourcesResponse>, Status> {
+        let resources = self
+            .manager
+            .list_resources(request.into_inner().include_archived)
+            .await?;
+        Ok(Response::new(ListResourcesResponse { resources }))
+    }
+}
+
+impl<S: Storage> RsvpService<S> {
+    /// to run the request once per idempotency key, the retries get the saved response.
+    /// the key is claimed before the request is run, and released if it fails or never finishes,
+    /// e.g. the client is gone, so it could be retried. without a key, the request is always run.
+    async fn idempotent<M, F, Fut>(
+        &self,
+        key: &str,
+        operation: &str,
+        request_hash: Vec<u8>,
+        f: F,
+    ) -> Result<M, Status>
+    where
+        M: Message + Default,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<M, abi::Error>>,
+    {
+        if key.is_empty() {
+            return Ok(f().await?);
+        }
+        match self.manager.claim(key, operation, &request_hash).await? {
+            IdempotencyClaim::Saved(saved) => M::decode(&saved[..]).map_err(invalid_saved_response),
+            IdempotencyClaim::Claimed => {
+                let claim = ClaimGuard::new(&self.manager, key, operation);
+                let res = match f().await {
+                    Ok(res) => res,
+                    Err(e) => {
+                        claim.release().await?;
+                        return Err(e.into());
+                    }
+                };
+                match self
+                    .manager
+                    .save_response(key, operation, res.encode_to_vec())
+                    .await
+                {
+                    Ok(()) => {
+                        claim.keep();
+                        Ok(res)
+                    }
+                    // the response is lost, so the retries should not wait for it
+                    Err(e) => {
+                        claim.release().await?;
+                        Err(e.into())
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// the claim of an idempotency key held by a running request.
+/// if the request is dropped before it is done, the key is released in the background.
+struct ClaimGuard<S: Storage> {
+    claim: Option<(S, String, String)>,
+}
+
+impl<S: Storage> ClaimGuard<S> {
+    fn new(manager: &S, key: &str, operation: &str) -> Self {
+        Self {
+            claim: Some((manager.clone(), key.to_owned(), operation.to_owned())),
+        }
+    }
+    /// the response is saved, the key stays claimed by it
+    fn keep(mut self) {
+        self.claim = None;
+    }
+    /// to release the key so it could be claimed again
+    async fn release(mut self) -> Result<(), abi::Error> {
+        match self.claim.take() {
+            Some((manager, key, operation)) => manager.release(&key, &operation).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S: Storage> Drop for ClaimGuard<S> {
+    fn drop(&mut self) {
+        // the claim lapses anyway if there is no runtime left to release it
+        if let (Some((manager, key, operation)), Ok(runtime)) =
+            (self.claim.take(), tokio::runtime::Handle::try_current())
+        {
+            runtime.spawn(async move {
+                let _ = manager.release(&key, &operation).await;
+            });
+        }
+    }
+}
+
+/// the same idempotency key could only be used again for the same request
+fn request_hash(request: &impl Message) -> Vec<u8> {
+    Sha256::digest(request.encode_to_vec()).to_vec()
+}
+
+fn invalid_saved_response(e: prost::DecodeError) -> Status {
+    Status::internal(format!("Invalid saved response: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi::{
+        AvailabilityQuery, Reservation, ReservationQueryBuilder, ReservationUpdateType, Resource,
+    };
+    use futures::StreamExt;
+    use prost_types::{FieldMask, Timestamp};
+    use reservation::{MemoryReservationManager, ReservationManager};
+    use sqlx::PgPool;
+    use std::time::Duration;
+    use tonic::Code;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_reserve_should_work() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        assert!(!rsvp.id.is_empty());
+        let res = service
+            .get(Request::new(GetRequest {
+                id: rsvp.id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.reservation, Some(rsvp));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_reserve_conflict_should_fail_precondition() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let mut rsvp2 = rsvp.clone();
+        rsvp2.id = String::new();
+        let status = service
+            .reserve(Request::new(ReservationRequest {
+                reservation: Some(rsvp2),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_confirm_cancel_should_work() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let res = service
+            .confirm(Request::new(ConfirmRequest {
+                reservation: Some(rsvp.clone()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            res.reservation.unwrap().status,
+            ReservationStatus::Confirmed as i32
+        );
+        let res = service
+            .cancel(Request::new(CancelRequest {
+                id: rsvp.id.clone(),
+                cancelled_by: "leon".into(),
+                reason: "change of plan".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        // the id is still set for the clients built before the reservation field
+        #[allow(deprecated)]
+        let id = res.id;
+        assert_eq!(id, rsvp.id);
+        let cancelled = res.reservation.unwrap();
+        assert_eq!(cancelled.status, ReservationStatus::Cancelled as i32);
+        assert_eq!(cancelled.cancel_reason, "change of plan");
+        let res = service
+            .get(Request::new(GetRequest { id: rsvp.id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.reservation, Some(cancelled));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_invalid_transition_should_fail_precondition() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let status = service
+            .transition(Request::new(TransitionRequest {
+                id: rsvp.id,
+                status: ReservationStatus::Completed as i32,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(
+            abi::Error::from(status),
+            abi::Error::InvalidStatusTransition {
+                from: ReservationStatus::Pending,
+                to: ReservationStatus::Completed,
+            }
+        );
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_query_should_stream_reservations() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let query = ReservationQueryBuilder::default()
+            .user_id("leon")
+            .start("2023-12-24T15:00:00-0700".parse::<Timestamp>().unwrap())
+            .end("2023-12-30T12:00:00-0700".parse::<Timestamp>().unwrap())
+            .status(ReservationStatus::Pending as i32)
+            .build()
+            .unwrap();
+        let stream = service
+            .query(Request::new(QueryRequest::from(query.clone())))
+            .await
+            .unwrap()
+            .into_inner();
+        let rsvps: Vec<_> = stream.collect().await;
+        assert_eq!(rsvps.len(), 1);
+        assert_eq!(rsvps[0].as_ref().unwrap(), &rsvp);
+        // the clients built before the query field send the filters on their own
+        #[allow(deprecated)]
+        let legacy = QueryRequest {
+            resource_id: query.resource_id.clone(),
+            user_id: query.user_id.clone(),
+            status: query.status,
+            start: query.start.clone(),
+            end: query.end.clone(),
+            ..Default::default()
+        };
+        let stream = service
+            .query(Request::new(legacy))
+            .await
+            .unwrap()
+            .into_inner();
+        let rsvps: Vec<_> = stream.collect().await;
+        assert_eq!(rsvps.len(), 1);
+        let page = service
+            .query_page(Request::new(QueryRequest::from(query)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(page.reservations, vec![rsvp]);
+        assert_eq!(page.total, 1);
+        assert!(!page.has_more);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_availability_should_return_free_slots() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let query = AvailabilityQuery::new(
+            vec![rsvp.resource_id.clone()],
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-29T12:00:00-0700".parse().unwrap(),
+            chrono::Duration::hours(1),
+        );
+        let res = service
+            .availability(Request::new(AvailabilityRequest {
+                query: Some(query.clone()),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.slots.len(), 1);
+        assert_eq!(res.slots[0].start, rsvp.end);
+        assert_eq!(res.slots[0].end, query.end);
+        let status = service
+            .availability(Request::new(AvailabilityRequest { query: None }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_update_should_follow_the_mask() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let mut moved = rsvp.clone();
+        moved.start = Some("2024-01-01T09:00:00+0000".parse::<Timestamp>().unwrap());
+        moved.end = Some("2024-01-01T10:00:00+0000".parse::<Timestamp>().unwrap());
+        moved.note = "moved".into();
+        let request = |paths: &[&str]| {
+            Request::new(UpdateRequest {
+                reservation: Some(moved.clone()),
+                update_mask: Some(FieldMask {
+                    paths: paths.iter().map(|p| p.to_string()).collect(),
+                }),
+                ..Default::default()
+            })
+        };
+        let status = service.update(request(&["status"])).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let updated = service
+            .update(request(&["start", "end"]))
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+        assert_eq!(updated.start, moved.start);
+        assert_eq!(updated.note, rsvp.note);
+        let status = service.update(request(&["note"])).await.unwrap_err();
+        assert_eq!(status.code(), Code::Aborted);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_reserve_retried_with_key_should_return_the_original() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let mut retry = rsvp.clone();
+        retry.id = String::new();
+        retry.start = Some("2024-01-01T09:00:00+0000".parse::<Timestamp>().unwrap());
+        retry.end = Some("2024-01-01T10:00:00+0000".parse::<Timestamp>().unwrap());
+        let request = || {
+            Request::new(ReservationRequest {
+                reservation: Some(retry.clone()),
+                idempotency_key: "leon-2024-01-01".into(),
+            })
+        };
+        let first = service.reserve(request()).await.unwrap().into_inner();
+        let second = service.reserve(request()).await.unwrap().into_inner();
+        assert_eq!(first, second);
+        // the key could not be reused for another request
+        let mut another = retry.clone();
+        another.note = "another note".into();
+        let status = service
+            .reserve(Request::new(ReservationRequest {
+                reservation: Some(another),
+                idempotency_key: "leon-2024-01-01".into(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            abi::Error::from(status),
+            abi::Error::IdempotencyKeyReused("leon-2024-01-01".into())
+        );
+        // the same key of another operation is independent
+        let confirmed = service
+            .confirm(Request::new(ConfirmRequest {
+                reservation: first.reservation.clone(),
+                idempotency_key: "leon-2024-01-01".into(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            confirmed.reservation.unwrap().status,
+            ReservationStatus::Confirmed as i32
+        );
+        // without the key, the retry conflicts with the first one
+        let status = service
+            .reserve(Request::new(ReservationRequest {
+                reservation: Some(retry.clone()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        let status = service
+            .reserve(Request::new(ReservationRequest {
+                reservation: Some(retry),
+                idempotency_key: "k".repeat(129),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_concurrent_retries_with_key_should_reserve_once() {
+        let service = RsvpService::new(ReservationManager::new(migrated_pool.clone()));
+        // nothing conflicts within the capacity, only the key stops the other attempts
+        service
+            .create_resource(Request::new(CreateResourceRequest {
+                resource: Some(Resource::new("meeting-room-1", "Meeting Room", 5)),
+            }))
+            .await
+            .unwrap();
+        let rsvp = Reservation::new_pending(
+            "leon",
+            "meeting-room-1",
+            "2024-01-01T09:00:00+0000".parse().unwrap(),
+            "2024-01-01T10:00:00+0000".parse().unwrap(),
+            "standup",
+        );
+        let request = || {
+            Request::new(ReservationRequest {
+                reservation: Some(rsvp.clone()),
+                idempotency_key: "leon-standup".into(),
+            })
+        };
+        let results = futures::future::join_all((0..4).map(|_| service.reserve(request()))).await;
+        let mut reserved = Vec::new();
+        for result in results {
+            match result {
+                Ok(res) => reserved.push(res.into_inner()),
+                Err(status) => assert_eq!(status.code(), Code::Aborted),
+            }
+        }
+        assert!(!reserved.is_empty());
+        assert!(reserved.windows(2).all(|pair| pair[0] == pair[1]));
+        let count: i64 = sqlx::query_scalar("SELECT count(*) FROM rsvp.reservations")
+            .fetch_one(&migrated_pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+    #[tokio::test]
+    async fn idempotent_request_dropped_midway_should_release_the_key() {
+        let service = RsvpService::new(MemoryReservationManager::new());
+        let hash = b"request".to_vec();
+        // the client is gone while the request is running
+        let dropped = service.idempotent("key", "reserve", hash.clone(), || async {
+            futures::future::pending::<Result<ConfirmResponse, abi::Error>>().await
+        });
+        assert!(tokio::time::timeout(Duration::from_millis(50), dropped)
+            .await
+            .is_err());
+        // the key is released in the background
+        let mut retried = None;
+        for _ in 0..50 {
+            let res = service
+                .idempotent("key", "reserve", hash.clone(), || async {
+                    Ok(ConfirmResponse::default())
+                })
+                .await;
+            match res {
+                Err(status) if status.code() == Code::Aborted => {
+                    tokio::time::sleep(Duration::from_millis(10)).await
+                }
+                res => {
+                    retried = Some(res);
+                    break;
+                }
+            }
+        }
+        assert_eq!(
+            retried.expect("the key should be released").unwrap(),
+            ConfirmResponse::default()
+        );
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_reserve_batch_conflict_should_report_every_item() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let mut free = rsvp.clone();
+        free.id = String::new();
+        free.start = Some("2024-01-01T09:00:00+0000".parse::<Timestamp>().unwrap());
+        free.end = Some("2024-01-01T10:00:00+0000".parse::<Timestamp>().unwrap());
+        let mut taken = rsvp.clone();
+        taken.id = String::new();
+        let status = service
+            .reserve_batch(Request::new(ReserveBatchRequest {
+                reservations: vec![free.clone(), taken.clone(), taken],
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        match abi::Error::from(status) {
+            abi::Error::ConflictingBatch(conflicts) => {
+                let indexes: Vec<_> = conflicts.iter().map(|c| c.index).collect();
+                assert_eq!(indexes, vec![1, 2]);
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let res = service
+            .reserve_batch(Request::new(ReserveBatchRequest {
+                reservations: vec![free],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.reservations.len(), 1);
+        assert!(!res.reservations[0].id.is_empty());
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_reserve_series_should_work() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let series = Reservation::new_pending(
+            "alice",
+            rsvp.resource_id,
+            "2024-01-01T09:00:00+0000".parse().unwrap(),
+            "2024-01-01T10:00:00+0000".parse().unwrap(),
+            "daily standup",
+        )
+        .with_recurrence("FREQ=DAILY;COUNT=5");
+        let res = service
+            .reserve_series(Request::new(ReservationRequest {
+                reservation: Some(series),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.reservations.len(), 5);
+        let series_id = res.reservations[0].series_id.clone();
+        let got = service
+            .get_series(Request::new(GetSeriesRequest {
+                series_id: series_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(got.reservations, res.reservations);
+        let status = service
+            .get_series(Request::new(GetSeriesRequest {
+                series_id: "not-a-uuid".into(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+    #[tokio::test]
+    async fn rpc_should_work_on_memory_storage() {
+        let service = RsvpService::new(MemoryReservationManager::new());
+        service
+            .create_resource(Request::new(CreateResourceRequest {
+                resource: Some(Resource::new("ocean-view-room-777", "Ocean View", 1)),
+            }))
+            .await
+            .unwrap();
+        let rsvp = Reservation::new_pending(
+            "leon",
+            "ocean-view-room-777",
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "test reservation",
+        );
+        let request = || {
+            Request::new(ReservationRequest {
+                reservation: Some(rsvp.clone()),
+                idempotency_key: "leon-2023-12-25".into(),
+            })
+        };
+        let first = service.reserve(request()).await.unwrap().into_inner();
+        let second = service.reserve(request()).await.unwrap().into_inner();
+        assert_eq!(first, second);
+        let status = service
+            .reserve(Request::new(ReservationRequest {
+                reservation: Some(rsvp),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_listen_should_stream_changes() {
+        let service = RsvpService::new(ReservationManager::new(migrated_pool.clone()));
+        let mut stream = service
+            .listen(Request::new(ListenRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        let (_service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let change = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.op, ReservationUpdateType::Create as i32);
+        assert_eq!(change.reservation, Some(rsvp));
+    }
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn rpc_reserve_archived_resource_should_be_not_found() {
+        let (service, rsvp) = make_reservation_for_leon(migrated_pool.clone()).await;
+        let res = service
+            .archive_resource(Request::new(ArchiveResourceRequest {
+                id: rsvp.resource_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(res.resource.unwrap().archived_at.is_some());
+        let res = service
+            .list_resources(Request::new(ListResourcesRequest {
+                include_archived: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(res.resources.is_empty());
+        let status = service
+            .reserve(Request::new(ReservationRequest {
+                reservation: Some(rsvp),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(
+            abi::Error::from(status),
+            abi::Error::ResourceNotFound("ocean-view-room-777".into())
+        );
+    }
+    async fn make_reservation_for_leon(pool: PgPool) -> (RsvpService, Reservation) {
+        let service = RsvpService::new(ReservationManager::new(pool));
+        service
+            .create_resource(Request::new(CreateResourceRequest {
+                resource: Some(Resource::new("ocean-view-room-777", "Ocean View", 1)),
+            }))
+            .await
+            .unwrap();
+        let rsvp = Reservation::new_pending(
+            "leon",
+            "ocean-view-room-777",
+            "2023-12-25T15:00:00-0700".parse().unwrap(),
+            "2023-12-28T12:00:00-0700".parse().unwrap(),
+            "test reservation",
+        );
+        let res = service
+            .reserve(Request::new(ReservationRequest {
+                reservation: Some(rsvp),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        (service, res.reservation.unwrap())
+    }
+}